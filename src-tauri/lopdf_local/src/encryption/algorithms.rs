@@ -819,6 +819,20 @@ fn authenticate_owner_password_r4<O>(
         doc: &Document,
         owner_password: O,
     ) -> Result<(), DecryptionError>
+    where
+        O: AsRef<[u8]>,
+    {
+        let user_password = self.user_password_from_owner_r4(owner_password)?;
+
+        // The result of the previous step purports to be the user password. Authenticate this user
+        // password using Algorithm 5. If it is correct, the password supplied is the correct owner
+        // password.
+        self.authenticate_user_password_r4(doc, &user_password)
+    }
+
+    /// Recover the user password from the owner password (revision 4 or earlier), as the first
+    /// steps of Algorithm 7. The result is only meaningful if the owner password is correct.
+    fn user_password_from_owner_r4<O>(&self, owner_password: O) -> Result<Vec<u8>, DecryptionError>
     where
         O: AsRef<[u8]>,
     {
@@ -896,10 +910,7 @@ fn authenticate_owner_password_r4<O>(
         // encryption key.
         result = Rc4::new(&hash[..n]).decrypt(&result);
 
-        // The result of the previous step purports to be the user password. Authenticate this user
-        // password using Algorithm 5. If it is correct, the password supplied is the correct owner
-        // password.
-        self.authenticate_user_password_r4(doc, &result)
+        Ok(result)
     }
 
     /// Compute the encryption dictionary's U-entry value (revision 6).
@@ -1205,7 +1216,17 @@ pub fn compute_file_encryption_key<P>(
         P: AsRef<[u8]>,
     {
         match self.revision {
-            2..=4 => self.compute_file_encryption_key_r4(doc, password),
+            2..=4 => {
+                // The file encryption key derives from the user password, so an owner password
+                // has to be turned into the user password first.
+                if self.authenticate_user_password_r4(doc, &password).is_err()
+                    && self.authenticate_owner_password_r4(doc, &password).is_ok()
+                {
+                    let user_password = self.user_password_from_owner_r4(&password)?;
+                    return self.compute_file_encryption_key_r4(doc, user_password);
+                }
+                self.compute_file_encryption_key_r4(doc, password)
+            }
             5..=6 => self.compute_file_encryption_key_r6(password),
             _ => Err(DecryptionError::UnsupportedRevision),
         }
@@ -9,6 +9,7 @@ use std::path::{Path, PathBuf};
 use tauri::{Emitter, Manager};
 use thiserror::Error;
 use memmap2::Mmap;
+use rayon::prelude::*;
 use std::io::{Read, Seek, SeekFrom};
 
 // --- Error Handling ---
@@ -23,6 +24,11 @@ pub enum AppError {
     Validation(String),
     #[error("Path error: {0}")]
     Path(String),
+    #[error("Unsupported feature: this build was compiled without {0} support")]
+    UnsupportedFeature(String),
+    /// The frontend cancelled the operation through `cancel_operation`.
+    #[error("Operation cancelled")]
+    Cancelled,
 }
 
 // Serialize error as a simple string for the frontend
@@ -43,6 +49,10 @@ pub type AppResult<T> = Result<T, AppError>;
 pub struct FileEntry {
     pub path: String,
     pub name: String,
+    /// Whether the PDF is encrypted, when the listing was asked to check.
+    /// `None` for other files and for PDFs whose trailer couldn't be found.
+    #[serde(default)]
+    pub encrypted: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -50,6 +60,11 @@ pub struct PreviewResult {
     pub preview_names: Vec<String>,
     pub placeholder_found: bool,
     pub overwrite_warnings: Vec<String>,
+    /// Target paths that two or more files in the batch would be renamed to.
+    pub internal_collisions: Vec<String>,
+    /// Template tokens that will be expanded (`###`, `{version}`, `{date}`,
+    /// `{original}`), in the order they appear.
+    pub tokens: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -62,19 +77,274 @@ pub struct RenameFailure {
 pub struct RenameResult {
     pub renamed: u32,
     pub failed: Vec<RenameFailure>,
+    /// A rename failed partway and the completed ones were undone, leaving
+    /// every file under its original name.
+    pub rolled_back: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "mode", rename_all = "snake_case")]
 pub enum SplitMode {
-    EveryN { n: u32 },
+    /// Parts of `n` pages; with `overlap`, each part after the first repeats
+    /// the last `overlap` pages of the one before it.
+    EveryN {
+        n: u32,
+        #[serde(default)]
+        overlap: u32,
+    },
     OnePerPage,
+    /// Parts sized by `sizes` in turn (e.g. `[2, 3]` for alternating two- and
+    /// three-page records). With `repeat` the sizes cycle to the end of the
+    /// document; otherwise pages past the sequence form one final part.
+    Pattern { sizes: Vec<u32>, repeat: bool },
+    /// Parts of at most `max_bytes` each. A page that is over the
+    /// limit on its own still gets a part of its own.
+    #[serde(alias = "max_bytes")]
+    BySize {
+        #[serde(alias = "limit")]
+        max_bytes: u64,
+    },
+    /// One part per listed range (`"1-3"`, `"7"`), in the order given. Ranges
+    /// may overlap; an entry may also hold several comma-separated ranges.
+    Ranges { ranges: Vec<String> },
+    /// Starts a new part at each top-level bookmark's page. Parts are named
+    /// after the bookmark title; pages before the first bookmark form a part
+    /// of their own.
+    Bookmarks,
+    /// Starts a new part at every page carrying a QR code (a scanner's
+    /// separator sheet). Parts are named after the code's value.
+    ByBarcodeSeparator { remove_separators: bool },
+}
+
+/// One planned split output: an inclusive page range and, for modes that
+/// name their parts, the name to use in place of `_partN`.
+#[derive(Debug, Clone, PartialEq)]
+struct SplitPart {
+    start: u32,
+    end: u32,
+    label: Option<String>,
+    /// The finished part, when planning had to build it to measure it (see
+    /// `SplitMode::BySize`); writing it then reuses these bytes.
+    prepared: Option<PreparedPart>,
+}
+
+/// A split part extracted, finished and serialized, ready to be written.
+#[derive(Debug, Clone, PartialEq)]
+struct PreparedPart {
+    bytes: Vec<u8>,
+    /// Links removed because they led to pages outside the part.
+    dropped_links: usize,
+}
+
+/// How each split part is finished before it is written. Size-capped
+/// planning measures parts after the same steps, so a part comes out the size
+/// it was planned at.
+#[derive(Debug, Clone)]
+struct PartOptions {
+    copy_metadata: bool,
+    number_titles: bool,
+    target_version: Option<String>,
+}
+
+impl Default for PartOptions {
+    fn default() -> Self {
+        PartOptions {
+            copy_metadata: true,
+            number_titles: false,
+            target_version: None,
+        }
+    }
+}
+
+impl PartOptions {
+    /// Extracts `part`, the `number`th (from 1), finishes and serializes it.
+    fn prepare(
+        &self,
+        doc: &Document,
+        pages: &std::collections::BTreeMap<u32, lopdf::ObjectId>,
+        part: &SplitPart,
+        number: usize,
+    ) -> AppResult<PreparedPart> {
+        let page_numbers: Vec<u32> = (part.start..=part.end).collect();
+        let (mut part_doc, dropped_links) = extract_page_list(doc, pages, &page_numbers)?;
+        set_part_metadata(&mut part_doc, self.copy_metadata, self.number_titles.then_some(number))?;
+        apply_target_version(&mut part_doc, self.target_version.as_deref())?;
+        Ok(PreparedPart {
+            bytes: save_pdf_to_vec(&mut part_doc)?,
+            dropped_links,
+        })
+    }
+
+    /// `part` ready to write: the bytes planning built, or built now.
+    fn prepared<'a>(
+        &self,
+        doc: &Document,
+        pages: &std::collections::BTreeMap<u32, lopdf::ObjectId>,
+        part: &'a SplitPart,
+        number: usize,
+    ) -> AppResult<std::borrow::Cow<'a, PreparedPart>> {
+        Ok(match &part.prepared {
+            Some(prepared) => std::borrow::Cow::Borrowed(prepared),
+            None => std::borrow::Cow::Owned(self.prepare(doc, pages, part, number)?),
+        })
+    }
+}
+
+/// A page selection as typed by the user (`"1-3,5,9-"`) or as explicit
+/// inclusive ranges.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PageSelection {
+    Spec(String),
+    Ranges(Vec<(u32, u32)>),
+}
+
+impl PageSelection {
+    /// Resolves the selection to page numbers in the order given. Pages named
+    /// more than once are repeated.
+    fn page_numbers(&self, page_count: u32) -> AppResult<Vec<u32>> {
+        match self {
+            PageSelection::Spec(spec) => parse_page_ranges(spec, page_count),
+            PageSelection::Ranges(ranges) => {
+                if ranges.is_empty() {
+                    return Err(AppError::Validation("No pages selected.".to_string()));
+                }
+                let mut pages = Vec::new();
+                for &(start, end) in ranges {
+                    check_page_range(start, end, page_count)?;
+                    pages.extend(start..=end);
+                }
+                Ok(pages)
+            }
+        }
+    }
+}
+
+/// How to order input files before an operation that depends on order.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// File name, byte-wise.
+    Name,
+    /// File name with digit runs compared as numbers (`page2` before `page10`).
+    Natural,
+    /// File size, smallest first.
+    Size,
+}
+
+/// How `rotate_pdf_pages` applies an angle to a page.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationMode {
+    /// Added to the page's current /Rotate; any multiple of 90.
+    #[default]
+    Relative,
+    /// Replaces /Rotate; one of 0, 90, 180 or 270.
+    Absolute,
+}
+
+/// Cipher used by `protect_pdf`. AES-256 unless asked otherwise; RC4 is only
+/// there for readers too old to open anything else.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum EncryptionAlgorithm {
+    /// 128-bit RC4 (V2), PDF 1.4.
+    #[serde(rename = "rc4_128")]
+    Rc4_128,
+    /// 128-bit AES (V4), PDF 1.6.
+    #[serde(rename = "aes_128")]
+    Aes128,
+    /// 256-bit AES (V5, revision 6), PDF 1.7 extension level 8 and PDF 2.0.
+    #[default]
+    #[serde(rename = "aes_256")]
+    Aes256,
+}
+
+impl EncryptionAlgorithm {
+    /// Earliest PDF version that defines the cipher.
+    fn min_version(self) -> (u32, u32) {
+        match self {
+            EncryptionAlgorithm::Rc4_128 => (1, 4),
+            EncryptionAlgorithm::Aes128 => (1, 6),
+            EncryptionAlgorithm::Aes256 => (1, 7),
+        }
+    }
+}
+
+/// What a reader without the owner password may do with a protected PDF.
+/// Omitted fields are allowed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PdfPermissions {
+    pub allow_print: bool,
+    /// Print at full quality; with only `allow_print`, printing is degraded.
+    pub allow_print_high_res: bool,
+    pub allow_copy: bool,
+    /// Change content other than annotations, form fields and page assembly.
+    pub allow_modify: bool,
+    pub allow_annotate: bool,
+    pub allow_fill_forms: bool,
+    /// Extract text for screen readers, even when copying is blocked.
+    pub allow_extract_accessibility: bool,
+    /// Insert, rotate and delete pages, and add bookmarks.
+    pub allow_assemble: bool,
+}
+
+impl Default for PdfPermissions {
+    fn default() -> Self {
+        PdfPermissions {
+            allow_print: true,
+            allow_print_high_res: true,
+            allow_copy: true,
+            allow_modify: true,
+            allow_annotate: true,
+            allow_fill_forms: true,
+            allow_extract_accessibility: true,
+            allow_assemble: true,
+        }
+    }
+}
+
+impl PdfPermissions {
+    fn to_lopdf(&self) -> lopdf::encryption::Permissions {
+        use lopdf::encryption::Permissions;
+        let mut permissions = Permissions::empty();
+        for (allowed, flag) in [
+            (self.allow_print, Permissions::PRINTABLE),
+            (self.allow_print_high_res, Permissions::PRINTABLE_IN_HIGH_QUALITY),
+            (self.allow_copy, Permissions::COPYABLE),
+            (self.allow_modify, Permissions::MODIFIABLE),
+            (self.allow_annotate, Permissions::ANNOTABLE),
+            (self.allow_fill_forms, Permissions::FILLABLE),
+            (self.allow_extract_accessibility, Permissions::COPYABLE_FOR_ACCESSIBILITY),
+            (self.allow_assemble, Permissions::ASSEMBLABLE),
+        ] {
+            permissions.set(flag, allowed);
+        }
+        permissions
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SplitPreviewItem {
     pub output_name: String,
     pub page_range: String,
+    /// Serialized size of the part in bytes; only estimated for size-capped
+    /// splits, where it is measured anyway.
+    pub estimated_size: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitOutput {
+    pub path: String,
+    /// Size of the written file in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SplitResult {
+    pub parts: Vec<SplitOutput>,
+    /// Non-fatal caveats about the output, for display.
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -107,6 +377,56 @@ pub struct CompressionResult {
     pub original_size: u64,
     pub compressed_size: u64,
     pub success: bool,
+    /// Output size as a fraction of the input size (below 1.0 means smaller).
+    pub ratio: f32,
+    /// Compression produced a larger file than the original.
+    pub grew: bool,
+    /// The original bytes were written to the output instead of the
+    /// compressed ones, because they were smaller.
+    pub kept_original: bool,
+    /// The input was tagged and its structure tree was removed.
+    pub accessibility_affected: bool,
+    /// Images re-encoded (and possibly downsampled) in the output.
+    pub images_recompressed: u32,
+    /// Estimated savings of each enabled step, in the order they ran.
+    pub breakdown: Vec<StepSavings>,
+    /// Non-fatal caveats about the output, for display.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DpiSummary {
+    pub min: u32,
+    pub typical: u32,
+    pub max: u32,
+}
+
+/// Bytes one compression step saved: the objects it made unreachable, or for
+/// `images` the difference between old and new image streams. Estimates, since
+/// the final file is written (and compressed) in one go.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepSavings {
+    /// `images`, `metadata`, `thumbnails`, `application_data`,
+    /// `structure_tree`, `form_fields` or `annotations`.
+    pub step: String,
+    pub bytes_saved: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MergeResult {
+    pub output_path: String,
+    /// Each source path with the number of pages it contributed, in merge order.
+    pub page_counts: Vec<(String, u32)>,
+    /// Total pages in the merged document, cover and back matter included.
+    pub page_count: u32,
+    /// Sources declared different /OutputIntents (colour profiles). The merged
+    /// file keeps the first one, so colour management may be inconsistent.
+    pub output_intents_conflict: bool,
+    /// Non-fatal caveats about the output, for display.
+    pub warnings: Vec<String>,
+    /// Form fields renamed to keep them independent, as (original, new)
+    /// fully qualified names.
+    pub renamed_fields: Vec<(String, String)>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -118,7 +438,13 @@ pub struct PdfDiagnosticResult {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PdfProperties {
+    /// The effective version: the later of the header and the catalog /Version.
     pub version: String,
+    /// The version in the `%PDF-x.y` header.
+    pub header_version: String,
+    /// The catalog /Version override, which incremental updates use to raise
+    /// the version without rewriting the header.
+    pub catalog_version: Option<String>,
     pub page_count: u32,
     pub page_size: String,
     pub metadata: std::collections::HashMap<String, String>,
@@ -128,11 +454,30 @@ pub struct PdfProperties {
     pub producer: String,
     pub creator: String,
     pub fonts: Vec<String>,
+    /// Effective resolution of each placed image, ascending. An image drawn
+    /// more than once counts at its highest-resolution placement.
     pub image_dpi: Vec<u32>,
+    /// Lowest, median and highest of `image_dpi`; None without placed images.
+    pub image_dpi_summary: Option<DpiSummary>,
     pub doc_dpi: u32,
     pub colorspace: String,
     pub page_width: f32,
     pub page_height: f32,
+    /// The catalog /Lang entry (a BCP 47 tag such as `en-GB`), if set.
+    pub language: Option<String>,
+    /// The displayed label of each page from /PageLabels; empty when the
+    /// document uses plain page numbers.
+    pub page_labels: Vec<String>,
+}
+
+/// What this build can do, so the frontend can hide tools whose backend was
+/// compiled out instead of calling them and getting `UnsupportedFeature`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub version: String,
+    pub render: bool,
+    pub barcode: bool,
+    pub ocr: bool,
 }
 
 // --- Virtual Repair Reader for large/malformed PDFs ---
@@ -192,24 +537,41 @@ impl<'a> Seek for SeekingChain<'a> {
 
 // --- Helpers ---
 
+const PDF_HEADER: &[u8] = b"%PDF-";
+
 fn load_pdf<P: AsRef<Path>>(path: P) -> AppResult<Document> {
-    let file = fs::File::open(path)?;
+    let file = fs::File::open(resolve_path(path))?;
+    // Mapping a zero-length file fails with an unhelpful OS error.
+    if file.metadata()?.len() < PDF_HEADER.len() as u64 {
+        return Err(AppError::Validation("Not a PDF or empty file".to_string()));
+    }
     // SAFETY: Memory mapping is unsafe because the OS delivers SIGBUS if the file
     // is truncated by another process while mapped. In our single-user desktop app
     // context this is an acceptable risk — users don't typically modify the same PDF
     // from two apps simultaneously. On networked/FUSE filesystems this could crash.
     let mmap = unsafe { Mmap::map(&file)? };
-    
+    load_pdf_mem(&mmap)
+}
+
+/// Parses a PDF held in memory, falling back to the virtual trailer repair.
+/// Shared by file loads (over the mmap) and the byte-in/byte-out commands.
+fn load_pdf_mem(data: &[u8]) -> AppResult<Document> {
+    // Readers tolerate a little junk before the header, so search the first KB.
+    let head = &data[..data.len().min(1024)];
+    if !head.windows(PDF_HEADER.len()).any(|w| w == PDF_HEADER) {
+        return Err(AppError::Validation("Not a PDF or empty file".to_string()));
+    }
+
     // 1. Try standard load from memory
-    match Document::load_mem(&mmap) {
+    match Document::load_mem(data) {
         Ok(doc) => Ok(doc),
         Err(e) => {
             // 2. If it fails, try the "Virtual Repair" for giant/malformed files.
             // Some giant PDFs (>4GB) have trailers that lopdf has trouble parsing due to lack of whitespace
             // or 32-bit truncation in various places. We "inject" a clean trailer in memory.
-            if let Some(offset) = find_start_xref(&mmap) {
+            if let Some(offset) = find_start_xref(data) {
                 let patch = format!("\n\nstartxref\n{}\n%%EOF", offset).into_bytes();
-                let mut reader = SeekingChain::new(&mmap, patch);
+                let mut reader = SeekingChain::new(data, patch);
                 match Document::load_from(&mut reader) {
                     Ok(doc) => Ok(doc),
                     Err(repair_err) => {
@@ -225,76 +587,544 @@ fn load_pdf<P: AsRef<Path>>(path: P) -> AppResult<Document> {
     }
 }
 
+const PDF_VERSIONS: &[&str] = &["1.0", "1.1", "1.2", "1.3", "1.4", "1.5", "1.6", "1.7", "2.0"];
+
+/// Sets the version the document is saved as, for commands that let the user
+/// pick one. Cross-reference streams arrived in 1.5, so older targets are
+/// written with a classic xref table, and a catalog /Version that would
+/// override the header is dropped.
+fn apply_target_version(doc: &mut Document, target_version: Option<&str>) -> AppResult<()> {
+    let Some(version) = target_version.map(str::trim) else {
+        return Ok(());
+    };
+    if !PDF_VERSIONS.contains(&version) {
+        return Err(AppError::Validation(format!(
+            "Unknown PDF version '{}' (expected one of {}).",
+            version,
+            PDF_VERSIONS.join(", ")
+        )));
+    }
+    doc.version = version.to_string();
+    if version_key(version) < (1, 5) {
+        doc.reference_table.cross_reference_type = lopdf::xref::XrefType::CrossReferenceTable;
+    }
+    if catalog_version(doc).is_some_and(|v| version_key(&v) > version_key(version)) {
+        if let Ok(catalog) = doc.catalog_mut() {
+            catalog.remove(b"Version");
+        }
+    }
+    Ok(())
+}
+
+/// Serializes a document into memory instead of to a file.
+fn save_pdf_to_vec(doc: &mut Document) -> AppResult<Vec<u8>> {
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// How far back from the final `%%EOF` we search for `startxref`. Some producers
+/// pad the tail with long comment blocks or whitespace, so this is generous.
+const STARTXREF_LOOKBACK: usize = 2048;
+
 fn find_start_xref(data: &[u8]) -> Option<u64> {
     // Find last %%EOF
     let eof_marker = b"%%EOF";
     let eof_pos = data.windows(5).rposition(|w| w == eof_marker)?;
     
-    // Look back from %%EOF for startxref (up to 128 bytes)
-    let lookback = if eof_pos > 128 { eof_pos - 128 } else { 0 };
+    // Look back from %%EOF for startxref
+    let lookback = eof_pos.saturating_sub(STARTXREF_LOOKBACK);
     let search_zone = &data[lookback..eof_pos];
     let startxref_marker = b"startxref";
     let s_pos = search_zone.windows(9).rposition(|w| w == startxref_marker)?;
     
-    // Extract digits between startxref and %%EOF
+    // Extract the offset between startxref and %%EOF. Skip whitespace and comment
+    // lines, then take the first contiguous run of digits; anything after it
+    // (stray bytes, trailing comments) is ignored rather than glued onto the number.
     let digit_start = lookback + s_pos + 9;
     let digit_zone = &data[digit_start..eof_pos];
-    let mut offset_str = String::new();
-    for &b in digit_zone {
-        if b.is_ascii_digit() {
-            offset_str.push(b as char);
+    let mut i = 0;
+    while i < digit_zone.len() {
+        match digit_zone[i] {
+            b'%' => {
+                while i < digit_zone.len() && digit_zone[i] != b'\n' && digit_zone[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b if b.is_ascii_digit() => break,
+            _ => i += 1,
         }
     }
+    let digits: String = digit_zone[i..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .map(|&b| b as char)
+        .collect();
     
-    offset_str.parse::<u64>().ok()
+    digits.parse::<u64>().ok()
+}
+
+/// Probes that `dir` accepts new files by creating and deleting a scratch file.
+/// Run before expensive loads so read-only or full volumes fail fast.
+fn ensure_dir_writable(dir: &Path) -> AppResult<()> {
+    let probe = dir.join(format!(".paradise-write-test-{}", std::process::id()));
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|e| AppError::Path(format!("Output directory is not writable: {} ({})", dir.display(), e)))?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Checks that the directory an output file will be written into is writable.
+fn ensure_output_writable(output_path: &str) -> AppResult<()> {
+    let output = resolve_path(output_path);
+    let parent = match output.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if !parent.is_dir() {
+        return Err(AppError::Path(format!("Output directory does not exist: {}", parent.display())));
+    }
+    ensure_dir_writable(parent)
+}
+
+/// Prefix marking a path that is not valid UTF-8 and was encoded for the
+/// frontend by `path_to_string`.
+const OPAQUE_PATH_PREFIX: &str = "paradise-path:";
+
+/// Converts a path to the string handed to the frontend. UTF-8 paths pass
+/// through unchanged. Anything else becomes `paradise-path:<base64>~<tail>`:
+/// the base64 holds the raw OS bytes, and the tail is the extension, kept
+/// readable so the UI can still derive names like `x_compressed.pdf` by string
+/// replacement. `resolve_path` reverses this.
+fn path_to_string(path: &Path) -> String {
+    if let Some(s) = path.to_str() {
+        return s.to_string();
+    }
+    let ext = path.extension().and_then(|e| e.to_str());
+    let base = match ext {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    };
+    format!(
+        "{}{}~{}",
+        OPAQUE_PATH_PREFIX,
+        base64_encode(&os_str_bytes(base.as_os_str())),
+        ext.map(|e| format!(".{}", e)).unwrap_or_default()
+    )
+}
+
+/// Turns a path string from the frontend back into a real path, decoding the
+/// opaque form produced by `path_to_string`.
+fn resolve_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    let decoded = path
+        .to_str()
+        .and_then(|s| s.strip_prefix(OPAQUE_PATH_PREFIX))
+        .and_then(|token| token.split_once('~'))
+        .and_then(|(encoded, tail)| {
+            let mut os = os_string_from_bytes(base64_decode(encoded)?);
+            os.push(tail);
+            Some(PathBuf::from(os))
+        });
+    decoded.unwrap_or_else(|| path.to_path_buf())
+}
+
+#[cfg(unix)]
+fn os_str_bytes(s: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    s.as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: Vec<u8>) -> std::ffi::OsString {
+    use std::os::unix::ffi::OsStringExt;
+    std::ffi::OsString::from_vec(bytes)
+}
+
+// Windows paths are UTF-16 (possibly with unpaired surrogates); store the code
+// units little-endian.
+#[cfg(windows)]
+fn os_str_bytes(s: &std::ffi::OsStr) -> Vec<u8> {
+    use std::os::windows::ffi::OsStrExt;
+    s.encode_wide().flat_map(u16::to_le_bytes).collect()
+}
+
+#[cfg(windows)]
+fn os_string_from_bytes(bytes: Vec<u8>) -> std::ffi::OsString {
+    use std::os::windows::ffi::OsStringExt;
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+    std::ffi::OsString::from_wide(&units)
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        out.push(BASE64_ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(BASE64_ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 { BASE64_ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+        out.push(if chunk.len() > 2 { BASE64_ALPHABET[n as usize & 63] as char } else { '=' });
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().take_while(|&c| c != b'=') {
+        let value = BASE64_ALPHABET.iter().position(|&a| a == c)? as u32;
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// Whether `a` and `b` name the same existing file, whatever their spelling.
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Guards a single-file output against clobbering: refuses to write over one
+/// of the inputs (which would be read mid-write), and over any other existing
+/// file unless `overwrite` is set.
+fn ensure_output_target(output_path: &str, inputs: &[String], overwrite: bool) -> AppResult<()> {
+    let output = resolve_path(output_path);
+    if !output.exists() {
+        return Ok(());
+    }
+    let output_canonical = fs::canonicalize(&output)?;
+    for input in inputs {
+        if fs::canonicalize(resolve_path(input)).is_ok_and(|p| p == output_canonical) {
+            return Err(AppError::Validation(format!(
+                "Output file is also one of the inputs: {}",
+                output.display()
+            )));
+        }
+    }
+    if !overwrite {
+        return Err(AppError::Validation(format!("Output file already exists: {}", output.display())));
+    }
+    Ok(())
+}
+
+/// Picks an output path in `dir` for `file_name` that neither exists on disk
+/// nor was already handed out in this batch, suffixing the stem (`name_2.pdf`,
+/// `name_3.pdf`, ...) as needed. The chosen path is recorded in `taken`.
+fn unique_output_path(
+    dir: &Path,
+    file_name: &std::ffi::OsStr,
+    taken: &mut std::collections::HashSet<PathBuf>,
+) -> PathBuf {
+    let name = Path::new(file_name);
+    let stem = name.file_stem().unwrap_or(file_name);
+
+    let mut candidate = dir.join(file_name);
+    let mut n = 2;
+    while candidate.exists() || taken.contains(&candidate) {
+        let mut numbered = stem.to_os_string();
+        numbered.push(format!("_{}", n));
+        if let Some(ext) = name.extension() {
+            numbered.push(".");
+            numbered.push(ext);
+        }
+        candidate = dir.join(numbered);
+        n += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+/// The deepest directory containing every path, if they share one.
+fn common_parent(paths: &[String]) -> Option<PathBuf> {
+    let mut parents = paths
+        .iter()
+        .map(|p| resolve_path(p).parent().map(Path::to_path_buf).unwrap_or_default());
+    let mut common = parents.next()?;
+    for parent in parents {
+        while !parent.starts_with(&common) {
+            if !common.pop() {
+                return None;
+            }
+        }
+    }
+    Some(common)
+}
+
+/// Sorts paths in place by `key`, comparing file names rather than full
+/// paths. Ties keep their input order.
+fn sort_paths(paths: &mut [String], key: SortKey) -> AppResult<()> {
+    let file_name = |p: &String| {
+        resolve_path(p)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+    match key {
+        SortKey::Name => paths.sort_by_key(file_name),
+        SortKey::Natural => paths.sort_by(|a, b| natural_cmp(&file_name(a), &file_name(b))),
+        SortKey::Size => {
+            let mut sized = Vec::with_capacity(paths.len());
+            for p in paths.iter() {
+                sized.push((fs::metadata(resolve_path(p))?.len(), p.clone()));
+            }
+            sized.sort_by_key(|(size, _)| *size);
+            for (slot, (_, p)) in paths.iter_mut().zip(sized) {
+                *slot = p;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Case-insensitive comparison that orders runs of digits by value.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                        digits.push(c);
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (x_num, y_num) = (take_digits(&mut a_chars), take_digits(&mut b_chars));
+                let ord = x_num.len().cmp(&y_num.len()).then_with(|| x_num.cmp(&y_num));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ord = x.to_lowercase().cmp(y.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+/// Parses a page range spec such as `1-3,5,9-11` into page numbers, in the
+/// order written. An open-ended range (`5-`) runs to the last page.
+fn parse_page_ranges(spec: &str, page_count: u32) -> AppResult<Vec<u32>> {
+    let entries: Vec<&str> = spec.split(',').map(str::trim).filter(|e| !e.is_empty()).collect();
+    if entries.is_empty() {
+        return Err(AppError::Validation("No pages selected.".to_string()));
+    }
+    let mut pages = Vec::new();
+    for entry in entries {
+        let (start, end) = parse_page_range(entry, page_count)?;
+        pages.extend(start..=end);
+    }
+    Ok(pages)
+}
+
+/// Parses a single page range entry: `7`, `2-4` or `5-`.
+fn parse_page_range(entry: &str, page_count: u32) -> AppResult<(u32, u32)> {
+    let entry = entry.trim();
+    let parse_number = |s: &str| -> AppResult<u32> {
+        s.trim().parse::<u32>().map_err(|_| {
+            AppError::Validation(format!("\"{}\" is not a valid page range. Use a form like 1-3,5,9-.", entry))
+        })
+    };
+    // Accept the en dash the UI uses when it displays ranges.
+    let (start, end) = match entry.split_once(['-', '–']) {
+        Some((start, end)) => {
+            let start = parse_number(start)?;
+            let end = if end.trim().is_empty() { page_count } else { parse_number(end)? };
+            (start, end)
+        }
+        None => {
+            let page = parse_number(entry)?;
+            (page, page)
+        }
+    };
+    check_page_range(start, end, page_count)?;
+    Ok((start, end))
+}
+
+fn check_page_range(start: u32, end: u32, page_count: u32) -> AppResult<()> {
+    if start == 0 {
+        return Err(AppError::Validation("Page numbers start at 1.".to_string()));
+    }
+    if start > end {
+        return Err(AppError::Validation(format!("Page range {}–{} runs backwards.", start, end)));
+    }
+    if end > page_count {
+        return Err(AppError::Validation(format!(
+            "Page {} is out of range (document has {} pages).",
+            end, page_count
+        )));
+    }
+    Ok(())
 }
 
 // --- Commands ---
 
 #[tauri::command]
-fn list_files_from_paths(paths: Vec<String>) -> AppResult<Vec<FileEntry>> {
+fn get_capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        render: cfg!(feature = "render"),
+        barcode: cfg!(feature = "barcode"),
+        ocr: cfg!(feature = "ocr"),
+    }
+}
+
+/// Lists the given files, plus the files inside any given folders. With
+/// `recursive`, subfolders are walked too, at most `max_depth` levels down
+/// (unlimited when unset). Symlinked folders are not followed, so links can't
+/// send the walk in circles. Subfolders that can't be read are skipped and
+/// reported in a `listing-skipped` event rather than failing the listing.
+/// `extensions` keeps only files with one of the given extensions ("pdf" and
+/// ".PDF" both work), explicitly passed files included.
+#[tauri::command]
+fn list_files_from_paths(
+    app: tauri::AppHandle,
+    paths: Vec<String>,
+    detect_encryption: Option<bool>,
+    recursive: Option<bool>,
+    max_depth: Option<u32>,
+    extensions: Option<Vec<String>>,
+) -> AppResult<Vec<FileEntry>> {
     let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    let depth_limit = if recursive.unwrap_or(false) { max_depth.unwrap_or(u32::MAX) } else { 0 };
     for path in paths {
-        let p = Path::new(&path);
+        let p = resolve_path(&path);
         if !p.exists() {
             return Err(AppError::Path(format!("Path does not exist: {}", path)));
         }
         if p.is_file() {
+            // Names are for display only; the path keeps the exact bytes.
             let name = p
                 .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("")
-                .to_string();
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
             entries.push(FileEntry {
                 path: path.clone(),
                 name,
+                encrypted: None,
             });
         } else if p.is_dir() {
-            let dir_iter = fs::read_dir(&path)?;
-            let mut dir_entries: Vec<FileEntry> = Vec::new();
-            for e in dir_iter {
-                let e = e?;
-                if e.path().is_file() {
-                   let path_buf = e.path();
-                    let path_str = path_buf.to_string_lossy().to_string();
-                    let name = path_buf
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("")
-                        .to_string();
-                    dir_entries.push(FileEntry {
-                        path: path_str,
-                        name,
-                    });
-                }
-            }
-            entries.append(&mut dir_entries);
+            let dir_iter = fs::read_dir(&p)?;
+            collect_dir_files(dir_iter, depth_limit, &mut entries, &mut skipped);
         }
     }
+    if !skipped.is_empty() {
+        let _ = app.emit("listing-skipped", &skipped);
+    }
+    if let Some(extensions) = extensions {
+        let wanted: Vec<String> = extensions
+            .iter()
+            .map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+        entries.retain(|entry| {
+            resolve_path(&entry.path)
+                .extension()
+                .is_some_and(|ext| wanted.iter().any(|w| ext.eq_ignore_ascii_case(w)))
+        });
+    }
+    // A dropped folder plus some of its files lists those files twice.
+    dedup_file_entries(&mut entries);
     entries.sort_by(|a, b| a.name.cmp(&b.name));
+    if detect_encryption.unwrap_or(false) {
+        for entry in entries.iter_mut() {
+            let p = resolve_path(&entry.path);
+            let is_pdf = p
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"));
+            if is_pdf {
+                entry.encrypted = probe_encrypted(&p);
+            }
+        }
+    }
     Ok(entries)
 }
 
+/// Adds the files in a folder listing to `entries`, descending into
+/// subfolders while `depth` allows. Unreadable subfolders go to `skipped`.
+fn collect_dir_files(dir_iter: fs::ReadDir, depth: u32, entries: &mut Vec<FileEntry>, skipped: &mut Vec<String>) {
+    for e in dir_iter.flatten() {
+        let path_buf = e.path();
+        // The entry's own type: a symlink to a folder is not a folder here.
+        let is_dir = e.file_type().is_ok_and(|t| t.is_dir());
+        if is_dir {
+            if depth > 0 {
+                match fs::read_dir(&path_buf) {
+                    Ok(sub_iter) => collect_dir_files(sub_iter, depth - 1, entries, skipped),
+                    Err(_) => skipped.push(path_to_string(&path_buf)),
+                }
+            }
+        } else if path_buf.is_file() {
+            let name = path_buf
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            entries.push(FileEntry {
+                path: path_to_string(&path_buf),
+                name,
+                encrypted: None,
+            });
+        }
+    }
+}
+
+/// Drops repeated entries for the same file, keeping the first. Paths are
+/// compared canonicalized so `dir/../dir/a.pdf` and `dir/a.pdf` match.
+fn dedup_file_entries(entries: &mut Vec<FileEntry>) {
+    let mut seen = std::collections::HashSet::new();
+    entries.retain(|entry| {
+        let p = resolve_path(&entry.path);
+        seen.insert(fs::canonicalize(&p).unwrap_or(p))
+    });
+}
+
+/// Cheap encryption check for file listings: looks for /Encrypt in the final
+/// trailer (or xref stream dictionary) without parsing the document.
+fn probe_encrypted(path: &Path) -> Option<bool> {
+    let file = fs::File::open(path).ok()?;
+    if file.metadata().ok()?.len() < PDF_HEADER.len() as u64 {
+        return None;
+    }
+    // SAFETY: see load_pdf; the mapping is only read while the file is open.
+    let mmap = unsafe { Mmap::map(&file).ok()? };
+    let head = &mmap[..mmap.len().min(1024)];
+    let header_pos = head.windows(PDF_HEADER.len()).position(|w| w == PDF_HEADER)?;
+    // startxref offsets count from the header, not from any junk before it.
+    let data = &mmap[header_pos..];
+    let tail = data.get(find_start_xref(data)? as usize..)?;
+    // An xref stream's dictionary ends where its compressed data begins.
+    let trailer = match tail.windows(6).position(|w| w == b"stream") {
+        Some(end) => &tail[..end],
+        None => tail,
+    };
+    Some(trailer.windows(8).any(|w| w == b"/Encrypt"))
+}
+
 fn parse_placeholder(template: &str) -> Option<(String, usize)> {
     if template.is_empty() {
         return None;
@@ -322,10 +1152,89 @@ fn version_string(index: u32, file_count: u32, min_digits: usize) -> String {
     format!("{:0width$}", index, width = width)
 }
 
-fn apply_template(template: &str, index: u32, file_count: u32) -> Option<String> {
+/// Tokens expanded for every file, alongside the version placeholder.
+const TEMPLATE_TOKENS: &[&str] = &["{date}", "{original}"];
+
+/// Expands the template for one file: the version placeholder (first `#` run
+/// or `{version}`) once, and `{date}` / `{original}` wherever they appear.
+/// Expansion is a single pass, so a stem that itself contains `{date}` or `#`
+/// is copied as is.
+fn apply_template(template: &str, index: u32, file_count: u32, entry: &FileEntry) -> Option<String> {
     let (placeholder, min_digits) = parse_placeholder(template)?;
-    let version = version_string(index, file_count, min_digits);
-    Some(template.replacen(&placeholder, &version, 1))
+    let mut version = Some(version_string(index, file_count, min_digits));
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with(placeholder.as_str()) && version.is_some() {
+            result.push_str(&version.take().unwrap_or_default());
+            rest = &rest[placeholder.len()..];
+        } else if let Some(after) = rest.strip_prefix("{date}") {
+            result.push_str(&file_date(&entry.path));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{original}") {
+            let source = resolve_path(&entry.path);
+            result.push_str(&source.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default());
+            rest = after;
+        } else {
+            result.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Some(result)
+}
+
+/// The template tokens in `template`, in order of appearance.
+fn template_tokens(template: &str) -> Vec<String> {
+    let mut tokens: Vec<(usize, String)> = parse_placeholder(template)
+        .and_then(|(placeholder, _)| Some((template.find(&placeholder)?, placeholder)))
+        .into_iter()
+        .collect();
+    for token in TEMPLATE_TOKENS {
+        tokens.extend(template.match_indices(token).map(|(i, t)| (i, t.to_string())));
+    }
+    tokens.sort();
+    tokens.into_iter().map(|(_, token)| token).collect()
+}
+
+/// The file's modification date as YYYY-MM-DD in local time, or today's date
+/// where the platform doesn't record one.
+fn file_date(path: &str) -> String {
+    let modified = fs::metadata(resolve_path(path)).and_then(|m| m.modified());
+    let date: chrono::DateTime<chrono::Local> = match modified {
+        Ok(time) => time.into(),
+        Err(_) => chrono::Local::now(),
+    };
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// The new file name and full path for one entry of a rename batch. The
+/// extension is kept from the original name.
+fn rename_target(template: &str, index: u32, file_count: u32, entry: &FileEntry) -> Option<(String, PathBuf)> {
+    let base = apply_template(template, index, file_count, entry)?;
+    let ext = Path::new(&entry.name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| format!(".{}", s))
+        .unwrap_or_default();
+    let new_name = format!("{}{}", base, ext);
+    let source = resolve_path(&entry.path);
+    let new_path = source.parent().unwrap_or(Path::new(".")).join(&new_name);
+    Some((new_name, new_path))
+}
+
+/// Target paths that more than one file in the batch would be renamed to, in
+/// order of first appearance. Files in different folders may share a name, so
+/// this compares full paths.
+fn internal_collisions<'a>(targets: impl Iterator<Item = &'a PathBuf>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    let mut collisions = Vec::new();
+    for target in targets {
+        let path = path_to_string(target);
+        if !seen.insert(target) && !collisions.contains(&path) {
+            collisions.push(path);
+        }
+    }
+    collisions
 }
 
 #[tauri::command]
@@ -339,6 +1248,8 @@ fn validate_template(
             preview_names: vec![],
             placeholder_found: parse_placeholder(&template).is_some(),
             overwrite_warnings: vec![],
+            internal_collisions: vec![],
+            tokens: template_tokens(&template),
         });
     }
     if parse_placeholder(&template).is_none() {
@@ -346,29 +1257,26 @@ fn validate_template(
             preview_names: vec![],
             placeholder_found: false,
             overwrite_warnings: vec![],
+            internal_collisions: vec![],
+            tokens: template_tokens(&template),
         });
     }
     let mut preview_names = Vec::new();
     let mut overwrite_warnings = Vec::new();
     let existing_paths: std::collections::HashSet<String> =
         file_entries.iter().map(|e| e.path.clone()).collect();
-    for (i, entry) in file_entries.iter().enumerate() {
-        let index = (i + 1) as u32;
-        let base = match apply_template(&template, index, count) {
-            Some(b) => b,
-            None => continue,
+    let targets: Vec<Option<(String, PathBuf)>> = file_entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| rename_target(&template, (i + 1) as u32, count, entry))
+        .collect();
+    for (i, target) in targets.iter().enumerate() {
+        let Some((new_name, new_path)) = target else {
+            continue;
         };
-        let ext = Path::new(&entry.name)
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|s| format!(".{}", s))
-            .unwrap_or_default();
-        let new_name = format!("{}{}", base, ext);
-        let parent = Path::new(&entry.path).parent().unwrap_or(Path::new("."));
-        let new_path = parent.join(&new_name);
-        let new_path_str = new_path.to_string_lossy().to_string();
+        let new_path_str = path_to_string(new_path);
         if i < 3 {
-            preview_names.push(new_name);
+            preview_names.push(new_name.clone());
         }
         if new_path.exists() && !existing_paths.contains(&new_path_str) {
             overwrite_warnings.push(new_path_str);
@@ -378,53 +1286,64 @@ fn validate_template(
         preview_names,
         placeholder_found: true,
         overwrite_warnings,
+        internal_collisions: internal_collisions(targets.iter().flatten().map(|(_, path)| path)),
+        tokens: template_tokens(&template),
     })
 }
 
+/// Renames the batch as a whole: every target is worked out and checked before
+/// anything is touched, and if a rename still fails partway the completed ones
+/// are undone. Files go through temporary names first, so swaps and other
+/// cycles (A→B, B→A) don't overwrite each other.
 #[tauri::command]
 fn batch_rename(
-    file_entries: Vec<FileEntry>,
+    mut file_entries: Vec<FileEntry>,
     template: String,
 ) -> AppResult<RenameResult> {
+    // Renaming the same source twice would fail the second time, after the
+    // first rename already used up its number.
+    dedup_file_entries(&mut file_entries);
     let count = file_entries.len() as u32;
     if count == 0 {
         return Ok(RenameResult {
             renamed: 0,
             failed: vec![],
+            rolled_back: false,
         });
     }
     if parse_placeholder(&template).is_none() {
         return Err(AppError::Validation("Template has no version placeholder.".to_string()));
     }
-    let mut renamed = 0u32;
     let mut failed = Vec::new();
     let existing_paths: std::collections::HashSet<String> =
         file_entries.iter().map(|e| e.path.clone()).collect();
-    
-    for (i, entry) in file_entries.iter().enumerate() {
-        let index = (i + 1) as u32;
-        let base = match apply_template(&template, index, count) {
-            Some(b) => b,
-            None => {
-                failed.push(RenameFailure {
-                    path: entry.path.clone(),
-                    error: "Could not apply template.".to_string(),
-                });
-                continue;
-            }
+
+    // 1. Plan every rename; refuse the whole batch if any can't be done.
+    let targets: Vec<Option<(String, PathBuf)>> = file_entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| rename_target(&template, (i + 1) as u32, count, entry))
+        .collect();
+    let collisions = internal_collisions(targets.iter().flatten().map(|(_, path)| path));
+    if !collisions.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Several files would get the same name: {}",
+            collisions.join(", ")
+        )));
+    }
+    let mut planned: Vec<(&FileEntry, PathBuf, PathBuf)> = Vec::new();
+    for (entry, target) in file_entries.iter().zip(targets) {
+        let Some((_, new_path)) = target else {
+            failed.push(RenameFailure {
+                path: entry.path.clone(),
+                error: "Could not apply template.".to_string(),
+            });
+            continue;
         };
-        let ext = Path::new(&entry.name)
-            .extension()
-            .and_then(|e| e.to_str())
-            .map(|s| format!(".{}", s))
-            .unwrap_or_default();
-        let new_name = format!("{}{}", base, ext);
-        let parent = Path::new(&entry.path).parent().unwrap_or(Path::new("."));
-        let new_path = parent.join(&new_name);
-        let new_path_str = new_path.to_string_lossy().to_string();
+        let source = resolve_path(&entry.path);
+        let new_path_str = path_to_string(&new_path);
         
         if new_path_str == entry.path {
-            renamed += 1;
             continue;
         }
         if new_path.exists() && !existing_paths.contains(&new_path_str) {
@@ -434,21 +1353,82 @@ fn batch_rename(
             });
             continue;
         }
-        if let Err(e) = fs::rename(&entry.path, &new_path) {
-            failed.push(RenameFailure {
-                path: entry.path.clone(),
-                error: e.to_string(),
-            });
-        } else {
-            renamed += 1;
-        }
+        planned.push((entry, source, new_path));
+    }
+    if !failed.is_empty() {
+        return Ok(RenameResult {
+            renamed: 0,
+            failed,
+            rolled_back: false,
+        });
     }
-    Ok(RenameResult { renamed, failed })
-}
 
-#[tauri::command]
-fn pdf_page_count(path: String) -> AppResult<u32> {
-    let doc = load_pdf(&path)?;
+    // 2. Move each source aside, then 3. onto its target. Every completed
+    // step is logged so a failure can be undone in reverse order.
+    let mut done: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut failure: Option<RenameFailure> = None;
+    let mut staged = Vec::new();
+    let mut taken = std::collections::HashSet::new();
+    for (i, (entry, source, _)) in planned.iter().enumerate() {
+        let parent = source.parent().unwrap_or(Path::new("."));
+        let temp_name = format!(".rename-{}-{}.tmp", std::process::id(), i);
+        let temp = unique_output_path(parent, std::ffi::OsStr::new(&temp_name), &mut taken);
+        match fs::rename(source, &temp) {
+            Ok(()) => {
+                done.push((source.clone(), temp.clone()));
+                staged.push(temp);
+            }
+            Err(e) => {
+                failure = Some(RenameFailure { path: entry.path.clone(), error: e.to_string() });
+                break;
+            }
+        }
+    }
+    if failure.is_none() {
+        for ((entry, _, new_path), temp) in planned.iter().zip(&staged) {
+            // Targets that were sources have been moved aside; anything left
+            // there now is a file we must not overwrite.
+            let result = if new_path.exists() {
+                Err(format!("Would overwrite existing file: {}", path_to_string(new_path)))
+            } else {
+                fs::rename(temp, new_path).map_err(|e| e.to_string())
+            };
+            match result {
+                Ok(()) => done.push((temp.clone(), new_path.clone())),
+                Err(error) => {
+                    failure = Some(RenameFailure { path: entry.path.clone(), error });
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(failure) = failure {
+        let mut failed = vec![failure];
+        for (from, to) in done.iter().rev() {
+            if let Err(e) = fs::rename(to, from) {
+                failed.push(RenameFailure {
+                    path: path_to_string(from),
+                    error: format!("Could not restore from {}: {}", path_to_string(to), e),
+                });
+            }
+        }
+        return Ok(RenameResult {
+            renamed: 0,
+            failed,
+            rolled_back: true,
+        });
+    }
+    Ok(RenameResult {
+        renamed: count,
+        failed,
+        rolled_back: false,
+    })
+}
+
+#[tauri::command]
+fn pdf_page_count(path: String) -> AppResult<u32> {
+    let doc = load_pdf(&path)?;
     let pages = doc.get_pages();
     Ok(pages.len() as u32)
 }
@@ -466,36 +1446,37 @@ fn split_pdf_preview(
         return Err(AppError::Validation("PDF has no pages.".to_string()));
     }
 
-    let path_obj = Path::new(&path);
+    let path_obj = resolve_path(&path);
     let stem = path_obj
         .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("document")
-        .to_string();
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document".to_string());
     let source_name = path_obj
         .file_name()
-        .and_then(|s| s.to_str())
-        .unwrap_or("document.pdf")
-        .to_string();
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document.pdf".to_string());
 
-    let chunk_ranges: Vec<(u32, u32)> = calculate_chunks(&mode, page_count);
+    let planned = plan_split(&doc, &mode, &PartOptions::default())?;
+    let names = split_part_names(std::ffi::OsStr::new(&stem), &planned);
     
-    let parts: Vec<SplitPreviewItem> = chunk_ranges
+    let parts: Vec<SplitPreviewItem> = planned
         .iter()
-        .enumerate()
-        .map(|(i, &(s, e))| {
-            let output_name = format!("{}_part{}.pdf", stem, i + 1);
-            let page_range = if s == e {
-                format!("{}", s)
+        .zip(names)
+        .map(|(part, name)| {
+            let output_name = name.to_string_lossy().to_string();
+            let page_range = if part.start == part.end {
+                format!("{}", part.start)
             } else {
-                format!("{}–{}", s, e)
+                format!("{}–{}", part.start, part.end)
             };
-            SplitPreviewItem {
+            let estimated_size = part.prepared.as_ref().map(|prepared| prepared.bytes.len() as u64);
+            Ok(SplitPreviewItem {
                 output_name,
                 page_range,
-            }
+                estimated_size,
+            })
         })
-        .collect();
+        .collect::<AppResult<_>>()?;
 
     Ok(SplitPreviewResult {
         source_name,
@@ -504,35 +1485,394 @@ fn split_pdf_preview(
     })
 }
 
-fn calculate_chunks(mode: &SplitMode, page_count: u32) -> Vec<(u32, u32)> {
-    match mode {
+/// Number of files a split would produce, so the UI can warn before a large
+/// split runs.
+#[tauri::command]
+fn split_part_count(path: String, mode: SplitMode) -> AppResult<u32> {
+    let doc = load_pdf(&path)?;
+    Ok(plan_split(&doc, &mode, &PartOptions::default())?.len() as u32)
+}
+
+/// Plans the page ranges of each split part. Shared by the split, its
+/// preview and the part count so they always agree. `options` only matter to
+/// size-capped splits, whose parts are measured finished.
+fn plan_split(doc: &Document, mode: &SplitMode, options: &PartOptions) -> AppResult<Vec<SplitPart>> {
+    let page_count = doc.get_pages().len() as u32;
+    if page_count == 0 {
+        return Err(AppError::Validation("PDF has no pages.".to_string()));
+    }
+    let ranges = match mode {
         SplitMode::OnePerPage => (1..=page_count).map(|p| (p, p)).collect(),
-        SplitMode::EveryN { n } => {
-            let n = (*n).max(1);
-            let mut ranges = Vec::new();
-            let mut start = 1u32;
-            while start <= page_count {
-                let end = (start + n - 1).min(page_count);
-                ranges.push((start, end));
-                start = end + 1;
+        SplitMode::EveryN { n, overlap } => {
+            if *overlap >= (*n).max(1) {
+                return Err(AppError::Validation(format!(
+                    "Overlap must be smaller than the part size ({} pages)",
+                    n
+                )));
+            }
+            calculate_chunks(*n, *overlap, page_count)
+        }
+        SplitMode::Pattern { sizes, repeat } => pattern_chunks(sizes, *repeat, page_count)?,
+        SplitMode::BySize { max_bytes } => return size_capped_parts(doc, *max_bytes, options),
+        SplitMode::Ranges { ranges } => range_chunks(ranges, page_count)?,
+        SplitMode::Bookmarks => return bookmark_parts(doc),
+        SplitMode::ByBarcodeSeparator { remove_separators } => {
+            return barcode_separator_parts(doc, *remove_separators);
+        }
+    };
+    Ok(ranges
+        .into_iter()
+        .map(|(start, end)| SplitPart { start, end, label: None, prepared: None })
+        .collect())
+}
+
+fn calculate_chunks(n: u32, overlap: u32, page_count: u32) -> Vec<(u32, u32)> {
+    let n = n.max(1);
+    let overlap = overlap.min(n - 1);
+    let mut ranges = Vec::new();
+    let mut start = 1u32;
+    while start <= page_count {
+        let end = (start + n - 1).min(page_count);
+        ranges.push((start, end));
+        if end == page_count {
+            break;
+        }
+        start = end + 1 - overlap;
+    }
+    ranges
+}
+
+fn pattern_chunks(sizes: &[u32], repeat: bool, page_count: u32) -> AppResult<Vec<(u32, u32)>> {
+    if sizes.is_empty() || sizes.contains(&0) {
+        return Err(AppError::Validation("Pattern sizes must be at least one page each.".to_string()));
+    }
+    let total: u64 = sizes.iter().map(|&n| n as u64).sum();
+    if !repeat && total > page_count as u64 {
+        return Err(AppError::Validation(format!(
+            "Pattern covers {} pages but the document has {}.",
+            total, page_count
+        )));
+    }
+
+    let mut ranges = Vec::new();
+    let mut start = 1u32;
+    for (i, &n) in sizes.iter().cycle().enumerate() {
+        if start > page_count || (!repeat && i == sizes.len()) {
+            break;
+        }
+        let end = start.saturating_add(n - 1).min(page_count);
+        ranges.push((start, end));
+        start = end + 1;
+    }
+    if start <= page_count {
+        ranges.push((start, page_count));
+    }
+    Ok(ranges)
+}
+
+fn range_chunks(ranges: &[String], page_count: u32) -> AppResult<Vec<(u32, u32)>> {
+    let entries: Vec<&str> = ranges
+        .iter()
+        .flat_map(|r| r.split(','))
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .collect();
+    if entries.is_empty() {
+        return Err(AppError::Validation("No page ranges given.".to_string()));
+    }
+    entries
+        .into_iter()
+        .map(|entry| {
+            // Each range becomes a file, so "to the end" must be spelled out.
+            if entry.ends_with(['-', '–']) {
+                return Err(AppError::Validation(format!(
+                    "\"{}\" has no end page. Use a form like 5-{}.",
+                    entry, page_count
+                )));
+            }
+            parse_page_range(entry, page_count)
+        })
+        .collect()
+}
+
+/// Plans a part per top-level bookmark, walking the outline's /First and /Next
+/// chain. Bookmarks that don't lead to a page of this document are skipped,
+/// and of several bookmarks on one page the first names the part.
+fn bookmark_parts(doc: &Document) -> AppResult<Vec<SplitPart>> {
+    let no_bookmarks = || AppError::Validation("The PDF has no bookmarks.".to_string());
+    let outlines = doc
+        .catalog()?
+        .get(b"Outlines")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .map_err(|_| no_bookmarks())?;
+    let pages = doc.get_pages();
+    let page_numbers: std::collections::HashMap<lopdf::ObjectId, u32> =
+        pages.iter().map(|(&n, &id)| (id, n)).collect();
+    let page_count = pages.len() as u32;
+
+    let mut starts: Vec<(u32, Option<String>)> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut next = outlines.get(b"First").and_then(|o| o.as_reference()).ok();
+    // The visited set guards against /Next cycles in damaged outlines.
+    while let Some(item_id) = next.filter(|&id| seen.insert(id)) {
+        let Ok(item) = doc.get_dictionary(item_id) else {
+            break;
+        };
+        next = item.get(b"Next").and_then(|o| o.as_reference()).ok();
+        let LinkTarget::Page { dest, .. } = goto_target(doc, item) else {
+            continue;
+        };
+        let page = match dest.first() {
+            Some(Object::Reference(id)) => page_numbers.get(id).copied(),
+            // Some producers write a zero-based page index instead.
+            Some(Object::Integer(i)) => u32::try_from(*i).ok().map(|i| i + 1).filter(|&n| n <= page_count),
+            _ => None,
+        };
+        if let Some(page) = page {
+            let title = item
+                .get(b"Title")
+                .and_then(|o| doc.dereference(o))
+                .map(|(_, o)| decode_pdf_text(o))
+                .unwrap_or_default();
+            starts.push((page, sanitize_part_label(&title)));
+        }
+    }
+    if seen.is_empty() {
+        return Err(no_bookmarks());
+    }
+    if starts.is_empty() {
+        return Err(AppError::Validation("None of the PDF's bookmarks point to one of its pages.".to_string()));
+    }
+
+    starts.sort_by_key(|&(page, _)| page);
+    starts.dedup_by_key(|(page, _)| *page);
+    if starts[0].0 > 1 {
+        starts.insert(0, (1, None));
+    }
+    let ends: Vec<u32> = starts.iter().skip(1).map(|&(page, _)| page - 1).chain([page_count]).collect();
+    Ok(starts
+        .into_iter()
+        .zip(ends)
+        .map(|((start, label), end)| SplitPart { start, end, label, prepared: None })
+        .collect())
+}
+
+/// Groups consecutive pages into the longest runs whose finished part stays
+/// within `max_bytes`. Part sizes are measured by actually building and
+/// serializing each candidate with `options`, so shared fonts and images are
+/// counted once per part, as they will be written, and each part keeps the
+/// bytes it was measured at. Each part's end is found by doubling the run
+/// until it no longer fits and then bisecting, keeping the number of trial
+/// saves logarithmic in the part length.
+fn size_capped_parts(doc: &Document, max_bytes: u64, options: &PartOptions) -> AppResult<Vec<SplitPart>> {
+    if max_bytes == 0 {
+        return Err(AppError::Validation("Maximum part size must be greater than zero.".to_string()));
+    }
+    let pages = doc.get_pages();
+    let page_count = pages.len() as u32;
+
+    let mut parts: Vec<SplitPart> = Vec::new();
+    let mut start = 1u32;
+    while start <= page_count {
+        let number = parts.len() + 1;
+        // The run `start..=end` once it has been measured and fits.
+        let measure = |end: u32| {
+            let part = SplitPart { start, end, label: None, prepared: None };
+            options.prepare(doc, &pages, &part, number)
+        };
+        let mut end = start;
+        let mut fitting = None;
+        let mut too_long = None;
+        let mut step = 1u32;
+        while end < page_count {
+            let candidate = end.saturating_add(step).min(page_count);
+            let prepared = measure(candidate)?;
+            if prepared.bytes.len() as u64 > max_bytes {
+                too_long = Some(candidate);
+                break;
+            }
+            end = candidate;
+            fitting = Some(prepared);
+            step = step.saturating_mul(2);
+        }
+        if let Some(mut hi) = too_long {
+            while hi - end > 1 {
+                let mid = end + (hi - end) / 2;
+                let prepared = measure(mid)?;
+                if prepared.bytes.len() as u64 <= max_bytes {
+                    end = mid;
+                    fitting = Some(prepared);
+                } else {
+                    hi = mid;
+                }
+            }
+        }
+        let prepared = match fitting {
+            Some(prepared) => prepared,
+            None => measure(end)?,
+        };
+        parts.push(SplitPart { start, end, label: None, prepared: Some(prepared) });
+        start = end + 1;
+    }
+    Ok(parts)
+}
+
+/// Turns a bookmark title or decoded code into something usable as a file
+/// stem.
+fn sanitize_part_label(value: &str) -> Option<String> {
+    let cleaned: String = value
+        .trim()
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .take(100)
+        .collect();
+    let cleaned = cleaned.trim_matches(|c: char| c == '.' || c.is_whitespace()).to_string();
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
+/// Output file names for planned parts: `<label>.pdf` for labelled parts
+/// (suffixed `_2`, `_3`, ... when a label repeats), `<stem>_partN.pdf`
+/// otherwise.
+fn split_part_names(stem: &std::ffi::OsStr, parts: &[SplitPart]) -> Vec<std::ffi::OsString> {
+    let mut used = std::collections::HashSet::new();
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let mut name = match &part.label {
+                Some(label) => {
+                    let mut candidate = label.clone();
+                    let mut n = 2;
+                    while !used.insert(candidate.clone()) {
+                        candidate = format!("{}_{}", label, n);
+                        n += 1;
+                    }
+                    std::ffi::OsString::from(candidate)
+                }
+                None => {
+                    let mut name = stem.to_os_string();
+                    name.push(format!("_part{}", i + 1));
+                    name
+                }
+            };
+            name.push(".pdf");
+            name
+        })
+        .collect()
+}
+
+/// Cancellation flags for running operations, keyed by the id the frontend
+/// passed when starting them. Held in Tauri state.
+#[derive(Default)]
+pub struct OperationRegistry(
+    std::sync::Mutex<std::collections::HashMap<String, std::sync::Arc<std::sync::atomic::AtomicBool>>>,
+);
+
+impl OperationRegistry {
+    /// Registers `id` and returns its flag, which is set once the operation
+    /// is cancelled.
+    fn register(&self, id: &str) -> std::sync::Arc<std::sync::atomic::AtomicBool> {
+        let flag = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.to_string(), flag.clone());
+        flag
+    }
+
+    /// Unregisters `id`, unless it has since been registered again by
+    /// another operation, whose flag is left in place.
+    fn finish(&self, id: &str, flag: &std::sync::Arc<std::sync::atomic::AtomicBool>) {
+        let mut operations = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if operations.get(id).is_some_and(|current| std::sync::Arc::ptr_eq(current, flag)) {
+            operations.remove(id);
+        }
+    }
+
+    fn cancel(&self, id: &str) -> bool {
+        match self.0.lock().unwrap_or_else(|e| e.into_inner()).get(id) {
+            Some(flag) => {
+                flag.store(true, std::sync::atomic::Ordering::Relaxed);
+                true
             }
-            ranges
+            None => false,
         }
     }
 }
 
+/// Asks the running operation started with `operation_id` to stop. It
+/// finishes the step in hand, removes what it had written and fails with
+/// `AppError::Cancelled`. Returns false when no such operation is running.
 #[tauri::command]
-fn split_pdf(
+fn cancel_operation(operations: tauri::State<'_, OperationRegistry>, operation_id: String) -> bool {
+    operations.cancel(&operation_id)
+}
+
+/// Splits on the blocking pool so the command thread stays free and
+/// `split-progress` events reach the UI while parts are being written.
+/// With an `operation_id`, `cancel_operation` can stop the split between
+/// parts; the parts already written are deleted.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn split_pdf(
     app: tauri::AppHandle,
+    operations: tauri::State<'_, OperationRegistry>,
     source_path: String,
     output_dir: Option<String>,
     mode: SplitMode,
-) -> AppResult<Vec<String>> {
-    let path = PathBuf::from(&source_path);
+    copy_metadata: Option<bool>,
+    number_titles: Option<bool>,
+    target_version: Option<String>,
+    operation_id: Option<String>,
+) -> AppResult<SplitResult> {
+    let cancel = operation_id.as_deref().map(|id| operations.register(id));
+    let registered = cancel.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let progress = |done: u32| {
+            let _ = app.emit("split-progress", done);
+        };
+        split_pdf_blocking(source_path, output_dir, mode, copy_metadata, number_titles, target_version, cancel, progress)
+    })
+    .await;
+    if let (Some(id), Some(flag)) = (&operation_id, &registered) {
+        operations.finish(id, flag);
+    }
+    result.map_err(|e| AppError::Validation(format!("Split task failed: {}", e)))?
+}
+
+/// The body of `split_pdf`. `progress` is called with the number of parts
+/// finished so far, from whichever worker finished one.
+#[allow(clippy::too_many_arguments)]
+fn split_pdf_blocking(
+    source_path: String,
+    output_dir: Option<String>,
+    mode: SplitMode,
+    copy_metadata: Option<bool>,
+    number_titles: Option<bool>,
+    target_version: Option<String>,
+    cancel: Option<std::sync::Arc<std::sync::atomic::AtomicBool>>,
+    progress: impl Fn(u32) + Sync,
+) -> AppResult<SplitResult> {
+    let path = resolve_path(&source_path);
     if !path.is_file() {
         return Err(AppError::Path("Path is not a file.".to_string()));
     }
 
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_os_string())
+        .unwrap_or_else(|| "document".into());
+    let out_dir_path = match &output_dir {
+        Some(d) => resolve_path(d),
+        None => path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf(),
+    };
+    if !out_dir_path.is_dir() {
+        return Err(AppError::Path("Output path is not a directory.".to_string()));
+    }
+    // Fail before the (potentially slow) load rather than at the first save.
+    ensure_dir_writable(&out_dir_path)?;
+
     // Load document to get page count
     let doc = load_pdf(&path)?;
     let pages = doc.get_pages();
@@ -542,69 +1882,433 @@ fn split_pdf(
         return Err(AppError::Validation("PDF has no pages.".to_string()));
     }
 
-    let stem = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("document")
-        .to_string();
-    let out_dir_path = match &output_dir {
-        Some(d) => PathBuf::from(d),
-        None => path.parent().unwrap_or_else(|| Path::new(".")).to_path_buf(),
+    let options = PartOptions {
+        copy_metadata: copy_metadata.unwrap_or(true),
+        number_titles: number_titles.unwrap_or(false),
+        target_version,
     };
-    if !out_dir_path.is_dir() {
-        return Err(AppError::Path("Output path is not a directory.".to_string()));
+    let planned = plan_split(&doc, &mode, &options)?;
+    let names = split_part_names(&stem, &planned);
+
+    // Parts are independent, so they are extracted and saved in parallel.
+    // `extract_pages` takes `&self` and copies what a part needs into a new
+    // document, so `doc` and `pages` are shared read-only by the workers
+    // without cloning; each worker holds at most one part in memory at a time.
+    // `split-progress` reports how many parts are finished, which need not be
+    // the first ones.
+    let done = std::sync::atomic::AtomicU32::new(0);
+    // Set when a part fails, so the parts not yet started are skipped.
+    let failed = std::sync::atomic::AtomicBool::new(false);
+    let cancelled = || cancel.as_ref().is_some_and(|flag| flag.load(std::sync::atomic::Ordering::Relaxed));
+    progress(0);
+    let results: Vec<_> = planned
+        .par_iter()
+        .zip(names.par_iter())
+        .enumerate()
+        .map(|(i, (part, out_name))| {
+            if cancelled() {
+                return Err(AppError::Cancelled);
+            }
+            if failed.load(std::sync::atomic::Ordering::Relaxed) {
+                return Ok(None);
+            }
+            let write_part = || -> AppResult<(SplitOutput, Vec<String>)> {
+                // We pass the pre-computed `pages` map to avoid O(P) walks per part.
+                let prepared = options.prepared(&doc, &pages, part, i + 1)?;
+                let out_path = out_dir_path.join(out_name);
+                fs::write(&out_path, &prepared.bytes)?;
+                let size = prepared.bytes.len() as u64;
+                let mut warnings: Vec<String> =
+                    dropped_links_warning(i + 1, prepared.dropped_links).into_iter().collect();
+                if let SplitMode::BySize { max_bytes } = mode {
+                    warnings.extend(over_limit_warning(part, i + 1, size, max_bytes));
+                }
+                Ok((
+                    SplitOutput {
+                        path: path_to_string(&out_path),
+                        size,
+                    },
+                    warnings,
+                ))
+            };
+            match write_part() {
+                Ok(written) => {
+                    progress(done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1);
+                    Ok(Some(written))
+                }
+                Err(e) => {
+                    failed.store(true, std::sync::atomic::Ordering::Relaxed);
+                    Err(e)
+                }
+            }
+        })
+        .collect();
+
+    let cancelled = results.iter().any(|result| matches!(result, Err(AppError::Cancelled)));
+    let mut first_error = None;
+    let mut outputs = Vec::with_capacity(results.len());
+    let mut warnings = Vec::new();
+    for result in results {
+        match result {
+            Ok(Some((output, part_warnings))) => {
+                outputs.push(output);
+                warnings.extend(part_warnings);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    // A failed or cancelled run leaves nothing behind: the parts other workers
+    // finished are removed before reporting the error (a cancellation first).
+    if let Some(e) = first_error {
+        for output in &outputs {
+            let _ = fs::remove_file(&output.path);
+        }
+        return Err(if cancelled { AppError::Cancelled } else { e });
     }
 
-    let chunk_ranges = calculate_chunks(&mode, page_count);
-    let mut saved_paths = Vec::new();
-
-    // Memory efficient split:
-    // Instead of cloning the entire doc for each chunk, we clone for each chunk. 
-    // Wait, deep cloning IS the easiest way to ensure data integrity in lopdf.
-    // However, to be "streaming-like" or more memory efficient with lopdf,
-    // we should ideally modify a copy or extract.
-    // Given lopdf's structure, doc.clone() performs a deep clone of the object list.
-    // For really large PDFs, we can improve by re-loading from disk if memory is tighter than CPU, 
-    // but cloning in RAM is usually faster than IO. 
-    //
-    // The previous implementation:
-    // for range:
-    //   clone doc
-    //   delete pages outside range
-    //   save
-    //
-    // This loops N times. Peak MEM = DocSize + DocSize (clone). 
-    // This IS strictly O(DocSize) peak memory, not O(N * DocSize).
-    // The user requested "streaming approach". 
-    // True streaming involves reading object by object. lopdf is DOM-based.
-    // The best we can do with lopdf to avoid holding 2x memory (if really constrained) 
-    // is to ensure we drop the clone immediately.
-    //
-    // However, if we want to avoid the overhead of `delete_pages` (which iterates everything),
-    // and if we want to produce checking behaviour.
-    // To strictly follow "streaming" we'd need a different crate or approach.
-    // But minimizing memory footprint:
-    // 
-    for (i, &(start, end)) in chunk_ranges.iter().enumerate() {
-        // Emit progress to frontend
-        let _ = app.emit("split-progress", i as u32);
-
-        // HIGH PERFORMANCE: extract_pages only copies required objects.
-        // We pass the pre-computed `pages` map to avoid O(P) walks in the loop.
-        let page_range: Vec<u32> = (start..=end).collect();
-        let mut part_doc = doc.extract_pages(&pages, &page_range)?;
-
-        let out_name = format!("{}_part{}.pdf", stem, i + 1);
-        let out_path = out_dir_path.join(&out_name);
-        
-        part_doc.save(&out_path)?;
-        
-        saved_paths.push(out_path.to_string_lossy().to_string());
+    Ok(SplitResult {
+        parts: outputs,
+        warnings,
+    })
+}
+
+/// Describes a size-capped part that still came out over `max_bytes`, which
+/// happens when a single page is larger than the limit.
+fn over_limit_warning(part: &SplitPart, number: usize, size: u64, max_bytes: u64) -> Option<String> {
+    if size <= max_bytes {
+        return None;
+    }
+    Some(if part.start == part.end {
+        format!("Page {} alone is {} bytes, over the {}-byte limit.", part.start, size, max_bytes)
+    } else {
+        format!("Part {} is {} bytes, over the {}-byte limit.", number, size, max_bytes)
+    })
+}
+
+/// Keeps or strips the source's document metadata on a split part.
+/// `extract_pages` already copies the Info dictionary and the catalog's XMP
+/// stream; when `part_number` is set the Info title gets a "(part N)" suffix.
+fn set_part_metadata(doc: &mut Document, copy: bool, part_number: Option<usize>) -> AppResult<()> {
+    if !copy {
+        doc.trailer.remove(b"Info");
+        doc.catalog_mut()?.remove(b"Metadata");
+        return Ok(());
+    }
+    let Some(part_number) = part_number else {
+        return Ok(());
+    };
+    let info = match doc.trailer.get(b"Info").and_then(|o| o.as_reference()) {
+        Ok(id) => doc.get_dictionary_mut(id).ok(),
+        Err(_) => doc.trailer.get_mut(b"Info").and_then(|o| o.as_dict_mut()).ok(),
+    };
+    if let Some(info) = info {
+        let title = info.get(b"Title").map(decode_pdf_text).unwrap_or_default();
+        if !title.trim().is_empty() {
+            info.set("Title", lopdf::text_string(&format!("{} (part {})", title, part_number)));
+        }
+    }
+    Ok(())
+}
+
+/// Where an annotation's link leads, as far as the page tree is concerned.
+enum LinkTarget {
+    /// Not an internal page link (URI, launch, JavaScript, ...).
+    Other,
+    /// An internal link with its explicit destination array.
+    Page { dest: Vec<Object>, named: bool },
+    /// An internal link whose destination cannot be resolved.
+    Broken,
+}
+
+fn link_target(doc: &Document, annot: &lopdf::Dictionary) -> LinkTarget {
+    if !annot.get(b"Subtype").and_then(|o| o.as_name()).is_ok_and(|n| n == b"Link") {
+        return LinkTarget::Other;
+    }
+    goto_target(doc, annot)
+}
+
+/// Resolves the /Dest or GoTo /A of a link annotation or outline item.
+fn goto_target(doc: &Document, item: &lopdf::Dictionary) -> LinkTarget {
+    let dest = if let Ok(dest) = item.get(b"Dest") {
+        dest
+    } else if let Ok(action) = item.get(b"A").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
+        if !action.get(b"S").and_then(|o| o.as_name()).is_ok_and(|n| n == b"GoTo") {
+            return LinkTarget::Other;
+        }
+        match action.get(b"D") {
+            Ok(dest) => dest,
+            Err(_) => return LinkTarget::Broken,
+        }
+    } else {
+        return LinkTarget::Other;
+    };
+
+    match doc.dereference(dest).map(|(_, o)| o) {
+        Ok(Object::Array(dest)) => LinkTarget::Page { dest: dest.clone(), named: false },
+        Ok(Object::Name(name)) | Ok(Object::String(name, _)) => match resolve_named_destination(doc, name) {
+            Some(dest) => LinkTarget::Page { dest, named: true },
+            None => LinkTarget::Broken,
+        },
+        _ => LinkTarget::Broken,
+    }
+}
+
+/// Looks a named destination up in the catalog's /Dests dictionary (PDF 1.1)
+/// or the /Names /Dests name tree.
+fn resolve_named_destination(doc: &Document, name: &[u8]) -> Option<Vec<Object>> {
+    fn lookup(doc: &Document, node: &lopdf::Dictionary, name: &[u8], depth: u32) -> Option<Object> {
+        if let Ok(names) = node.get(b"Names").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()) {
+            for pair in names.chunks(2) {
+                if pair.len() == 2 && pair[0].as_str().is_ok_and(|key| key == name) {
+                    return Some(pair[1].clone());
+                }
+            }
+        }
+        if depth >= 32 {
+            return None;
+        }
+        let kids = node.get(b"Kids").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()).ok()?;
+        kids.iter()
+            .filter_map(|kid| doc.dereference(kid).and_then(|(_, o)| o.as_dict()).ok())
+            .find_map(|kid| lookup(doc, kid, name, depth + 1))
+    }
+
+    let catalog = doc.catalog().ok()?;
+    let found = catalog
+        .get(b"Dests")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .and_then(|dests| dests.get(name))
+        .ok()
+        .cloned()
+        .or_else(|| {
+            let tree = catalog
+                .get(b"Names")
+                .and_then(|o| doc.dereference(o))
+                .and_then(|(_, o)| o.as_dict())
+                .and_then(|names| names.get(b"Dests"))
+                .and_then(|o| doc.dereference(o))
+                .and_then(|(_, o)| o.as_dict())
+                .ok()?;
+            lookup(doc, tree, name, 0)
+        })?;
+
+    match doc.dereference(&found).ok()?.1 {
+        Object::Array(dest) => Some(dest.clone()),
+        // Destinations in the tree may be wrapped as << /D [...] >>.
+        Object::Dictionary(d) => d.get(b"D").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()).ok().cloned(),
+        _ => None,
+    }
+}
+
+/// Writes the given pages of a PDF, in the order given, to a single new file.
+/// Pages may repeat.
+#[tauri::command]
+fn extract_pages(source_path: String, page_numbers: Vec<u32>, output_path: String) -> AppResult<()> {
+    if page_numbers.is_empty() {
+        return Err(AppError::Validation("No pages selected.".to_string()));
+    }
+    ensure_output_target(&output_path, std::slice::from_ref(&source_path), true)?;
+    ensure_output_writable(&output_path)?;
+    let doc = load_pdf(&source_path)?;
+    let pages = doc.get_pages();
+    let page_count = pages.len() as u32;
+    for &page in &page_numbers {
+        check_page_range(page, page, page_count)?;
+    }
+    let (mut out_doc, _) = extract_page_list(&doc, &pages, &page_numbers)?;
+    out_doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Copies the given pages (1-based, all in range) into a new document, in the
+/// order given. A page listed twice appears twice, sharing its content.
+///
+/// lopdf's extraction also copies everything reachable from the pages' old
+/// /Parent nodes, which includes every other page, so the tree is flattened
+/// and pruned afterwards. Links and bookmarks to dropped pages are removed
+/// first so they don't keep those pages alive; the number of links removed is
+/// returned with the document.
+fn extract_page_list(
+    doc: &Document,
+    pages: &std::collections::BTreeMap<u32, lopdf::ObjectId>,
+    page_numbers: &[u32],
+) -> AppResult<(Document, usize)> {
+    let mut part_doc = doc.extract_pages(pages, page_numbers)?;
+    let dropped_links = drop_external_page_links(&mut part_doc);
+    let kept: std::collections::HashSet<lopdf::ObjectId> = page_numbers.iter().map(|n| pages[n]).collect();
+    prune_outline(&mut part_doc, &kept);
+
+    let mut seen = std::collections::HashSet::new();
+    let mut page_ids = Vec::with_capacity(page_numbers.len());
+    for n in page_numbers {
+        let id = pages[n];
+        if seen.insert(id) {
+            page_ids.push(id);
+        } else {
+            // A page object can only have one place in the tree.
+            let copy = part_doc.get_object(id)?.clone();
+            page_ids.push(part_doc.add_object(copy));
+        }
+    }
+    rebuild_page_tree(&mut part_doc, page_ids)?;
+    Ok((part_doc, dropped_links))
+}
+
+/// Warns that a split part lost links to pages that went to other parts.
+fn dropped_links_warning(part_number: usize, dropped_links: usize) -> Option<String> {
+    (dropped_links > 0).then(|| {
+        format!(
+            "Part {} had {} link{} to pages outside it removed.",
+            part_number,
+            dropped_links,
+            if dropped_links == 1 { "" } else { "s" }
+        )
+    })
+}
+
+/// Removes bookmarks that don't lead to one of `kept` pages, moving their
+/// surviving children up in their place, and rewrites named destinations to
+/// explicit ones. Removes the outline entirely if nothing survives.
+fn prune_outline(doc: &mut Document, kept: &std::collections::HashSet<lopdf::ObjectId>) {
+    /// Returns the surviving items of the chain starting at `first`, with
+    /// each one's children relinked and /Count updated.
+    fn prune_chain(
+        doc: &mut Document,
+        first: Option<lopdf::ObjectId>,
+        kept: &std::collections::HashSet<lopdf::ObjectId>,
+        seen: &mut std::collections::HashSet<lopdf::ObjectId>,
+    ) -> Vec<lopdf::ObjectId> {
+        let mut survivors = Vec::new();
+        let mut next = first;
+        while let Some(id) = next.filter(|&id| seen.insert(id)) {
+            let Ok(item) = doc.get_dictionary(id) else { break };
+            next = item.get(b"Next").and_then(|o| o.as_reference()).ok();
+            let first_child = item.get(b"First").and_then(|o| o.as_reference()).ok();
+            let open = item.get(b"Count").and_then(|o| o.as_i64()).is_ok_and(|c| c > 0);
+            let target = match goto_target(doc, item) {
+                LinkTarget::Page { dest, named } => dest
+                    .first()
+                    .and_then(|o| o.as_reference().ok())
+                    .filter(|page| kept.contains(page))
+                    .map(|_| (dest, named)),
+                _ => None,
+            };
+            let children = prune_chain(doc, first_child, kept, seen);
+            let Some((dest, named)) = target else {
+                survivors.extend(children);
+                continue;
+            };
+            let _ = link_outline_children(doc, id, &children, open);
+            if named {
+                if let Ok(item) = doc.get_dictionary_mut(id) {
+                    item.remove(b"A");
+                    item.set("Dest", Object::Array(dest));
+                }
+            }
+            survivors.push(id);
+        }
+        survivors
+    }
+
+    let Some(root_id) = doc
+        .catalog()
+        .and_then(|c| c.get(b"Outlines"))
+        .and_then(|o| o.as_reference())
+        .ok()
+    else {
+        return;
+    };
+    let first = doc.get_dictionary(root_id).and_then(|d| d.get(b"First")).and_then(|o| o.as_reference()).ok();
+    let items = prune_chain(doc, first, kept, &mut std::collections::HashSet::new());
+    if items.is_empty() {
+        if let Ok(catalog) = doc.catalog_mut() {
+            catalog.remove(b"Outlines");
+        }
+    } else {
+        let _ = link_outline_children(doc, root_id, &items, true);
     }
+}
+
+/// Removes internal link annotations that point at pages outside `doc`'s page
+/// tree, as happens after extracting a subset of pages. Links into the kept
+/// pages still work because extraction preserves object ids; named links are
+/// rewritten to explicit destinations since the name tree still lists pages
+/// that are gone. Returns the number of links removed.
+fn drop_external_page_links(doc: &mut Document) -> usize {
+    let page_ids: std::collections::HashSet<lopdf::ObjectId> = doc.get_pages().values().copied().collect();
+    let mut removed = 0;
+
+    for &page_id in &page_ids {
+        let annots_obj = match doc.get_dictionary(page_id).and_then(|d| d.get(b"Annots")) {
+            Ok(o) => o.clone(),
+            Err(_) => continue,
+        };
+        let annots = match doc.dereference(&annots_obj).and_then(|(_, o)| o.as_array()) {
+            Ok(a) => a.clone(),
+            Err(_) => continue,
+        };
 
-    let _ = app.emit("split-progress", chunk_ranges.len() as u32);
+        let mut kept = Vec::with_capacity(annots.len());
+        let mut rewritten = Vec::new();
+        for item in annots {
+            let annot = match doc.dereference(&item).and_then(|(_, o)| o.as_dict()) {
+                Ok(d) => d,
+                Err(_) => {
+                    kept.push(item);
+                    continue;
+                }
+            };
+            match link_target(doc, annot) {
+                LinkTarget::Other => kept.push(item),
+                LinkTarget::Page { dest, named } => {
+                    let in_part = dest
+                        .first()
+                        .and_then(|o| o.as_reference().ok())
+                        .is_some_and(|id| page_ids.contains(&id));
+                    if !in_part {
+                        removed += 1;
+                    } else if named {
+                        let mut annot = annot.clone();
+                        annot.remove(b"A");
+                        annot.set("Dest", Object::Array(dest));
+                        match item {
+                            Object::Reference(id) => {
+                                rewritten.push((id, annot));
+                                kept.push(item);
+                            }
+                            _ => kept.push(Object::Dictionary(annot)),
+                        }
+                    } else {
+                        kept.push(item);
+                    }
+                }
+                LinkTarget::Broken => removed += 1,
+            }
+        }
 
-    Ok(saved_paths)
+        for (id, annot) in rewritten {
+            doc.objects.insert(id, Object::Dictionary(annot));
+        }
+        match annots_obj {
+            Object::Reference(id) => {
+                doc.objects.insert(id, Object::Array(kept));
+            }
+            _ => {
+                if let Ok(page) = doc.get_dictionary_mut(page_id) {
+                    if kept.is_empty() {
+                        page.remove(b"Annots");
+                    } else {
+                        page.set("Annots", Object::Array(kept));
+                    }
+                }
+            }
+        }
+    }
+    removed
 }
 
 // --- Merge and Inspect ---
@@ -617,12 +2321,49 @@ pub struct PageBoxes {
     pub bleed_box: Option<String>,
     pub trim_box: Option<String>,
     pub art_box: Option<String>,
+    /// Neither the page nor any ancestor has a usable MediaBox.
+    pub missing_mediabox: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PageMetadata {
     pub page_number: u32,
+    /// The MediaBox is wider than it is tall.
     pub is_landscape: bool,
+    /// The inherited /Rotate, normalised to 0, 90, 180 or 270.
+    pub rotation: i32,
+    /// The page shows as landscape once its rotation is applied.
+    pub display_landscape: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrganiseResult {
+    /// The document is tagged and the new page order no longer matches the
+    /// structure tree's reading order.
+    pub accessibility_affected: bool,
+    /// Non-fatal caveats about the output, for display.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageCountReport {
+    /// The root Pages node's /Count before the repair, if it had one.
+    pub declared_count: Option<i64>,
+    pub actual_count: u32,
+    /// How many Pages nodes had their /Count rewritten.
+    pub nodes_fixed: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageInfo {
+    pub page_number: u32,
+    /// MediaBox dimensions in points, before rotation.
+    pub width: f64,
+    pub height: f64,
+    /// Effective /Rotate, normalized to 0, 90, 180 or 270.
+    pub rotation: i64,
+    pub has_annotations: bool,
+    pub has_images: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -632,24 +2373,38 @@ pub enum PageAction {
     Blank,
 }
 
-fn format_rect(obj: &lopdf::Object) -> Option<String> {
-    if let Ok(arr) = obj.as_array() {
-        if arr.len() == 4 {
-            let nums: Vec<f64> = arr
-                .iter()
-                .filter_map(|o| match o {
-                     lopdf::Object::Real(f) => Some(*f as f64),
-                     lopdf::Object::Integer(i) => Some(*i as f64),
-                     _ => None,
-                })
-                .collect();
-            if nums.len() == 4 {
-               return Some(format!("[{:.2}, {:.2}, {:.2}, {:.2}]", nums[0], nums[1], nums[2], nums[3]));
-            }
-        }
+/// Reads a rectangle array. Both the array and its entries may be indirect
+/// references (`10 0 R`), which some generators emit for box coordinates.
+fn parse_rect(doc: &Document, obj: &lopdf::Object) -> Option<[f64; 4]> {
+    let arr = doc.dereference(obj).ok()?.1.as_array().ok()?;
+    if arr.len() != 4 {
+        return None;
     }
-    None
-}
+    let nums: Vec<f64> = arr
+        .iter()
+        .filter_map(|o| match doc.dereference(o).ok()?.1 {
+             lopdf::Object::Real(f) => Some(*f as f64),
+             lopdf::Object::Integer(i) => Some(*i as f64),
+             _ => None,
+        })
+        .collect();
+    if nums.len() == 4 {
+        Some([nums[0], nums[1], nums[2], nums[3]])
+    } else {
+        None
+    }
+}
+
+fn format_rect(doc: &Document, obj: &lopdf::Object) -> Option<String> {
+    let r = parse_rect(doc, obj)?;
+    Some(format!("[{:.2}, {:.2}, {:.2}, {:.2}]", r[0], r[1], r[2], r[3]))
+}
+
+/// Normalizes a rectangle so the first corner is lower-left, as the spec allows
+/// either corner order.
+fn normalize_rect(r: [f64; 4]) -> [f64; 4] {
+    [r[0].min(r[2]), r[1].min(r[3]), r[0].max(r[2]), r[1].max(r[3])]
+}
 
 #[tauri::command]
 fn get_page_boxes(path: String) -> AppResult<Vec<PageBoxes>> {
@@ -661,7 +2416,7 @@ fn get_page_boxes(path: String) -> AppResult<Vec<PageBoxes>> {
         let page_dict = doc.get_dictionary(page_id)?;
         
         let get_box = |name: &[u8]| -> Option<String> {
-             page_dict.get(name).ok().and_then(format_rect)
+             page_dict.get(name).ok().and_then(|o| format_rect(&doc, o))
         };
         
         results.push(PageBoxes {
@@ -671,20 +2426,511 @@ fn get_page_boxes(path: String) -> AppResult<Vec<PageBoxes>> {
             bleed_box: get_box(b"BleedBox"),
             trim_box: get_box(b"TrimBox"),
             art_box: get_box(b"ArtBox"),
+            missing_mediabox: inherited_attr(&doc, page_id, b"MediaBox").and_then(|o| parse_rect(&doc, o)).is_none(),
         });
     }
     
     Ok(results)
 }
 
+/// Gives every page without a usable MediaBox (on itself or an ancestor) one.
+/// `default` is "a4", "letter", or "most_common" for the size most other pages
+/// in the document use. Returns how many pages were fixed.
+#[tauri::command]
+fn fix_missing_mediabox(path: String, default: String, output_path: String) -> AppResult<u32> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let pages = doc.get_pages();
+    let media_box = |page_id| inherited_attr(&doc, page_id, b"MediaBox").and_then(|o| parse_rect(&doc, o));
+    let missing: Vec<lopdf::ObjectId> = pages.values().copied().filter(|&id| media_box(id).is_none()).collect();
+
+    let rect = match default.trim().to_ascii_lowercase().as_str() {
+        "a4" => [0.0, 0.0, 595.28, 841.89],
+        "letter" => [0.0, 0.0, 612.0, 792.0],
+        "most_common" => {
+            // Sizes are compared to the hundredth of a point; ties go to the
+            // size that appears first.
+            let mut counts: Vec<([f64; 4], u32)> = Vec::new();
+            for rect in pages.values().filter_map(|&id| media_box(id)).map(normalize_rect) {
+                match counts.iter_mut().find(|(r, _)| r.iter().zip(&rect).all(|(a, b)| (a - b).abs() < 0.01)) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((rect, 1)),
+                }
+            }
+            let best = counts.iter().map(|(_, n)| *n).max().unwrap_or(0);
+            match counts.into_iter().find(|(_, n)| *n == best) {
+                Some((rect, _)) => rect,
+                None => {
+                    return Err(AppError::Validation(
+                        "No page has a MediaBox to copy; choose A4 or Letter instead.".to_string(),
+                    ))
+                }
+            }
+        }
+        other => {
+            return Err(AppError::Validation(format!(
+                "Unknown default page size '{}' (expected a4, letter or most_common).",
+                other
+            )))
+        }
+    };
+
+    for &page_id in &missing {
+        let page = doc.get_dictionary_mut(page_id)?;
+        page.set(b"MediaBox", rect.iter().map(|&v| Object::Real(v as f32)).collect::<Vec<_>>());
+    }
+    doc.save(resolve_path(&output_path))?;
+    Ok(missing.len() as u32)
+}
+
+/// Sets /CropBox on the given 1-based pages. Rectangles are
+/// `[llx, lly, urx, ury]` in default user space (origin bottom-left) and must
+/// lie within the page's MediaBox, inherited or its own.
+#[tauri::command]
+fn set_crop_box(
+    path: String,
+    crop_boxes: std::collections::HashMap<u32, [f64; 4]>,
+    output_path: String,
+) -> AppResult<()> {
+    if crop_boxes.is_empty() {
+        return Err(AppError::Validation("No crop boxes given.".to_string()));
+    }
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let page_map = doc.get_pages();
+    let mut crop_boxes: Vec<(u32, [f64; 4])> = crop_boxes.into_iter().collect();
+    crop_boxes.sort_by_key(|&(page, _)| page);
+
+    for (page, rect) in crop_boxes {
+        check_page_range(page, page, page_map.len() as u32)?;
+        let page_id = page_map[&page];
+        let rect = normalize_rect(rect);
+        if rect[2] - rect[0] <= 0.0 || rect[3] - rect[1] <= 0.0 {
+            return Err(AppError::Validation(format!("Crop box for page {} is empty.", page)));
+        }
+        let media = inherited_attr(&doc, page_id, b"MediaBox")
+            .and_then(|o| parse_rect(&doc, o))
+            .map(normalize_rect)
+            .ok_or_else(|| AppError::Validation(format!("Page {} has no MediaBox to crop within.", page)))?;
+        const TOLERANCE: f64 = 0.01;
+        if rect[0] < media[0] - TOLERANCE
+            || rect[1] < media[1] - TOLERANCE
+            || rect[2] > media[2] + TOLERANCE
+            || rect[3] > media[3] + TOLERANCE
+        {
+            return Err(AppError::Validation(format!(
+                "Crop box for page {} extends outside its MediaBox [{} {} {} {}].",
+                page, media[0], media[1], media[2], media[3]
+            )));
+        }
+        doc.get_dictionary_mut(page_id)?
+            .set(b"CropBox", rect.iter().map(|&v| Object::Real(v as f32)).collect::<Vec<_>>());
+    }
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Standard paper sizes for `normalize_page_size`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaperSize {
+    A3,
+    A4,
+    A5,
+    Letter,
+    Legal,
+    Tabloid,
+}
+
+impl PaperSize {
+    /// Portrait width and height in points.
+    fn dimensions(self) -> (f64, f64) {
+        match self {
+            PaperSize::A3 => (841.89, 1190.55),
+            PaperSize::A4 => (595.28, 841.89),
+            PaperSize::A5 => (419.53, 595.28),
+            PaperSize::Letter => (612.0, 792.0),
+            PaperSize::Legal => (612.0, 1008.0),
+            PaperSize::Tabloid => (792.0, 1224.0),
+        }
+    }
+}
+
+/// Scales every page to `target`, keeping each page's orientation as displayed
+/// (landscape pages, including portrait pages turned by /Rotate, get the
+/// landscape size). Content keeps its aspect ratio and is centred, leaving
+/// margins on the short side; anything outside the old visible area stays
+/// clipped. Annotation rectangles and the trim, bleed and art boxes are moved
+/// along with the content; the crop box is dropped since the new MediaBox is
+/// the visible area.
+#[tauri::command]
+fn normalize_page_size(path: String, target: PaperSize, output_path: String) -> AppResult<()> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let (short, long) = target.dimensions();
+
+    for (_, page_id) in doc.get_pages() {
+        let [x0, y0, x1, y1] = page_visible_rect(&doc, page_id);
+        let (width, height) = (x1 - x0, y1 - y0);
+        let quarter_turn = inherited_attr(&doc, page_id, b"Rotate")
+            .and_then(|o| o.as_i64().ok())
+            .unwrap_or(0)
+            .rem_euclid(180)
+            == 90;
+        let landscape = if quarter_turn { height > width } else { width > height };
+        // The target in unrotated user space: a quarter turn swaps its sides.
+        let (new_width, new_height) = if landscape != quarter_turn { (long, short) } else { (short, long) };
+        let scale = (new_width / width).min(new_height / height);
+        let dx = (new_width - width * scale) / 2.0 - x0 * scale;
+        let dy = (new_height - height * scale) / 2.0 - y0 * scale;
+        let map_rect = |rect: [f64; 4]| -> Object {
+            let r = normalize_rect(rect);
+            [r[0] * scale + dx, r[1] * scale + dy, r[2] * scale + dx, r[3] * scale + dy]
+                .iter()
+                .map(|&v| Object::Real(v as f32))
+                .collect::<Vec<_>>()
+                .into()
+        };
+
+        let open = format!(
+            "q {:.6} 0 0 {:.6} {:.4} {:.4} cm {:.4} {:.4} {:.4} {:.4} re W n\n",
+            scale, scale, dx, dy, x0, y0, width, height
+        );
+        let mut contents: Vec<Object> = doc.get_page_contents(page_id).into_iter().map(Object::Reference).collect();
+        contents.insert(0, doc.add_object(lopdf::Stream::new(dictionary! {}, open.into_bytes())).into());
+        contents.push(doc.add_object(lopdf::Stream::new(dictionary! {}, b"Q\n".to_vec())).into());
+
+        let page = doc.get_dictionary(page_id)?;
+        let other_boxes: Vec<(&[u8], Object)> = [b"TrimBox".as_slice(), b"BleedBox", b"ArtBox"]
+            .into_iter()
+            .filter_map(|key| Some((key, map_rect(parse_rect(&doc, page.get(key).ok()?)?))))
+            .collect();
+        let annots: Vec<lopdf::ObjectId> = page
+            .get(b"Annots")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_array())
+            .map(|a| a.iter().filter_map(|o| o.as_reference().ok()).collect())
+            .unwrap_or_default();
+        for annot_id in annots {
+            let Ok(annot) = doc.get_dictionary_mut(annot_id) else { continue };
+            if let Some(rect) = annot.get(b"Rect").ok().and_then(|o| o.as_array().ok()).and_then(|a| {
+                let v: Vec<f64> = a.iter().filter_map(|o| o.as_float().ok()).map(|v| v as f64).collect();
+                (v.len() == 4).then(|| [v[0], v[1], v[2], v[3]])
+            }) {
+                annot.set(b"Rect", map_rect(rect));
+            }
+            if let Ok(points) = annot.get(b"QuadPoints").and_then(|o| o.as_array()) {
+                let moved: Vec<Object> = points
+                    .iter()
+                    .enumerate()
+                    .map(|(i, o)| {
+                        let offset = if i % 2 == 0 { dx } else { dy };
+                        Object::Real((o.as_float().unwrap_or(0.0) as f64 * scale + offset) as f32)
+                    })
+                    .collect();
+                annot.set(b"QuadPoints", moved);
+            }
+        }
+
+        let media_box: Vec<Object> = vec![0.into(), 0.into(), Object::Real(new_width as f32), Object::Real(new_height as f32)];
+        let page = doc.get_dictionary_mut(page_id)?;
+        page.set(b"Contents", Object::Array(contents));
+        page.set(b"MediaBox", media_box.clone());
+        page.remove(b"CropBox");
+        // A CropBox inherited from the page tree would still apply in the old
+        // coordinates; override it with the new MediaBox.
+        if inherited_attr(&doc, page_id, b"CropBox").is_some() {
+            doc.get_dictionary_mut(page_id)?.set(b"CropBox", media_box);
+        }
+        let page = doc.get_dictionary_mut(page_id)?;
+        for (key, rect) in other_boxes {
+            page.set(key, rect);
+        }
+    }
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageBoxIssue {
+    /// The box that violates the constraint, e.g. "CropBox".
+    pub box_name: String,
+    /// The rule it breaks, e.g. "CropBox extends outside MediaBox".
+    pub constraint: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageBoxReport {
+    pub page_number: u32,
+    pub issues: Vec<PageBoxIssue>,
+}
+
+/// Flags page boxes that break the prepress nesting rules:
+/// Art ⊆ Trim ⊆ Bleed ⊆ Media and Crop ⊆ Media. Only pages with issues are
+/// returned.
+#[tauri::command]
+fn check_page_boxes(path: String) -> AppResult<Vec<PageBoxReport>> {
+    const EPSILON: f64 = 0.01;
+    let contains = |outer: [f64; 4], inner: [f64; 4]| {
+        inner[0] >= outer[0] - EPSILON
+            && inner[1] >= outer[1] - EPSILON
+            && inner[2] <= outer[2] + EPSILON
+            && inner[3] <= outer[3] + EPSILON
+    };
+
+    let doc = load_pdf(&path)?;
+    let mut reports = Vec::new();
+
+    for (page_number, page_id) in doc.get_pages() {
+        let inherited_box = |name: &[u8]| inherited_attr(&doc, page_id, name).and_then(|o| parse_rect(&doc, o)).map(normalize_rect);
+        // BleedBox, TrimBox and ArtBox aren't inheritable; one on a Pages
+        // node means nothing to the pages under it.
+        let own_box = |name: &[u8]| {
+            doc.get_dictionary(page_id)
+                .and_then(|page| page.get(name))
+                .ok()
+                .and_then(|o| parse_rect(&doc, o))
+                .map(normalize_rect)
+        };
+        let media = inherited_box(b"MediaBox");
+        let crop = inherited_box(b"CropBox");
+        let bleed = own_box(b"BleedBox");
+        let trim = own_box(b"TrimBox");
+        let art = own_box(b"ArtBox");
+
+        let mut issues = Vec::new();
+        let mut issue = |box_name: &str, constraint: String| {
+            issues.push(PageBoxIssue {
+                box_name: box_name.to_string(),
+                constraint,
+            });
+        };
+
+        for (name, rect) in [("MediaBox", media), ("CropBox", crop), ("BleedBox", bleed), ("TrimBox", trim), ("ArtBox", art)] {
+            if let Some(r) = rect {
+                if r[2] - r[0] <= EPSILON || r[3] - r[1] <= EPSILON {
+                    issue(name, format!("{} has zero or negative area", name));
+                }
+            }
+        }
+
+        let media = match media {
+            Some(m) => m,
+            None => {
+                issue("MediaBox", "MediaBox is missing".to_string());
+                reports.push(PageBoxReport { page_number, issues });
+                continue;
+            }
+        };
+        if let Some(c) = crop {
+            if !contains(media, c) {
+                issue("CropBox", "CropBox extends outside MediaBox".to_string());
+            }
+        }
+        if let Some(b) = bleed {
+            if !contains(media, b) {
+                issue("BleedBox", "BleedBox extends outside MediaBox".to_string());
+            }
+        }
+        if let Some(t) = trim {
+            match bleed {
+                Some(b) if !contains(b, t) => issue("TrimBox", "TrimBox extends outside BleedBox".to_string()),
+                None if !contains(media, t) => issue("TrimBox", "TrimBox extends outside MediaBox".to_string()),
+                _ => {}
+            }
+        }
+        if let Some(a) = art {
+            match (trim, bleed) {
+                (Some(t), _) if !contains(t, a) => issue("ArtBox", "ArtBox extends outside TrimBox".to_string()),
+                (None, Some(b)) if !contains(b, a) => issue("ArtBox", "ArtBox extends outside BleedBox".to_string()),
+                (None, None) if !contains(media, a) => issue("ArtBox", "ArtBox extends outside MediaBox".to_string()),
+                _ => {}
+            }
+        }
+
+        if !issues.is_empty() {
+            reports.push(PageBoxReport { page_number, issues });
+        }
+    }
+
+    Ok(reports)
+}
+
+/// Merges `paths` in order. `cover` and `back` are placed before and after the
+/// body and are left out of any `sort`. With `independent_fields`, form fields
+/// from every source stay fillable, and fields whose name is already taken are
+/// renamed so filled copies of one form keep their own values.
 #[tauri::command]
-fn merge_pdfs(paths: Vec<String>, output_path: String) -> AppResult<()> {
+#[allow(clippy::too_many_arguments)]
+fn merge_pdfs(
+    mut paths: Vec<String>,
+    output_path: String,
+    overwrite: Option<bool>,
+    sort: Option<SortKey>,
+    cover: Option<String>,
+    back: Option<String>,
+    independent_fields: Option<bool>,
+    target_version: Option<String>,
+) -> AppResult<MergeResult> {
     if paths.is_empty() {
         return Err(AppError::Validation("No files to merge.".to_string()));
     }
-    
+    // File dialogs return selections in no particular order.
+    if let Some(key) = sort {
+        sort_paths(&mut paths, key)?;
+    }
+    if let Some(cover) = cover {
+        paths.insert(0, cover);
+    }
+    paths.extend(back);
+    ensure_output_target(&output_path, &paths, overwrite.unwrap_or(false))?;
+    ensure_output_writable(&output_path)?;
+
+    let mut merged = merge_documents(&paths, independent_fields.unwrap_or(false))?;
+    let outlines: Vec<_> = merged.source_outlines.drain(..).flatten().flatten().collect();
+    attach_outlines(&mut merged.doc, outlines)?;
+
+    apply_target_version(&mut merged.doc, target_version.as_deref())?;
+    merged.doc.save(resolve_path(&output_path))?;
+    Ok(merge_result(output_path, merged))
+}
+
+/// Merges `paths` in order under a fresh outline with one bookmark per file,
+/// pointing at its first page. Bookmarks are titled from `titles`, one per
+/// path, or else the file stems; each source's own bookmarks are nested
+/// (collapsed) under its entry.
+#[tauri::command]
+fn merge_pdfs_with_bookmarks(
+    paths: Vec<String>,
+    output_path: String,
+    titles: Option<Vec<String>>,
+    overwrite: Option<bool>,
+) -> AppResult<MergeResult> {
+    if paths.is_empty() {
+        return Err(AppError::Validation("No files to merge.".to_string()));
+    }
+    let titles = match titles {
+        Some(titles) if titles.len() != paths.len() => {
+            return Err(AppError::Validation(format!(
+                "Got {} bookmark titles for {} files.",
+                titles.len(),
+                paths.len()
+            )))
+        }
+        Some(titles) => titles,
+        None => paths
+            .iter()
+            .map(|p| {
+                resolve_path(p)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            })
+            .collect(),
+    };
+    ensure_output_target(&output_path, &paths, overwrite.unwrap_or(false))?;
+    ensure_output_writable(&output_path)?;
+
+    let mut merged = merge_documents(&paths, false)?;
+    let doc = &mut merged.doc;
+    let pages: Vec<lopdf::ObjectId> = doc.get_pages().into_values().collect();
+    let mut file_items = Vec::new();
+    let mut first_page = 0;
+    for ((title, (_, page_count)), source_outline) in
+        titles.iter().zip(&merged.page_counts).zip(merged.source_outlines.drain(..))
+    {
+        let Some(&page_id) = pages.get(first_page) else { break };
+        first_page += *page_count as usize;
+        let item_id = doc.add_object(dictionary! {
+            "Title" => lopdf::text_string(title),
+            "Dest" => vec![Object::Reference(page_id), "Fit".into()],
+        });
+        if let Some(children) = source_outline {
+            link_outline_children(doc, item_id, &children, false)?;
+        }
+        file_items.push(item_id);
+    }
+    attach_outlines(doc, file_items)?;
+
+    merged.doc.save(resolve_path(&output_path))?;
+    Ok(merge_result(output_path, merged))
+}
+
+/// Inserts every page of `insert_path` into `base_path` before the page at
+/// 0-based `at_index`; an index past the end appends. The inserted pages keep
+/// their fonts, images and inherited attributes. Only the base document's
+/// catalog survives, so the inserted file's bookmarks and form are dropped.
+#[tauri::command]
+fn insert_pages(base_path: String, insert_path: String, at_index: u32, output_path: String) -> AppResult<()> {
+    ensure_output_writable(&output_path)?;
+    let mut base = load_pdf(&base_path)?;
+    let mut insert = load_pdf(&insert_path)?;
+
+    // Shift the inserted document's IDs past the base's, as merging does.
+    insert.renumber_objects_with(base.max_id);
+    base.max_id = insert.max_id;
+    let inserted: Vec<lopdf::ObjectId> = insert.get_pages().into_values().collect();
+    if inserted.is_empty() {
+        return Err(AppError::Validation(format!("{} has no pages to insert.", insert_path)));
+    }
+    base.objects.extend(insert.objects);
+
+    let mut page_ids: Vec<lopdf::ObjectId> = base.get_pages().into_values().collect();
+    let at = (at_index as usize).min(page_ids.len());
+    page_ids.splice(at..at, inserted);
+    // Reparents the inserted pages onto the base root, copying down what
+    // they inherited from their own page tree, and drops the leftovers of
+    // the inserted document's catalog.
+    rebuild_page_tree(&mut base, page_ids)?;
+    base.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Builds the report for a merged document that has been saved.
+fn merge_result(output_path: String, merged: MergedDocument) -> MergeResult {
+    let page_count = merged.page_counts.iter().map(|(_, count)| count).sum();
+    let mut warnings = Vec::new();
+    if merged.output_intents_conflict {
+        warnings.push(
+            "Sources use different output intents (colour profiles); the first one was kept.".to_string(),
+        );
+    }
+    MergeResult {
+        output_path,
+        page_counts: merged.page_counts,
+        page_count,
+        output_intents_conflict: merged.output_intents_conflict,
+        warnings,
+        renamed_fields: merged.renamed_fields,
+    }
+}
+
+/// A merged document before it is saved, with what `MergeResult` reports
+/// about it.
+struct MergedDocument {
+    doc: Document,
+    page_counts: Vec<(String, u32)>,
+    output_intents_conflict: bool,
+    renamed_fields: Vec<(String, String)>,
+    /// Each source's detached outline (see `detach_outline`), in order. The
+    /// merged document has none until they are attached.
+    source_outlines: Vec<Option<Vec<lopdf::ObjectId>>>,
+}
+
+/// Appends the pages of each of `paths` (at least one) to the first
+/// document's page tree.
+fn merge_documents(paths: &[String], independent_fields: bool) -> AppResult<MergedDocument> {
     // We start with the first document as our base using memory mapping
     let mut final_doc = load_pdf(&paths[0])?;
+    let mut page_counts = vec![(paths[0].clone(), final_doc.get_pages().len() as u32)];
+    let mut intent = output_intent_key(&final_doc);
+    let mut output_intents_conflict = false;
+    let mut used_field_names: std::collections::HashSet<String> = form_field_roots(&final_doc)
+        .into_iter()
+        .filter_map(|id| final_doc.get_dictionary(id).ok()?.get(b"T").ok().map(decode_pdf_text))
+        .collect();
+    let mut renamed_fields = Vec::new();
+    let mut source_outlines = vec![detach_outline(&mut final_doc)];
 
     // Append subsequent documents
     for path_str in paths.iter().skip(1) {
@@ -693,15 +2939,42 @@ fn merge_pdfs(paths: Vec<String>, output_path: String) -> AppResult<()> {
          // 1. Shift IDs of the incoming doc so they don't collide with final_doc
          doc.renumber_objects_with(final_doc.max_id);
          final_doc.max_id = doc.max_id;
+         // Outline items move with the other objects in step 3 and keep
+         // pointing at the same (renumbered) pages.
+         source_outlines.push(detach_outline(&mut doc));
+
+         // Only the base catalog survives, so carry over the first declared
+         // output intent and note any source that declares a different one.
+         if let Some(key) = output_intent_key(&doc) {
+             match &intent {
+                 Some(existing) => output_intents_conflict |= *existing != key,
+                 None => {
+                     let intents = doc.catalog()?.get(b"OutputIntents")?.clone();
+                     final_doc.catalog_mut()?.set("OutputIntents", intents);
+                     intent = Some(key);
+                 }
+             }
+         }
          
          // 2. Get pages BEFORE moving objects
          // `doc.get_pages()` returns BTreeMap<u32, ObjectId>.
          let pages: Vec<lopdf::ObjectId> = doc.get_pages().values().cloned().collect();
+         page_counts.push((path_str.clone(), pages.len() as u32));
+         let field_roots = form_field_roots(&doc);
+         let field_names: Vec<String> = collect_form_fields(&doc).into_iter().map(|f| f.name).collect();
          
          // 3. Add all objects from incoming doc to final_doc
          for (id, obj) in doc.objects {
              final_doc.objects.insert(id, obj);
          }
+         if independent_fields && !field_roots.is_empty() {
+             renamed_fields.extend(adopt_form_fields(
+                 &mut final_doc,
+                 &field_roots,
+                 &field_names,
+                 &mut used_field_names,
+             )?);
+         }
          
          // 4. Append pages to final_doc's page tree.
          let catalog_id = final_doc.trailer.get(b"Root")?.as_reference()?;
@@ -723,14 +2996,220 @@ fn merge_pdfs(paths: Vec<String>, output_path: String) -> AppResult<()> {
              }
          }
     }
-    
-    final_doc.save(output_path)?;
+    Ok(MergedDocument {
+        doc: final_doc,
+        page_counts,
+        output_intents_conflict,
+        renamed_fields,
+        source_outlines,
+    })
+}
+
+/// Takes a document's outline apart for merging: returns its top-level items
+/// in order and removes the root. Named destinations anywhere in the outline
+/// become explicit, since only the first document's name tree survives a
+/// merge.
+fn detach_outline(doc: &mut Document) -> Option<Vec<lopdf::ObjectId>> {
+    let root_ref = doc.catalog_mut().ok()?.remove(b"Outlines")?;
+    let root = doc.dereference(&root_ref).and_then(|(_, o)| o.as_dict()).ok()?.clone();
+    if let Object::Reference(root_id) = root_ref {
+        doc.objects.remove(&root_id);
+    }
+
+    let mut top_level = Vec::new();
+    let mut rewritten = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    // (item, is top level); the visited set guards against cycles.
+    let mut pending: Vec<(lopdf::ObjectId, bool)> = Vec::new();
+    let mut next = root.get(b"First").and_then(|o| o.as_reference()).ok();
+    while let Some(id) = next.filter(|&id| seen.insert(id)) {
+        pending.push((id, true));
+        next = doc.get_dictionary(id).and_then(|d| d.get(b"Next")).and_then(|o| o.as_reference()).ok();
+    }
+    while let Some((id, is_top)) = pending.pop() {
+        let Ok(item) = doc.get_dictionary(id) else { continue };
+        if is_top {
+            top_level.push(id);
+        }
+        if let LinkTarget::Page { dest, named: true } = goto_target(doc, item) {
+            let mut item = item.clone();
+            item.remove(b"A");
+            item.set("Dest", Object::Array(dest));
+            rewritten.push((id, item));
+        }
+        let mut child = item.get(b"First").and_then(|o| o.as_reference()).ok();
+        while let Some(child_id) = child.filter(|&id| seen.insert(id)) {
+            pending.push((child_id, false));
+            child = doc.get_dictionary(child_id).and_then(|d| d.get(b"Next")).and_then(|o| o.as_reference()).ok();
+        }
+    }
+    for (id, item) in rewritten {
+        doc.objects.insert(id, Object::Dictionary(item));
+    }
+    if top_level.is_empty() {
+        return None;
+    }
+    // `pending` is a stack, so the top-level items came off it last to first.
+    top_level.reverse();
+    Some(top_level)
+}
+
+/// Puts `items` under a new /Outlines root in `doc`'s catalog, in order, as
+/// the top-level bookmarks. Does nothing when there are no items.
+fn attach_outlines(doc: &mut Document, items: Vec<lopdf::ObjectId>) -> AppResult<()> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    let root_id = doc.add_object(dictionary! { "Type" => "Outlines" });
+    link_outline_children(doc, root_id, &items, true)?;
+    doc.catalog_mut()?.set("Outlines", Object::Reference(root_id));
+    Ok(())
+}
+
+/// Makes `items` the children of `parent`, in order, setting their /Parent,
+/// /Prev and /Next and the parent's /First, /Last and /Count (which are
+/// removed when there are no items). `open` picks whether the parent shows
+/// its children expanded.
+fn link_outline_children(
+    doc: &mut Document,
+    parent: lopdf::ObjectId,
+    items: &[lopdf::ObjectId],
+    open: bool,
+) -> AppResult<()> {
+    for (i, &id) in items.iter().enumerate() {
+        let item = doc.get_dictionary_mut(id)?;
+        item.set("Parent", Object::Reference(parent));
+        match i.checked_sub(1) {
+            Some(prev) => item.set("Prev", Object::Reference(items[prev])),
+            None => {
+                item.remove(b"Prev");
+            }
+        }
+        match items.get(i + 1) {
+            Some(&next) => item.set("Next", Object::Reference(next)),
+            None => {
+                item.remove(b"Next");
+            }
+        }
+    }
+    // Each item shows itself plus its open descendants.
+    let visible: i64 = items
+        .iter()
+        .map(|&id| 1 + doc.get_dictionary(id).and_then(|d| d.get(b"Count")).and_then(|o| o.as_i64()).unwrap_or(0).max(0))
+        .sum();
+    let parent = doc.get_dictionary_mut(parent)?;
+    match (items.first(), items.last()) {
+        (Some(&first), Some(&last)) => {
+            parent.set("First", Object::Reference(first));
+            parent.set("Last", Object::Reference(last));
+            // A negative count shows the entry closed.
+            parent.set("Count", if open { visible } else { -visible });
+        }
+        _ => {
+            parent.remove(b"First");
+            parent.remove(b"Last");
+            parent.remove(b"Count");
+        }
+    }
     Ok(())
 }
 
+/// Merges `paths` in order, then splits the result into parts no larger than
+/// `max_bytes` (see `SplitMode::BySize`), written to `output_dir` as
+/// `<output_prefix>_partN.pdf`. Returns the written parts in order, with a
+/// warning for each one still over the limit.
+#[tauri::command]
+async fn merge_capped(
+    paths: Vec<String>,
+    max_bytes: u64,
+    output_prefix: String,
+    output_dir: String,
+) -> AppResult<SplitResult> {
+    tauri::async_runtime::spawn_blocking(move || merge_capped_blocking(paths, max_bytes, output_prefix, output_dir))
+        .await
+        .map_err(|e| AppError::Validation(format!("Merge task failed: {}", e)))?
+}
+
+/// The body of `merge_capped`.
+fn merge_capped_blocking(
+    paths: Vec<String>,
+    max_bytes: u64,
+    output_prefix: String,
+    output_dir: String,
+) -> AppResult<SplitResult> {
+    if paths.is_empty() {
+        return Err(AppError::Validation("No files to merge.".to_string()));
+    }
+    let prefix = output_prefix.trim();
+    if prefix.is_empty() || prefix.contains(['/', '\\']) {
+        return Err(AppError::Validation("Output prefix must be a plain file name.".to_string()));
+    }
+    let out_dir_path = resolve_path(&output_dir);
+    if !out_dir_path.is_dir() {
+        return Err(AppError::Path("Output path is not a directory.".to_string()));
+    }
+    ensure_dir_writable(&out_dir_path)?;
+
+    let mut merged = merge_documents(&paths, false)?;
+    let outlines: Vec<_> = merged.source_outlines.drain(..).flatten().flatten().collect();
+    attach_outlines(&mut merged.doc, outlines)?;
+    let merged = merged.doc;
+    let options = PartOptions::default();
+    let planned = plan_split(&merged, &SplitMode::BySize { max_bytes }, &options)?;
+    let names = split_part_names(std::ffi::OsStr::new(prefix), &planned);
+    let pages = merged.get_pages();
+    let mut parts: Vec<SplitOutput> = Vec::with_capacity(planned.len());
+    let mut warnings = Vec::new();
+    for (i, (part, name)) in planned.iter().zip(&names).enumerate() {
+        let out_path = out_dir_path.join(name);
+        let written = options.prepared(&merged, &pages, part, i + 1).and_then(|prepared| {
+            fs::write(&out_path, &prepared.bytes)?;
+            Ok((prepared.bytes.len() as u64, prepared.dropped_links))
+        });
+        let (size, dropped_links) = match written {
+            Ok(written) => written,
+            Err(e) => {
+                // As with `split_pdf`, a failed run leaves no parts behind.
+                for output in &parts {
+                    let _ = fs::remove_file(&output.path);
+                }
+                return Err(e);
+            }
+        };
+        warnings.extend(dropped_links_warning(i + 1, dropped_links));
+        warnings.extend(over_limit_warning(part, i + 1, size, max_bytes));
+        parts.push(SplitOutput {
+            path: path_to_string(&out_path),
+            size,
+        });
+    }
+    Ok(SplitResult { parts, warnings })
+}
+
+/// Identifies a document's first output intent by its subtype and output
+/// condition (e.g. `GTS_PDFX` / `FOGRA39`), or `None` when it declares none.
+fn output_intent_key(doc: &Document) -> Option<(Vec<u8>, String)> {
+    let intents = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"OutputIntents"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_array())
+        .ok()?;
+    let intent = intents
+        .iter()
+        .find_map(|o| doc.dereference(o).and_then(|(_, o)| o.as_dict()).ok())?;
+    let subtype = intent.get(b"S").and_then(|o| o.as_name()).unwrap_or_default().to_vec();
+    let condition = intent
+        .get(b"OutputConditionIdentifier")
+        .and_then(|o| doc.dereference(o))
+        .map(|(_, o)| decode_pdf_text(o))
+        .unwrap_or_default();
+    Some((subtype, condition))
+}
+
 #[tauri::command]
 fn read_pdf_buffer(path: String) -> AppResult<Vec<u8>> {
-    let path = Path::new(&path);
+    let path = resolve_path(&path);
     if !path.is_file() {
          return Err(AppError::Path("Path is not a file.".to_string()));
     }
@@ -739,10 +3218,12 @@ fn read_pdf_buffer(path: String) -> AppResult<Vec<u8>> {
 }
 
 #[tauri::command]
-fn mix_pdfs(paths: Vec<String>, output_path: String) -> AppResult<()> {
+fn mix_pdfs(paths: Vec<String>, output_path: String, overwrite: Option<bool>) -> AppResult<()> {
     if paths.is_empty() {
         return Err(AppError::Validation("No files to mix.".to_string()));
     }
+    ensure_output_target(&output_path, &paths, overwrite.unwrap_or(false))?;
+    ensure_output_writable(&output_path)?;
 
     // 1. Initialize an empty document to hold everything
     let mut final_doc = Document::new();
@@ -813,7 +3294,7 @@ fn mix_pdfs(paths: Vec<String>, output_path: String) -> AppResult<()> {
 
     // 8. Prune and Save
     final_doc.prune_objects();
-    final_doc.save(output_path)?;
+    final_doc.save(resolve_path(&output_path))?;
 
     Ok(())
 }
@@ -828,77 +3309,294 @@ fn protect_pdf(
     user_password: String,
     owner_password: Option<String>,
     output_path: String,
+    target_version: Option<String>,
+    algorithm: Option<EncryptionAlgorithm>,
+    permissions: Option<PdfPermissions>,
 ) -> AppResult<()> {
-    use lopdf::encryption::{EncryptionVersion, EncryptionState, Permissions};
-    use lopdf::Object;
-    use std::convert::TryFrom;
+    let algorithm = algorithm.unwrap_or_default();
+    let mut doc = load_pdf(&path)?;
+    apply_target_version(&mut doc, target_version.as_deref())?;
+    if target_version.is_some() && version_key(&doc.version) < algorithm.min_version() {
+        let (major, minor) = algorithm.min_version();
+        return Err(AppError::Validation(format!(
+            "This encryption needs PDF {}.{} or later.",
+            major, minor
+        )));
+    }
+    encrypt_document(&mut doc, &user_password, owner_password, algorithm, &permissions.unwrap_or_default())?;
+    doc.save(resolve_path(&output_path))?;
+
+    Ok(())
+}
 
+/// Writes an unencrypted copy of a PDF that opens without a password but carries
+/// permission restrictions (no printing, copying, ...). The owner password is
+/// still required: opening the file is not the same as being allowed to lift
+/// its restrictions.
+#[tauri::command]
+fn remove_restrictions(path: String, owner_password: String, output_path: String) -> AppResult<()> {
+    ensure_output_writable(&output_path)?;
     let mut doc = load_pdf(&path)?;
 
-    // PDF encryption requires /ID array in trailer. Add if missing.
+    let state = match doc.encryption_state.take() {
+        Some(state) => state,
+        None if doc.is_encrypted() => {
+            return Err(AppError::Validation(
+                "PDF requires a password to open; unlock it instead".to_string(),
+            ))
+        }
+        None => return Err(AppError::Validation("PDF has no permission restrictions".to_string())),
+    };
+
+    // The reader drops /Encrypt once the empty user password opens the file, so
+    // re-attach the encryption dictionary just long enough to check the owner password.
+    let encrypt_dict = state
+        .encode()
+        .map_err(|e| AppError::Validation(format!("Failed to read encryption settings: {}", e)))?;
+    let encrypt_id = doc.add_object(encrypt_dict);
+    doc.trailer.set("Encrypt", Object::Reference(encrypt_id));
+    let authenticated = doc.authenticate_owner_password(&owner_password).is_ok();
+    doc.trailer.remove(b"Encrypt");
+    doc.objects.remove(&encrypt_id);
+
+    if !authenticated {
+        return Err(AppError::Validation("Incorrect owner password".to_string()));
+    }
+
+    doc.save(resolve_path(&output_path))?;
+
+    Ok(())
+}
+
+/// Writes a copy of a password-protected PDF with its encryption removed.
+/// `password` may be the user or the owner password.
+///
+/// `load_pdf` stays password-free: a file that needs a password comes back
+/// still encrypted (and without its objects), which the other commands reject.
+/// Only this command takes a password, so it reloads the file with it rather
+/// than threading a password through every load.
+#[tauri::command]
+fn decrypt_pdf(path: String, password: String, output_path: String) -> AppResult<()> {
+    ensure_output_writable(&output_path)?;
+    let doc = load_pdf(&path)?;
+    if !doc.is_encrypted() {
+        // Opening without a password means only restrictions remain, and
+        // lifting those takes the owner password.
+        return Err(AppError::Validation(if doc.was_encrypted() {
+            "PDF has no open password; remove its restrictions instead".to_string()
+        } else {
+            "PDF is not encrypted".to_string()
+        }));
+    }
+    drop(doc);
+
+    let file = fs::File::open(resolve_path(&path))?;
+    // SAFETY: see load_pdf.
+    let mmap = unsafe { Mmap::map(&file)? };
+    let mut doc = match Document::load_mem_with_password(&mmap, &password) {
+        Ok(doc) => doc,
+        Err(lopdf::Error::InvalidPassword) => {
+            return Err(AppError::Validation("Incorrect password".to_string()))
+        }
+        Err(e) => return Err(AppError::Pdf(e)),
+    };
+    // The reader already decrypted the objects and dropped /Encrypt.
+    doc.encryption_state = None;
+    doc.save(resolve_path(&output_path))?;
+
+    Ok(())
+}
+
+/// Builds a random trailer /ID. The PDF spec requires an array of two identical
+/// byte strings for newly created documents.
+fn fresh_document_id() -> Object {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let id_bytes: Vec<u8> = (0..16).map(|_| rng.gen::<u8>()).collect();
+    Object::Array(vec![
+        Object::String(id_bytes.clone(), lopdf::StringFormat::Hexadecimal),
+        Object::String(id_bytes, lopdf::StringFormat::Hexadecimal),
+    ])
+}
+
+/// Declares Adobe extension level `level` to PDF 1.7 in the catalog
+/// /Extensions dictionary, unless a higher level is already declared.
+fn declare_adbe_extension(doc: &mut Document, level: i64) -> AppResult<()> {
+    let mut extensions = doc
+        .catalog()?
+        .get(b"Extensions")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .cloned()
+        .unwrap_or_else(|_| lopdf::Dictionary::new());
+    let current = extensions
+        .get(b"ADBE")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .and_then(|adbe| adbe.get(b"ExtensionLevel"))
+        .and_then(|o| o.as_i64())
+        .unwrap_or(0);
+    if current < level {
+        extensions.set("ADBE", dictionary! {"BaseVersion" => "1.7", "ExtensionLevel" => level});
+        doc.catalog_mut()?.set("Extensions", extensions);
+    }
+    Ok(())
+}
+
+/// Encrypts a loaded document in place with the given passwords, raising the
+/// header version if the cipher is newer than the file (AES-256 in a 1.x file
+/// also declares Adobe extension level 8). Restricted `permissions` need a
+/// separate owner password, since anyone who opens the file with the owner
+/// password may do everything.
+fn encrypt_document(
+    doc: &mut Document,
+    user_password: &str,
+    owner_password: Option<String>,
+    algorithm: EncryptionAlgorithm,
+    permissions: &PdfPermissions,
+) -> AppResult<()> {
+    use lopdf::encryption::crypt_filters::{Aes128CryptFilter, Aes256CryptFilter, CryptFilter};
+    use lopdf::encryption::{EncryptionVersion, EncryptionState, Permissions};
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use std::convert::TryFrom;
+    use std::sync::Arc;
+
+    // RC4 and AES-128 derive the key from the trailer /ID, so it must exist.
+    // AES-256 uses a random key instead, but readers still expect an /ID.
     if doc.trailer.get(b"ID").is_err() {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        let id_bytes: Vec<u8> = (0..16).map(|_| rng.gen::<u8>()).collect();
-        
-        // PDF spec requires array of two identical byte strings for new documents
-        let id_array = Object::Array(vec![
-            Object::String(id_bytes.clone(), lopdf::StringFormat::Hexadecimal),
-            Object::String(id_bytes, lopdf::StringFormat::Hexadecimal),
-        ]);
-        doc.trailer.set(b"ID", id_array);
+        doc.trailer.set(b"ID", fresh_document_id());
+    }
+    if version_key(&doc.version) < algorithm.min_version() {
+        let (major, minor) = algorithm.min_version();
+        doc.version = format!("{}.{}", major, minor);
+    }
+    // PDF 1.7 only has AES-256 (revision 6) as Adobe's extension level 8.
+    if matches!(algorithm, EncryptionAlgorithm::Aes256) && version_key(&doc.version) < (2, 0) {
+        declare_adbe_extension(doc, 8)?;
     }
 
-    // Use owner password if provided, otherwise use user password for both
-    let owner_pwd = owner_password.unwrap_or_else(|| user_password.clone());
+    let permissions = permissions.to_lopdf();
+    let has_owner_password = owner_password.as_deref().is_some_and(|p| !p.is_empty() && p != user_password);
+    if permissions != Permissions::all() && !has_owner_password {
+        return Err(AppError::Validation(
+            "Permission restrictions need an owner password that differs from the open password; \
+             without one, anyone who can open the file can lift them."
+                .to_string(),
+        ));
+    }
 
-    // Create encryption version with V2 (128-bit RC4, compatible with most readers)
-    let encryption_version = EncryptionVersion::V2 {
-        document: &doc,
-        owner_password: &owner_pwd,
-        user_password: &user_password,
-        key_length: 128,
-        permissions: Permissions::default(),
+    // Use owner password if provided, otherwise use user password for both
+    let owner_pwd = owner_password.unwrap_or_else(|| user_password.to_string());
+
+    let mut file_encryption_key = [0u8; 32];
+    let encryption_version = match algorithm {
+        EncryptionAlgorithm::Rc4_128 => EncryptionVersion::V2 {
+            document: doc,
+            owner_password: &owner_pwd,
+            user_password,
+            key_length: 128,
+            permissions,
+        },
+        EncryptionAlgorithm::Aes128 => {
+            let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes128CryptFilter);
+            EncryptionVersion::V4 {
+                document: doc,
+                encrypt_metadata: true,
+                crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+                stream_filter: b"StdCF".to_vec(),
+                string_filter: b"StdCF".to_vec(),
+                owner_password: &owner_pwd,
+                user_password,
+                permissions,
+            }
+        }
+        EncryptionAlgorithm::Aes256 => {
+            rand::thread_rng().fill(&mut file_encryption_key);
+            let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes256CryptFilter);
+            EncryptionVersion::V5 {
+                encrypt_metadata: true,
+                crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+                file_encryption_key: &file_encryption_key,
+                stream_filter: b"StdCF".to_vec(),
+                string_filter: b"StdCF".to_vec(),
+                owner_password: &owner_pwd,
+                user_password,
+                permissions,
+            }
+        }
     };
 
     // Convert to EncryptionState
     let encryption_state = EncryptionState::try_from(encryption_version)
         .map_err(|e| AppError::Validation(format!("Failed to create encryption state: {}", e)))?;
 
-    // Clone the document and encrypt
-    let mut encrypted_doc = doc.clone();
-    encrypted_doc.encrypt(&encryption_state)
+    doc.encrypt(&encryption_state)
         .map_err(|e| AppError::Validation(format!("Failed to encrypt PDF: {}", e)))?;
 
-    encrypted_doc.save(&output_path)?;
-
     Ok(())
 }
 
-
-
+/// Rotates pages in place. By default each angle is added to the page's
+/// current rotation; with `RotationMode::Absolute` it replaces it.
 #[tauri::command]
-fn rotate_pdf_pages(path: String, rotations: std::collections::HashMap<u32, i32>) -> AppResult<()> {
-    // Validate all angles are multiples of 90
-    for (&page, &angle) in &rotations {
-        if angle % 90 != 0 {
-            return Err(AppError::Validation(
-                format!("Rotation for page {} must be a multiple of 90 degrees, got {}", page, angle),
-            ));
-        }
-    }
-
+fn rotate_pdf_pages(
+    path: String,
+    rotations: std::collections::HashMap<u32, i32>,
+    mode: Option<RotationMode>,
+) -> AppResult<()> {
     // Load the document using memory mapping
     let mut doc = load_pdf(&path)?;
+    apply_rotations(&mut doc, &rotations, mode.unwrap_or_default())?;
+    // 8. Save the document
+    doc.save(resolve_path(&path))?;
+    Ok(())
+}
 
-    // Iterate through pages
+/// Rotates a PDF held in memory and returns the rotated bytes.
+#[tauri::command]
+fn rotate_pdf_bytes(
+    data: Vec<u8>,
+    rotations: std::collections::HashMap<u32, i32>,
+    mode: Option<RotationMode>,
+) -> AppResult<Vec<u8>> {
+    let mut doc = load_pdf_mem(&data)?;
+    apply_rotations(&mut doc, &rotations, mode.unwrap_or_default())?;
+    save_pdf_to_vec(&mut doc)
+}
+
+/// Adds each page's requested angle to its current /Rotate, or in absolute
+/// mode sets /Rotate to it.
+fn apply_rotations(
+    doc: &mut Document,
+    rotations: &std::collections::HashMap<u32, i32>,
+    mode: RotationMode,
+) -> AppResult<()> {
+    // Validate all angles are multiples of 90
+    for (&page, &angle) in rotations {
+        match mode {
+            RotationMode::Relative if angle % 90 != 0 => {
+                return Err(AppError::Validation(
+                    format!("Rotation for page {} must be a multiple of 90 degrees, got {}", page, angle),
+                ));
+            }
+            RotationMode::Absolute if !matches!(angle, 0 | 90 | 180 | 270) => {
+                return Err(AppError::Validation(
+                    format!("Rotation for page {} must be 0, 90, 180 or 270 degrees, got {}", page, angle),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    // Iterate through pages
     // doc.get_pages() returns a BTreeMap<u32, ObjectId> mapping page_number (1-based) to ObjectId
     for (page_num, page_id) in doc.get_pages() {
         // If this page is in our rotations map
         if let Some(&angle_change) = rotations.get(&page_num) {
-            // Get current rotation
+            // Get current rotation; an absolute angle starts from zero
             let mut current_rotation = 0;
-            if let Ok(page_dict) = doc.get_dictionary(page_id) {
+            if let (RotationMode::Relative, Ok(page_dict)) = (mode, doc.get_dictionary(page_id)) {
                 if let Ok(rot) = page_dict.get(b"Rotate") {
                     if let Ok(val) = rot.as_i64() {
                         current_rotation = val as i32;
@@ -919,8 +3617,6 @@ fn rotate_pdf_pages(path: String, rotations: std::collections::HashMap<u32, i32>
             }
         }
     }
-    // 8. Save the document
-    doc.save(path)?;
     Ok(())
 }
 
@@ -929,11 +3625,97 @@ async fn compress_pdf_v2(
     path: String,
     output_path: String,
     settings: CompressionSettings,
+    keep_smallest: Option<bool>,
+    target_version: Option<String>,
 ) -> AppResult<CompressionResult> {
-    let original_size = std::fs::metadata(&path)?.len();
+    let original_size = std::fs::metadata(resolve_path(&path))?.len();
 
     let mut doc = load_pdf(&path)?;
-    
+    let tagged = has_structure_tree(&doc);
+    let stats = compress_document(&mut doc, &settings)?;
+    apply_target_version(&mut doc, target_version.as_deref())?;
+    let compressed = save_pdf_to_vec(&mut doc)?;
+
+    // Recompressing an already optimized file can make it bigger; unless the
+    // caller opts out, never hand the user a larger "compressed" file.
+    let grew = compressed.len() as u64 > original_size;
+    let kept_original = grew && keep_smallest.unwrap_or(true);
+    if kept_original {
+        // Copying a file onto itself truncates it, so compare the files
+        // rather than the path text (`./a.pdf`, symlinks, case-insensitive
+        // names).
+        if !is_same_file(&resolve_path(&path), &resolve_path(&output_path)) {
+            fs::copy(resolve_path(&path), resolve_path(&output_path))?;
+        }
+    } else {
+        fs::write(resolve_path(&output_path), &compressed)?;
+    }
+
+    let compressed_size = std::fs::metadata(resolve_path(&output_path))?.len();
+    let accessibility_affected = tagged && settings.remove_structure_tree && !kept_original;
+
+    let mut warnings = Vec::new();
+    if accessibility_affected {
+        warnings.push("Accessibility tags (the structure tree) were removed.".to_string());
+    }
+    if stats.skipped_images > 0 && !kept_original {
+        warnings.push(format!(
+            "{} image{} left uncompressed (unsupported format).",
+            stats.skipped_images,
+            if stats.skipped_images == 1 { "" } else { "s" }
+        ));
+    }
+
+    Ok(CompressionResult {
+        original_size,
+        compressed_size,
+        success: true,
+        ratio: if original_size > 0 { compressed_size as f32 / original_size as f32 } else { 1.0 },
+        grew,
+        kept_original,
+        accessibility_affected,
+        images_recompressed: if kept_original { 0 } else { stats.recompressed_images },
+        // None of the savings reached the output when the original was kept.
+        breakdown: if kept_original { Vec::new() } else { stats.breakdown },
+        warnings,
+    })
+}
+
+/// Compresses a PDF held in memory and returns the compressed bytes, so
+/// pipelines can chain operations without intermediate files.
+#[tauri::command]
+async fn compress_pdf_bytes(data: Vec<u8>, settings: CompressionSettings) -> AppResult<Vec<u8>> {
+    let mut doc = load_pdf_mem(&data)?;
+    compress_document(&mut doc, &settings)?;
+    save_pdf_to_vec(&mut doc)
+}
+
+/// What `compress_document` did to a document's images.
+#[derive(Debug, Default)]
+struct CompressionStats {
+    /// Images replaced by a smaller re-encoding.
+    recompressed_images: u32,
+    /// Of those, images that were losslessly compressed and are now JPEG.
+    jpeg_converted_images: u32,
+    /// Images left untouched because their format can't be decoded safely.
+    skipped_images: u32,
+    /// Of those, images in CMYK.
+    skipped_cmyk_images: u32,
+    breakdown: Vec<StepSavings>,
+}
+
+/// Applies the enabled compression steps to a loaded document.
+fn compress_document(doc: &mut Document, settings: &CompressionSettings) -> AppResult<CompressionStats> {
+    let mut stats = CompressionStats::default();
+    // Drop what was already unreachable, so it isn't credited to the first step.
+    doc.prune_objects();
+    let mut record = |doc: &mut Document, step: &str| {
+        stats.breakdown.push(StepSavings {
+            step: step.to_string(),
+            bytes_saved: prune_measured(doc),
+        });
+    };
+
     // 1. Basic cleaning
     if settings.remove_metadata {
         doc.trailer.remove(b"Info");
@@ -942,6 +3724,7 @@ async fn compress_pdf_v2(
         if let Ok(root) = doc.get_object_mut(root_id).and_then(|o| o.as_dict_mut()) {
             root.remove(b"Metadata");
         }
+        record(doc, "metadata");
     }
     
     if settings.remove_thumbnails {
@@ -950,10 +3733,12 @@ async fn compress_pdf_v2(
                 page.remove(b"Thumb");
             }
         }
+        record(doc, "thumbnails");
     }
     
     if settings.remove_application_data {
         doc.trailer.remove(b"PieceInfo");
+        record(doc, "application_data");
     }
     
     if settings.remove_structure_tree {
@@ -961,6 +3746,12 @@ async fn compress_pdf_v2(
         if let Ok(root) = doc.get_object_mut(root_id).and_then(|o| o.as_dict_mut()) {
             root.remove(b"StructTreeRoot");
         }
+        record(doc, "structure_tree");
+    }
+
+    if settings.flatten_form_fields {
+        flatten_form_fields(doc)?;
+        record(doc, "form_fields");
     }
 
     if settings.remove_annotations {
@@ -969,341 +3760,5778 @@ async fn compress_pdf_v2(
                 page.remove(b"Annots");
             }
         }
+        record(doc, "annotations");
     }
 
     // 2. Image Compression
-    // This is the heavy part. We iterate over all XObjects and re-compress them if they are images.
+    // This is the heavy part. Each image is decoded and re-encoded on its own:
+    // photographs go to JPEG, flat-colour artwork to lossless Flate.
+    let extents = image_page_extents(doc);
+    let soft_masks: std::collections::HashSet<lopdf::ObjectId> = doc
+        .objects
+        .values()
+        .filter_map(|o| o.as_stream().ok())
+        .filter_map(|s| s.dict.get(b"SMask").and_then(|o| o.as_reference()).ok())
+        .collect();
     let object_ids: Vec<lopdf::ObjectId> = doc.objects.keys().cloned().collect();
+    let mut image_savings = 0;
     for id in object_ids {
-        if let Ok(obj) = doc.get_object(id) {
-            if let Ok(dict) = obj.as_dict() {
-                if dict.get(b"Subtype").map_or(false, |s| s.as_name().map_or(false, |n| n == b"Image")) {
-                    // It's an image. Re-compress based on settings.
-                    // For now, we'll implement a basic filter check and re-encoding if needed.
-                    // In a production environment, we'd use 'image' crate to downscale/re-encode.
-                    // To keep implementation safe and robust for this first pass, we'll use lopdf's internal filters.
+        let mut was_jpeg = false;
+        let replacement = match doc.get_object(id) {
+            Ok(Object::Stream(stream))
+                if stream.dict.get(b"Subtype").and_then(|s| s.as_name()).is_ok_and(|n| n == b"Image") =>
+            {
+                match decode_image_xobject(doc, stream).filter(|decoded| decoded.faithful) {
+                    Some(decoded) => {
+                        was_jpeg = decoded.jpeg;
+                        recompress_image(
+                            decoded,
+                            stream,
+                            settings,
+                            extents.get(&id).copied(),
+                            soft_masks.contains(&id),
+                        )
+                    }
+                    None => {
+                        stats.skipped_images += 1;
+                        let space = stream.dict.get(b"ColorSpace").ok().and_then(|cs| ImageColorSpace::resolve(doc, cs));
+                        if matches!(space, Some(ImageColorSpace::Cmyk)) {
+                            stats.skipped_cmyk_images += 1;
+                        }
+                        None
+                    }
                 }
             }
+            _ => None,
+        };
+        if let Some(stream) = replacement {
+            let new_len = stream.content.len();
+            if !was_jpeg && stream.dict.get(b"Filter").and_then(|o| o.as_name()).is_ok_and(|n| n == b"DCTDecode") {
+                stats.jpeg_converted_images += 1;
+            }
+            if let Some(Object::Stream(old)) = doc.objects.insert(id, Object::Stream(stream)) {
+                // Replacements are only kept when smaller.
+                image_savings += old.content.len().saturating_sub(new_len) as u64;
+            }
+            stats.recompressed_images += 1;
         }
     }
+    stats.breakdown.push(StepSavings {
+        step: "images".to_string(),
+        bytes_saved: image_savings,
+    });
 
-    // 3. Final Pruning and Save
+    // 3. Final Pruning
     doc.prune_objects();
     doc.renumber_objects();
-    doc.save(&output_path)?;
+    Ok(stats)
+}
 
-    let compressed_size = std::fs::metadata(&output_path)?.len();
+/// Removes every object no longer reachable from the trailer and returns
+/// roughly how many bytes they took up, uncompressed.
+fn prune_measured(doc: &mut Document) -> u64 {
+    fn dict_size(dict: &lopdf::Dictionary) -> u64 {
+        4 + dict.iter().map(|(key, value)| key.len() as u64 + 2 + size(value)).sum::<u64>()
+    }
+    fn size(obj: &Object) -> u64 {
+        match obj {
+            Object::Stream(stream) => dict_size(&stream.dict) + stream.content.len() as u64 + 18,
+            Object::Dictionary(dict) => dict_size(dict),
+            Object::Array(items) => 2 + items.iter().map(|item| size(item) + 1).sum::<u64>(),
+            Object::String(bytes, _) | Object::Name(bytes) => bytes.len() as u64 + 2,
+            _ => 8,
+        }
+    }
+    // Object header, "endobj" and the xref entry.
+    const OVERHEAD: u64 = 36;
 
-    Ok(CompressionResult {
-        original_size,
-        compressed_size,
-        success: true,
-    })
+    let reachable: std::collections::HashSet<lopdf::ObjectId> = doc.traverse_objects(|_| {}).into_iter().collect();
+    let unreachable: Vec<lopdf::ObjectId> = doc.objects.keys().filter(|id| !reachable.contains(id)).copied().collect();
+    unreachable
+        .iter()
+        .filter_map(|id| doc.objects.remove(id))
+        .map(|obj| size(&obj) + OVERHEAD)
+        .sum()
 }
 
-#[tauri::command]
-fn get_organiser_pdf_metadata(path: String) -> AppResult<Vec<PageMetadata>> {
-    let doc = load_pdf(&path)?;
-    let mut results = Vec::new();
+/// Re-encodes one decoded image XObject, returning the replacement only if it is
+/// smaller than the original. `max_extent` is the longest side (in points) of
+/// the largest page the image is drawn on; images with more pixels than that
+/// page needs at `max_resolution_dpi` are downsampled. Soft masks always stay
+/// lossless, since JPEG noise in an alpha channel shows up as halos.
+fn recompress_image(
+    decoded: DecodedImage,
+    stream: &lopdf::Stream,
+    settings: &CompressionSettings,
+    max_extent: Option<f64>,
+    is_soft_mask: bool,
+) -> Option<lopdf::Stream> {
+    let (mut img, was_jpeg) = (decoded.pixels, decoded.jpeg);
+    let longest = img.width().max(img.height());
+    let target = match max_extent {
+        Some(extent) if settings.max_resolution_dpi > 0 => {
+            ((extent / 72.0 * settings.max_resolution_dpi as f64).ceil() as u32).max(1)
+        }
+        _ => longest,
+    };
+    let downscale = target < longest;
+    // Re-encoding a JPEG at the same size only stacks artifacts.
+    if was_jpeg && !downscale && !settings.force_recompression {
+        return None;
+    }
+    if downscale {
+        let scale = target as f64 / longest as f64;
+        let width = ((img.width() as f64 * scale).round() as u32).max(1);
+        let height = ((img.height() as f64 * scale).round() as u32).max(1);
+        img = img.resize_exact(width, height, image::imageops::FilterType::Triangle);
+    }
 
-    for (i, (_page_num, &page_id)) in doc.get_pages().iter().enumerate() {
-        let page_dict = doc.get_dictionary(page_id)?;
-        let mut is_landscape = false;
+    let mut dict = stream.dict.clone();
+    dict.set("Width", img.width() as i64);
+    dict.set("Height", img.height() as i64);
+    dict.set("BitsPerComponent", 8);
+    dict.remove(b"DecodeParms");
+    dict.remove(b"Filter");
+
+    let candidate = if !is_soft_mask && is_photographic(&img) {
+        let mut jpeg = Vec::new();
+        let quality = settings.image_quality.clamp(1, 100) as u8;
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality)
+            .encode_image(&img)
+            .ok()?;
+        dict.set("Filter", "DCTDecode");
+        lopdf::Stream::new(dict, jpeg)
+    } else {
+        let mut flate = lopdf::Stream::new(dict, img.into_bytes());
+        flate.compress().ok()?;
+        flate
+    };
 
-        if let Ok(media_box) = page_dict.get(b"MediaBox").and_then(|o| o.as_array()) {
-            if media_box.len() == 4 {
-                let nums: Vec<f64> = media_box
-                    .iter()
-                    .filter_map(|o| match o {
-                        lopdf::Object::Real(f) => Some(*f as f64),
-                        lopdf::Object::Integer(i) => Some(*i as f64),
-                        _ => None,
-                    })
-                    .collect();
-                if nums.len() == 4 {
-                    let width = (nums[2] - nums[0]).abs();
-                    let height = (nums[3] - nums[1]).abs();
-                    is_landscape = width > height;
+    (candidate.content.len() < stream.content.len()).then_some(candidate)
+}
+
+/// Decides JPEG vs lossless for an image. Photographs have many distinct
+/// colours and mostly soft transitions between neighbours; screenshots and
+/// line art have few colours, large flat runs, and hard edges that JPEG turns
+/// into ringing.
+fn is_photographic(img: &image::DynamicImage) -> bool {
+    const MAX_COLORS: usize = 256;
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    // JPEG's fixed overhead and 8x8 blocks don't pay off on tiny images.
+    if width < 32 || height < 32 {
+        return false;
+    }
+    // Sample on a grid so huge images cost about the same as small ones.
+    let step = ((width as f64 * height as f64 / 250_000.0).sqrt().ceil() as u32).max(1);
+
+    let mut colors = std::collections::HashSet::new();
+    let (mut pairs, mut identical, mut hard_edges) = (0u64, 0u64, 0u64);
+    for y in (0..height).step_by(step as usize) {
+        for x in (0..width - 1).step_by(step as usize) {
+            let a = rgb.get_pixel(x, y).0;
+            let b = rgb.get_pixel(x + 1, y).0;
+            if colors.len() <= MAX_COLORS {
+                colors.insert(a);
+            }
+            pairs += 1;
+            let diff: u32 = a.iter().zip(b.iter()).map(|(p, q)| p.abs_diff(*q) as u32).sum();
+            if diff == 0 {
+                identical += 1;
+            } else if diff > 96 {
+                hard_edges += 1;
+            }
+        }
+    }
+    if colors.len() <= MAX_COLORS || pairs == 0 {
+        return false;
+    }
+    let flat_ratio = identical as f64 / pairs as f64;
+    let changed = (pairs - identical).max(1);
+    let hard_ratio = hard_edges as f64 / changed as f64;
+    flat_ratio < 0.6 && hard_ratio < 0.3
+}
+
+/// Maps each image XObject to the longest side (in points) of the largest
+/// page that draws it, directly or through nested form XObjects. Images not
+/// reachable from a page (e.g. only in annotations) are absent.
+fn image_page_extents(doc: &Document) -> std::collections::HashMap<lopdf::ObjectId, f64> {
+    let mut extents = std::collections::HashMap::new();
+    for (_, page_id) in doc.get_pages() {
+        let rect = page_visible_rect(doc, page_id);
+        let extent = (rect[2] - rect[0]).max(rect[3] - rect[1]);
+
+        let mut pending: Vec<&lopdf::Dictionary> = inherited_attr(doc, page_id, b"Resources")
+            .and_then(|o| doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_dict().ok())
+            .into_iter()
+            .collect();
+        let mut seen = std::collections::HashSet::new();
+        while let Some(resources) = pending.pop() {
+            let xobjects = match resources.get(b"XObject").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            for (_, xo) in xobjects.iter() {
+                let Ok(id) = xo.as_reference() else { continue };
+                if !seen.insert(id) {
+                    continue;
+                }
+                let Ok(stream) = doc.get_object(id).and_then(|o| o.as_stream()) else { continue };
+                match stream.dict.get(b"Subtype").and_then(|o| o.as_name()) {
+                    Ok(b"Image") => {
+                        let entry = extents.entry(id).or_insert(0.0f64);
+                        *entry = entry.max(extent);
+                        if let Ok(mask) = stream.dict.get(b"SMask").and_then(|o| o.as_reference()) {
+                            let entry = extents.entry(mask).or_insert(0.0f64);
+                            *entry = entry.max(extent);
+                        }
+                    }
+                    Ok(b"Form") => {
+                        if let Ok(form_resources) =
+                            stream.dict.get(b"Resources").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict())
+                        {
+                            pending.push(form_resources);
+                        }
+                    }
+                    _ => {}
                 }
             }
         }
+    }
+    extents
+}
 
-        results.push(PageMetadata {
-            page_number: (i + 1) as u32,
-            is_landscape,
-        });
+/// Writes every raster image XObject in a PDF to `output_dir`, numbered in
+/// object order: JPEG streams are copied byte for byte as `img_{n}.jpg`, and
+/// everything else is decoded and saved as `img_{n}.png`. Soft masks become
+/// the alpha channel of their image's PNG rather than files of their own, and
+/// stencil masks are skipped. Images we can't decode (JPEG 2000, JBIG2, CCITT,
+/// Lab or spot colour) are skipped too. Returns the written paths.
+#[tauri::command]
+fn extract_images(path: String, output_dir: String) -> AppResult<Vec<String>> {
+    let out_dir = resolve_path(&output_dir);
+    if !out_dir.is_dir() {
+        return Err(AppError::Path("Output path is not a directory.".to_string()));
     }
+    ensure_dir_writable(&out_dir)?;
+    let doc = load_pdf(&path)?;
 
-    Ok(results)
+    let soft_masks: std::collections::HashSet<lopdf::ObjectId> = doc
+        .objects
+        .values()
+        .filter_map(|o| o.as_stream().ok())
+        .filter_map(|s| s.dict.get(b"SMask").and_then(|o| o.as_reference()).ok())
+        .collect();
+    let mut written = Vec::new();
+    let mut taken = std::collections::HashSet::new();
+    for (id, obj) in &doc.objects {
+        let Ok(stream) = obj.as_stream() else { continue };
+        if !stream.dict.get(b"Subtype").and_then(|o| o.as_name()).is_ok_and(|n| n == b"Image")
+            || stream.dict.get(b"ImageMask").and_then(|o| o.as_bool()).unwrap_or(false)
+            || soft_masks.contains(id)
+        {
+            continue;
+        }
+        let (bytes, ext) = if stream.filters().unwrap_or_default() == [b"DCTDecode".as_slice()] {
+            (stream.content.clone(), "jpg")
+        } else {
+            let Some(img) = decode_image_xobject(&doc, stream).map(|decoded| decoded.pixels) else { continue };
+            let img = match stream
+                .dict
+                .get(b"SMask")
+                .and_then(|o| doc.dereference(o))
+                .and_then(|(_, o)| o.as_stream())
+                .ok()
+                .and_then(|mask| decode_image_xobject(&doc, mask))
+                .map(|decoded| decoded.pixels)
+            {
+                Some(mask) => {
+                    let alpha = mask
+                        .resize_exact(img.width(), img.height(), image::imageops::FilterType::Triangle)
+                        .to_luma8();
+                    let mut rgba = img.to_rgba8();
+                    for (pixel, a) in rgba.pixels_mut().zip(alpha.pixels()) {
+                        pixel.0[3] = a.0[0];
+                    }
+                    image::DynamicImage::ImageRgba8(rgba)
+                }
+                None => img,
+            };
+            let mut png = Vec::new();
+            img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+                .map_err(|e| AppError::Validation(format!("Failed to encode image: {}", e)))?;
+            (png, "png")
+        };
+        let name = format!("img_{}.{}", written.len() + 1, ext);
+        let out = unique_output_path(&out_dir, std::ffi::OsStr::new(&name), &mut taken);
+        fs::write(&out, &bytes)?;
+        written.push(path_to_string(&out));
+    }
+    Ok(written)
 }
 
+/// Writes every file attached through the catalog's /Names /EmbeddedFiles
+/// tree to `output_dir`, under its /UF (or /F) file name with any directory
+/// parts dropped, so a name like `../../x` can't escape the folder. Clashing
+/// names get a numeric suffix and attachments whose data can't be decoded are
+/// skipped. Returns the written paths, which is empty when there are no
+/// attachments.
 #[tauri::command]
+fn extract_attachments(path: String, output_dir: String) -> AppResult<Vec<String>> {
+    let out_dir = resolve_path(&output_dir);
+    if !out_dir.is_dir() {
+        return Err(AppError::Path("Output path is not a directory.".to_string()));
+    }
+    ensure_dir_writable(&out_dir)?;
+    let doc = load_pdf(&path)?;
 
-/// Applies the user's organisation changes to the PDF.
-/// 
-/// **Strategy: Safe Tree Flattening**
-/// Instead of copying pages between documents (which risks missing indirect resources like fonts),
-/// we modify the *existing* document in memory:
-/// 1. Create a new "Pages" dictionary.
-/// 2. Reparent the selected Page objects to this new root.
-/// 3. Update the Catalog to point to the new root.
-/// 4. Prune any pages that are no longer referenced.
-/// 
-/// This ensures 100% fidelity for resources since we never "move" the page content's resources,
-/// only the reference to the Page object itself.
-fn apply_pdf_organisation(
-    input_path: String,
-    actions: Vec<PageAction>,
-    output_path: String,
-) -> AppResult<()> {
-    // Load the release PDF using memory mapping
-    let mut doc = load_pdf(&input_path)?;
+    let mut written = Vec::new();
+    let mut taken = std::collections::HashSet::new();
+    for (key, value) in embedded_files(&doc) {
+        let Ok(spec) = doc.dereference(&value).and_then(|(_, o)| o.as_dict()) else { continue };
+        let Some(stream) = spec
+            .get(b"EF")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_dict())
+            .ok()
+            .and_then(|ef| ef.get(b"F").or_else(|_| ef.get(b"UF")).ok())
+            .and_then(|o| doc.dereference(o).and_then(|(_, o)| o.as_stream()).ok())
+        else {
+            continue;
+        };
+        let Ok(bytes) = stream.get_plain_content() else { continue };
+        let name = [spec.get(b"UF"), spec.get(b"F")]
+            .into_iter()
+            .filter_map(|o| o.ok().map(decode_pdf_text))
+            .chain(std::iter::once(decode_pdf_text(&Object::string_literal(key))))
+            .map(|name| attachment_file_name(&name))
+            .find(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("attachment_{}", written.len() + 1));
+        let out = unique_output_path(&out_dir, std::ffi::OsStr::new(&name), &mut taken);
+        fs::write(&out, &bytes)?;
+        written.push(path_to_string(&out));
+    }
+    Ok(written)
+}
 
-    // 1. Get current pages mapping (page_num -> object_id)
-    let pages = doc.get_pages();
+/// The (key, file specification) pairs of the catalog's /Names
+/// /EmbeddedFiles tree, in tree order.
+fn embedded_files(doc: &Document) -> Vec<(Vec<u8>, Object)> {
+    fn collect(doc: &Document, node: &lopdf::Dictionary, depth: u32, out: &mut Vec<(Vec<u8>, Object)>) {
+        if let Ok(names) = node.get(b"Names").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()) {
+            for pair in names.chunks(2) {
+                if let [key, value] = pair {
+                    out.push((key.as_str().map(<[u8]>::to_vec).unwrap_or_default(), value.clone()));
+                }
+            }
+        }
+        if depth >= 32 {
+            return;
+        }
+        if let Ok(kids) = node.get(b"Kids").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()) {
+            for kid in kids.iter().filter_map(|kid| doc.dereference(kid).and_then(|(_, o)| o.as_dict()).ok()) {
+                collect(doc, kid, depth + 1, out);
+            }
+        }
+    }
 
-    // Get MediaBox from the first page (if available) to use for blank pages
-    let default_media_box = if let Some(&first_page_id) = pages.get(&1) {
-        doc.get_dictionary(first_page_id)
-            .ok()
-            .and_then(|dict| dict.get(b"MediaBox").ok())
-            .cloned()
-            .unwrap_or_else(|| vec![0.into(), 0.into(), 595.28.into(), 841.89.into()].into()) // Fallback A4
-    } else {
-        vec![0.into(), 0.into(), 595.28.into(), 841.89.into()].into() // Fallback A4
+    let mut entries = Vec::new();
+    if let Ok(tree) = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Names"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .and_then(|names| names.get(b"EmbeddedFiles"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+    {
+        collect(doc, tree, 0, &mut entries);
+    }
+    entries
+}
+
+/// Attaches `file_to_embed` to the document, listed under its file name in
+/// the catalog's /Names /EmbeddedFiles tree (created if missing; other name
+/// trees are left alone). The tree is rewritten as a single sorted node; a
+/// name already in use gets a numeric suffix in the tree key, while the
+/// attachment keeps its real file name.
+#[tauri::command]
+fn add_attachment(path: String, file_to_embed: String, output_path: String) -> AppResult<()> {
+    let file_path = resolve_path(&file_to_embed);
+    let data = fs::read(&file_path)?;
+    let name = file_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| AppError::Path(format!("{} is not a file.", file_to_embed)))?;
+    let modified: chrono::DateTime<chrono::Local> = match fs::metadata(&file_path).and_then(|m| m.modified()) {
+        Ok(time) => time.into(),
+        Err(_) => chrono::Local::now(),
     };
-    
-    // 2. Resolve actions to a list of ObjectIds for the new document
-    let mut new_page_ids = Vec::new();
-    
-    for action in actions {
-        match action {
-            PageAction::Existing { page_number } => {
-                if let Some(&id) = pages.get(&(page_number as u32)) {
-                    new_page_ids.push(id);
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+
+    let mut file = lopdf::Stream::new(
+        dictionary! {
+            "Type" => "EmbeddedFile",
+            "Params" => dictionary! {
+                "Size" => data.len() as i64,
+                "ModDate" => Object::string_literal(pdf_date(&modified.fixed_offset())),
+            },
+        },
+        data,
+    );
+    file.compress()?;
+    let file_id = doc.add_object(file);
+    let spec_id = doc.add_object(dictionary! {
+        "Type" => "Filespec",
+        "F" => lopdf::text_string(&name),
+        "UF" => lopdf::text_string(&name),
+        "EF" => dictionary! { "F" => file_id, "UF" => file_id },
+    });
+
+    let mut entries = embedded_files(&doc);
+    let mut key = name.clone().into_bytes();
+    let mut n = 2;
+    while entries.iter().any(|(existing, _)| *existing == key) {
+        key = format!("{} ({})", name, n).into_bytes();
+        n += 1;
+    }
+    entries.push((key, Object::Reference(spec_id)));
+    // Name tree keys must be in byte order.
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let tree_id = doc.add_object(dictionary! {
+        "Names" => entries
+            .into_iter()
+            .flat_map(|(key, spec)| [Object::String(key, lopdf::StringFormat::Literal), spec])
+            .collect::<Vec<_>>(),
+    });
+
+    let names_id = match doc.catalog()?.get(b"Names") {
+        Ok(Object::Reference(id)) => *id,
+        Ok(Object::Dictionary(inline)) => {
+            let inline = inline.clone();
+            doc.add_object(inline)
+        }
+        _ => doc.add_object(lopdf::Dictionary::new()),
+    };
+    doc.catalog_mut()?.set("Names", names_id);
+    doc.get_dictionary_mut(names_id)?.set("EmbeddedFiles", tree_id);
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Reduces an attachment's stored file name to a plain file name: only the
+/// last path component survives (either slash counts), and `.`/`..` and
+/// characters that aren't allowed in file names are dropped.
+fn attachment_file_name(name: &str) -> String {
+    let last = name
+        .split(['/', '\\'])
+        .rfind(|part| !part.trim().is_empty())
+        .unwrap_or("")
+        .trim();
+    if last == "." || last == ".." {
+        return String::new();
+    }
+    last.chars()
+        .filter(|c| !c.is_control() && !matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect()
+}
+
+/// Colour spaces `decode_image_xobject` can convert to RGB.
+enum ImageColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+    /// Base space and lookup table: one entry of base components per index.
+    Indexed(Box<ImageColorSpace>, Vec<u8>),
+}
+
+impl ImageColorSpace {
+    fn resolve(doc: &Document, obj: &Object) -> Option<Self> {
+        match doc.dereference(obj).ok()?.1 {
+            Object::Name(n) => match n.as_slice() {
+                b"DeviceGray" => Some(Self::Gray),
+                b"DeviceRGB" => Some(Self::Rgb),
+                b"DeviceCMYK" => Some(Self::Cmyk),
+                _ => None,
+            },
+            Object::Array(a) => match a.first()?.as_name().ok()? {
+                b"CalGray" => Some(Self::Gray),
+                b"CalRGB" => Some(Self::Rgb),
+                b"ICCBased" => {
+                    let profile = doc.dereference(a.get(1)?).ok()?.1.as_stream().ok()?;
+                    match profile.dict.get(b"N").and_then(|o| o.as_i64()).ok()? {
+                        1 => Some(Self::Gray),
+                        3 => Some(Self::Rgb),
+                        4 => Some(Self::Cmyk),
+                        _ => None,
+                    }
                 }
-            }
-            PageAction::Blank => {
-                // Create a blank page matching the document size
-                let content_id = doc.add_object(lopdf::Object::Stream(lopdf::Stream::new(
-                    dictionary! {},
-                    vec![],
-                )));
-                
-                let page_id = doc.add_object(dictionary! {
-                    b"Type" => "Page",
-                    b"MediaBox" => default_media_box.clone(),
-                    b"Resources" => dictionary! {},
-                    b"Contents" => content_id,
-                });
-                new_page_ids.push(page_id);
+                b"Indexed" => {
+                    let base = Self::resolve(doc, a.get(1)?)?;
+                    if matches!(base, Self::Indexed(..)) {
+                        return None;
+                    }
+                    let lookup = match doc.dereference(a.get(3)?).ok()?.1 {
+                        Object::String(bytes, _) => bytes.clone(),
+                        Object::Stream(stream) => stream.get_plain_content().ok()?,
+                        _ => return None,
+                    };
+                    Some(Self::Indexed(Box::new(base), lookup))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn components(&self) -> usize {
+        match self {
+            Self::Gray | Self::Indexed(..) => 1,
+            Self::Rgb => 3,
+            Self::Cmyk => 4,
+        }
+    }
+
+    /// Converts one colour of a direct space, 0-255 per component. CMYK uses
+    /// the naive complement, which is close enough for a preview-grade export.
+    fn to_rgb(&self, c: &[u8]) -> [u8; 3] {
+        match self {
+            Self::Gray | Self::Indexed(..) => [c[0]; 3],
+            Self::Rgb => [c[0], c[1], c[2]],
+            Self::Cmyk => {
+                let k = 255 - c[3] as u32;
+                let channel = |v: u8| ((255 - v as u32) * k / 255) as u8;
+                [channel(c[0]), channel(c[1]), channel(c[2])]
             }
         }
     }
-    
-    // 3. Create a new "Pages" tree root
-    // We flatten the tree to a single Pages object for simplicity and robustness.
-    let pages_root_id = doc.new_object_id();
-    
-    // 4. Update all pages to point to this new parent
-    for &page_id in &new_page_ids {
-        if let Ok(page_dict) = doc.get_object_mut(page_id).and_then(|o| o.as_dict_mut()) {
-            page_dict.set(b"Parent", lopdf::Object::Reference(pages_root_id));
+}
+
+/// An image XObject decoded to 8-bit gray or RGB pixels.
+struct DecodedImage {
+    pixels: image::DynamicImage,
+    /// The stream is a JPEG.
+    jpeg: bool,
+    /// The pixels are the stream's own samples in its own gray or RGB colour
+    /// space, with no /Decode array or colour-key mask to reinterpret them, so
+    /// a re-encoding can stand in for the stream without changing its meaning.
+    faithful: bool,
+}
+
+/// Decodes an image XObject to 8-bit gray or RGB pixels: JPEGs through the
+/// `image` crate, anything else at any bit depth in gray, RGB, CMYK or indexed
+/// colour, honouring /Decode for direct colour. Returns None for stencil
+/// masks, filters we can't decode and other colour spaces.
+fn decode_image_xobject(doc: &Document, stream: &lopdf::Stream) -> Option<DecodedImage> {
+    let dict = &stream.dict;
+    if dict.get(b"ImageMask").and_then(|o| o.as_bool()).unwrap_or(false) {
+        return None;
+    }
+    let width = usize::try_from(dict.get(b"Width").and_then(|o| o.as_i64()).ok()?).ok()?;
+    let height = usize::try_from(dict.get(b"Height").and_then(|o| o.as_i64()).ok()?).ok()?;
+    let space = ImageColorSpace::resolve(doc, dict.get(b"ColorSpace").ok()?)?;
+    let n = space.components();
+    let gray = matches!(space, ImageColorSpace::Gray);
+    let faithful = matches!(space, ImageColorSpace::Gray | ImageColorSpace::Rgb)
+        && !dict.has(b"Decode")
+        && !matches!(dict.get(b"Mask"), Ok(Object::Array(_)));
+
+    if stream.filters().unwrap_or_default() == [b"DCTDecode".as_slice()] {
+        let img = image::load_from_memory_with_format(&stream.content, image::ImageFormat::Jpeg).ok()?;
+        if img.width() as usize != width || img.height() as usize != height {
+            return None;
         }
+        let same_space = img.color().channel_count() as usize == n;
+        let pixels = if gray {
+            image::DynamicImage::ImageLuma8(img.to_luma8())
+        } else {
+            image::DynamicImage::ImageRgb8(img.to_rgb8())
+        };
+        return Some(DecodedImage {
+            pixels,
+            jpeg: true,
+            faithful: faithful && same_space,
+        });
     }
-    
-    // 5. Create the Pages dictionary
-    let pages_dict = dictionary! {
-        b"Type" => "Pages",
-        b"Count" => new_page_ids.len() as i64,
-        b"Kids" => new_page_ids.into_iter().map(lopdf::Object::Reference).collect::<Vec<_>>(),
-    };
-    
-    doc.objects.insert(pages_root_id, lopdf::Object::Dictionary(pages_dict));
-    
-    // 6. Update the Catalog to point to our new Pages root
-    let catalog_id = doc.trailer.get(b"Root")?.as_reference()?;
-    if let Ok(catalog) = doc.get_object_mut(catalog_id).and_then(|o| o.as_dict_mut()) {
-        catalog.set(b"Pages", lopdf::Object::Reference(pages_root_id));
+
+    let bpc = dict.get(b"BitsPerComponent").and_then(|o| o.as_i64()).ok()? as usize;
+    if !matches!(bpc, 1 | 2 | 4 | 8 | 16) {
+        return None;
+    }
+    let data = stream.get_plain_content().ok()?;
+    let row_bytes = (width * n * bpc).div_ceil(8);
+    if width == 0 || height == 0 || data.len() < row_bytes * height {
+        return None;
     }
-    
-    // 7. Prune unused objects (orphaned old Pages nodes, unused pages)
-    // loose_objects will be removed.
-    doc.prune_objects();
-    
-    // 8. Save
-    // We use compress to keep it efficient
-    doc.save(output_path)?;
 
-    Ok(())
+    let max = ((1u32 << bpc) - 1) as f64;
+    let decode: Vec<(f64, f64)> = match dict.get(b"Decode").and_then(|o| o.as_array()) {
+        Ok(a) if a.len() >= 2 * n => a
+            .chunks(2)
+            .map(|pair| (pair[0].as_float().unwrap_or(0.0) as f64, pair[1].as_float().unwrap_or(1.0) as f64))
+            .collect(),
+        _ => vec![(0.0, 1.0); n],
+    };
+    let sample = |row: &[u8], index: usize| -> u32 {
+        let bit = index * bpc;
+        if bpc >= 8 {
+            row[bit / 8..bit / 8 + bpc / 8].iter().fold(0, |acc, &b| (acc << 8) | b as u32)
+        } else {
+            (row[bit / 8] >> (8 - bpc - bit % 8)) as u32 & max as u32
+        }
+    };
+
+    let mut pixels = Vec::with_capacity(width * height * if gray { 1 } else { 3 });
+    let mut color = [0u8; 4];
+    for row in data.chunks(row_bytes).take(height) {
+        for x in 0..width {
+            let rgb = match &space {
+                ImageColorSpace::Indexed(base, lookup) => {
+                    let bn = base.components();
+                    let start = sample(row, x) as usize * bn;
+                    color[..bn].copy_from_slice(lookup.get(start..start + bn).unwrap_or(&[0; 4][..bn]));
+                    base.to_rgb(&color)
+                }
+                _ => {
+                    for (c, value) in color.iter_mut().enumerate().take(n) {
+                        let (lo, hi) = decode[c];
+                        let v = lo + sample(row, x * n + c) as f64 / max * (hi - lo);
+                        *value = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+                    }
+                    space.to_rgb(&color)
+                }
+            };
+            if gray {
+                pixels.push(rgb[0]);
+            } else {
+                pixels.extend_from_slice(&rgb);
+            }
+        }
+    }
+    let (width, height) = (width as u32, height as u32);
+    let pixels = if gray {
+        image::DynamicImage::ImageLuma8(image::GrayImage::from_raw(width, height, pixels)?)
+    } else {
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_raw(width, height, pixels)?)
+    };
+    Some(DecodedImage {
+        pixels,
+        jpeg: false,
+        faithful,
+    })
+}
+
+// --- Pipelines ---
+
+/// One operation in a `process_pipeline` run. Steps reuse the same cores as
+/// their standalone commands but share a single loaded document.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum PipelineStep {
+    Rotate {
+        #[serde(deserialize_with = "deserialize_page_map")]
+        rotations: std::collections::HashMap<u32, i32>,
+        #[serde(default)]
+        mode: RotationMode,
+    },
+    KeepPages { ranges: PageSelection },
+    Compress { settings: CompressionSettings },
+    Protect {
+        user_password: String,
+        owner_password: Option<String>,
+        #[serde(default)]
+        algorithm: EncryptionAlgorithm,
+        #[serde(default)]
+        permissions: PdfPermissions,
+    },
+}
+
+impl PipelineStep {
+    fn name(&self) -> &'static str {
+        match self {
+            PipelineStep::Rotate { .. } => "rotate",
+            PipelineStep::KeepPages { .. } => "keep_pages",
+            PipelineStep::Compress { .. } => "compress",
+            PipelineStep::Protect { .. } => "protect",
+        }
+    }
+}
+
+/// Deserializes a map keyed by page number. JSON object keys are strings, and
+/// serde cannot coerce them to integers once an internally tagged enum has
+/// buffered the content, so parse them explicitly.
+fn deserialize_page_map<'de, D, V>(deserializer: D) -> Result<std::collections::HashMap<u32, V>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    let raw = std::collections::HashMap::<String, V>::deserialize(deserializer)?;
+    raw.into_iter()
+        .map(|(key, value)| {
+            key.parse::<u32>()
+                .map(|page| (page, value))
+                .map_err(|_| serde::de::Error::custom(format!("invalid page number: {}", key)))
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PipelineStepTiming {
+    pub step: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PipelineResult {
+    pub steps: Vec<PipelineStepTiming>,
+    pub total_ms: u64,
+}
+
+/// Runs an ordered list of operations on one loaded document and saves once,
+/// avoiding a load/save cycle and temp file per step.
+#[tauri::command]
+fn process_pipeline(
+    path: String,
+    steps: Vec<PipelineStep>,
+    output_path: String,
+) -> AppResult<PipelineResult> {
+    validate_pipeline(&steps)?;
+    ensure_output_writable(&output_path)?;
+
+    let started = std::time::Instant::now();
+    let mut doc = load_pdf(&path)?;
+    let timings = run_pipeline_steps(&mut doc, &steps)?;
+    doc.save(resolve_path(&output_path))?;
+
+    Ok(PipelineResult {
+        steps: timings,
+        total_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+fn validate_pipeline(steps: &[PipelineStep]) -> AppResult<()> {
+    if steps.is_empty() {
+        return Err(AppError::Validation("Pipeline has no steps.".to_string()));
+    }
+    // Encryption must come last: later steps would operate on encrypted objects.
+    if let Some(pos) = steps.iter().position(|s| matches!(s, PipelineStep::Protect { .. })) {
+        if pos != steps.len() - 1 {
+            return Err(AppError::Validation("Protect must be the last pipeline step.".to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn run_pipeline_steps(doc: &mut Document, steps: &[PipelineStep]) -> AppResult<Vec<PipelineStepTiming>> {
+    let mut timings = Vec::new();
+    for step in steps {
+        let step_started = std::time::Instant::now();
+        match step {
+            PipelineStep::Rotate { rotations, mode } => apply_rotations(doc, rotations, *mode)?,
+            PipelineStep::KeepPages { ranges } => {
+                let pages = doc.get_pages();
+                let kept = page_ids_for_selection(&pages, ranges)?;
+                rebuild_page_tree(doc, kept)?;
+            }
+            PipelineStep::Compress { settings } => {
+                compress_document(doc, settings)?;
+            }
+            PipelineStep::Protect { user_password, owner_password, algorithm, permissions } => {
+                encrypt_document(doc, user_password, owner_password.clone(), *algorithm, permissions)?
+            }
+        }
+        timings.push(PipelineStepTiming {
+            step: step.name().to_string(),
+            duration_ms: step_started.elapsed().as_millis() as u64,
+        });
+    }
+    Ok(timings)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchOutput {
+    pub source: String,
+    pub output_path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchApplyResult {
+    pub outputs: Vec<BatchOutput>,
+    pub failed: Vec<RenameFailure>,
+}
+
+/// Runs the same pipeline over many files, writing each result into
+/// `output_dir`. Same-named inputs get suffixed outputs (`name_2.pdf`) rather
+/// than overwriting each other; with `preserve_subpaths` the inputs' folder
+/// layout below their common parent is recreated instead.
+#[tauri::command]
+fn batch_apply(
+    paths: Vec<String>,
+    steps: Vec<PipelineStep>,
+    output_dir: String,
+    preserve_subpaths: Option<bool>,
+) -> AppResult<BatchApplyResult> {
+    if paths.is_empty() {
+        return Err(AppError::Validation("No files to process.".to_string()));
+    }
+    validate_pipeline(&steps)?;
+    let out_dir = resolve_path(&output_dir);
+    if !out_dir.is_dir() {
+        return Err(AppError::Path("Output path is not a directory.".to_string()));
+    }
+    ensure_dir_writable(&out_dir)?;
+
+    let root = if preserve_subpaths.unwrap_or(false) { common_parent(&paths) } else { None };
+    let mut taken = std::collections::HashSet::new();
+    let mut outputs = Vec::new();
+    let mut failed = Vec::new();
+
+    for path_str in &paths {
+        let target_dir = match (&root, resolve_path(path_str).parent()) {
+            (Some(root), Some(parent)) => out_dir.join(parent.strip_prefix(root).unwrap_or(Path::new(""))),
+            _ => out_dir.clone(),
+        };
+        match apply_pipeline_to_file(path_str, &steps, &target_dir, &mut taken) {
+            Ok(out_path) => outputs.push(BatchOutput {
+                source: path_str.clone(),
+                output_path: path_to_string(&out_path),
+            }),
+            Err(e) => failed.push(RenameFailure {
+                path: path_str.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    Ok(BatchApplyResult { outputs, failed })
+}
+
+fn apply_pipeline_to_file(
+    path: &str,
+    steps: &[PipelineStep],
+    target_dir: &Path,
+    taken: &mut std::collections::HashSet<PathBuf>,
+) -> AppResult<PathBuf> {
+    let source = resolve_path(path);
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| AppError::Path(format!("Invalid file path: {}", path)))?;
+    fs::create_dir_all(target_dir)?;
+    let out_path = unique_output_path(target_dir, file_name, taken);
+
+    let mut doc = load_pdf(&source)?;
+    run_pipeline_steps(&mut doc, steps)?;
+    doc.save(&out_path)?;
+    Ok(out_path)
+}
+
+// --- Privacy ---
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScrubReport {
+    /// Human-readable description of each item that was removed or replaced.
+    pub removed: Vec<String>,
+}
+
+/// Removes every piece of identifying metadata we know about: the Info
+/// dictionary (including Producer, Creator and custom keys), XMP packets on
+/// the catalog and on any other object, PieceInfo application data and its
+/// LastModified stamps, and replaces the trailer /ID with a fresh random one.
+#[tauri::command]
+fn scrub_metadata(path: String, output_path: String) -> AppResult<ScrubReport> {
+    let mut doc = load_pdf(&path)?;
+    let mut removed = Vec::new();
+
+    // 1. Document Info dictionary
+    if let Ok(info) = doc.trailer.get(b"Info") {
+        let keys: Vec<String> = doc
+            .dereference(info)
+            .ok()
+            .and_then(|(_, o)| o.as_dict().ok())
+            .map(|d| d.iter().map(|(k, _)| String::from_utf8_lossy(k).to_string()).collect())
+            .unwrap_or_default();
+        if keys.is_empty() {
+            removed.push("Info dictionary".to_string());
+        } else {
+            removed.push(format!("Info dictionary ({})", keys.join(", ")));
+        }
+        doc.trailer.remove(b"Info");
+    }
+    if doc.trailer.remove(b"PieceInfo").is_some() {
+        removed.push("Trailer PieceInfo".to_string());
+    }
+
+    // 2. Catalog-level XMP and application data
+    let catalog_id = doc.trailer.get(b"Root")?.as_reference()?;
+    if let Ok(catalog) = doc.get_object_mut(catalog_id).and_then(|o| o.as_dict_mut()) {
+        if catalog.remove(b"Metadata").is_some() {
+            removed.push("Document XMP metadata".to_string());
+        }
+        if catalog.remove(b"PieceInfo").is_some() {
+            removed.push("Document PieceInfo".to_string());
+        }
+    }
+
+    // 3. Per-object XMP and PieceInfo (pages, images, fonts, form XObjects)
+    let page_ids: std::collections::HashSet<lopdf::ObjectId> = doc.get_pages().values().cloned().collect();
+    let mut page_piece_info = 0;
+    let mut page_xmp = 0;
+    let mut other_xmp = 0;
+    for (id, obj) in doc.objects.iter_mut() {
+        let dict = match obj {
+            Object::Dictionary(d) => d,
+            Object::Stream(s) => &mut s.dict,
+            _ => continue,
+        };
+        let had_piece_info = dict.remove(b"PieceInfo").is_some();
+        if had_piece_info {
+            dict.remove(b"LastModified");
+        }
+        let had_xmp = dict.remove(b"Metadata").is_some();
+        if page_ids.contains(id) {
+            page_piece_info += had_piece_info as u32;
+            page_xmp += had_xmp as u32;
+        } else {
+            other_xmp += had_xmp as u32;
+        }
+    }
+    if page_piece_info > 0 {
+        removed.push(format!("PieceInfo on {} page(s)", page_piece_info));
+    }
+    if page_xmp > 0 {
+        removed.push(format!("XMP metadata on {} page(s)", page_xmp));
+    }
+    if other_xmp > 0 {
+        removed.push(format!("XMP metadata on {} embedded object(s)", other_xmp));
+    }
+
+    // 4. A fresh /ID so the output cannot be correlated with the source file
+    doc.trailer.set(b"ID", fresh_document_id());
+    removed.push("Document ID (regenerated)".to_string());
+
+    // Orphaned XMP streams are dropped on prune.
+    doc.prune_objects();
+    doc.save(resolve_path(&output_path))?;
+
+    Ok(ScrubReport { removed })
+}
+
+/// One revision of a PDF: the original file or an incremental update
+/// appended after it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateInfo {
+    /// 1 for the original document, 2 for the first update, and so on.
+    pub revision: u32,
+    /// Byte offset where the revision starts.
+    pub offset: u64,
+    pub size: u64,
+    /// Objects written in this revision (new or replacing earlier versions).
+    pub objects: u32,
+}
+
+/// Lists the revisions stacked in a file. More than one means earlier
+/// versions of edited objects, including "deleted" content, are still in it.
+#[tauri::command]
+fn report_incremental_updates(path: String) -> AppResult<Vec<UpdateInfo>> {
+    let file = fs::File::open(resolve_path(&path))?;
+    if file.metadata()?.len() < PDF_HEADER.len() as u64 {
+        return Err(AppError::Validation("Not a PDF or empty file".to_string()));
+    }
+    // SAFETY: see load_pdf.
+    let mmap = unsafe { Mmap::map(&file)? };
+    Ok(revision_spans(&mmap)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (start, end))| UpdateInfo {
+            revision: i as u32 + 1,
+            offset: start as u64,
+            size: (end - start) as u64,
+            objects: count_object_headers(&mmap[start..end]),
+        })
+        .collect())
+}
+
+/// Rewrites the document from its current state, dropping every superseded
+/// object version and the update sections that held them.
+#[tauri::command]
+fn remove_update_history(path: String, output_path: String) -> AppResult<()> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    // Objects orphaned by later revisions are still loaded; prune them too.
+    doc.prune_objects();
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Byte ranges of each revision, split after every `%%EOF` line. The first-page
+/// section of a linearized file ends in its own `%%EOF` but belongs to the
+/// original revision.
+fn revision_spans(data: &[u8]) -> Vec<(usize, usize)> {
+    let mut ends = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = data[pos..].windows(5).position(|w| w == b"%%EOF") {
+        let mut end = pos + found + 5;
+        let at_line_start = end - 5 == 0 || matches!(data[end - 6], b'\n' | b'\r');
+        while end < data.len() && matches!(data[end], b'\r' | b'\n') {
+            end += 1;
+        }
+        if at_line_start {
+            ends.push(end);
+        }
+        pos = end;
+    }
+    // An update that was cut off before its %%EOF still counts.
+    let tail = ends.last().copied().unwrap_or(0);
+    if data[tail..].iter().any(|b| !b.is_ascii_whitespace()) {
+        ends.push(data.len());
+    }
+    if ends.len() > 1 && data[..ends[0]].windows(11).any(|w| w == b"/Linearized") {
+        ends.remove(0);
+    }
+
+    let mut start = 0;
+    ends.into_iter()
+        .map(|end| {
+            let span = (start, end);
+            start = end;
+            span
+        })
+        .collect()
+}
+
+/// Counts `N G obj` headers in a byte range.
+fn count_object_headers(data: &[u8]) -> u32 {
+    let skip_back = |mut k: usize, pred: fn(&u8) -> bool| {
+        while k > 0 && pred(&data[k - 1]) {
+            k -= 1;
+        }
+        k
+    };
+    let mut count = 0;
+    for i in 0..data.len().saturating_sub(2) {
+        if &data[i..i + 3] != b"obj" || data.get(i + 3).is_some_and(|b| b.is_ascii_alphanumeric()) {
+            continue;
+        }
+        // "endobj" fails here: no whitespace before the "obj".
+        let gen_end = skip_back(i, u8::is_ascii_whitespace);
+        let gen_start = skip_back(gen_end, u8::is_ascii_digit);
+        let num_end = skip_back(gen_start, u8::is_ascii_whitespace);
+        let num_start = skip_back(num_end, u8::is_ascii_digit);
+        let header = gen_end < i && gen_start < gen_end && num_end < gen_start && num_start < num_end;
+        if header && (num_start == 0 || data[num_start - 1].is_ascii_whitespace()) {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Returns size, rotation and content flags for one page, for views that only
+/// need the page currently shown.
+#[tauri::command]
+fn get_page_info(path: String, page_number: u32) -> AppResult<PageInfo> {
+    let doc = load_pdf(&path)?;
+    let page_id = match find_page_id(&doc, page_number) {
+        Some(id) => id,
+        // Inconsistent /Count values: fall back to a full walk.
+        None => *doc.get_pages().get(&page_number).ok_or_else(|| {
+            AppError::Validation(format!("Page {} is out of range.", page_number))
+        })?,
+    };
+
+    let media = inherited_attr(&doc, page_id, b"MediaBox")
+        .and_then(|o| parse_rect(&doc, o))
+        .map(normalize_rect)
+        .unwrap_or([0.0, 0.0, 612.0, 792.0]);
+    let rotation = inherited_attr(&doc, page_id, b"Rotate")
+        .and_then(|o| o.as_i64().ok())
+        .unwrap_or(0)
+        .rem_euclid(360)
+        / 90
+        * 90;
+
+    let page = doc.get_dictionary(page_id)?;
+    let has_annotations = page
+        .get(b"Annots")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_array())
+        .is_ok_and(|a| !a.is_empty());
+    let has_images = inherited_attr(&doc, page_id, b"Resources")
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .and_then(|r| r.get(b"XObject").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()).ok())
+        .is_some_and(|xobjects| {
+            xobjects.iter().any(|(_, xo)| {
+                doc.dereference(xo)
+                    .and_then(|(_, o)| o.as_stream())
+                    .and_then(|s| s.dict.get(b"Subtype"))
+                    .and_then(|o| o.as_name())
+                    .is_ok_and(|n| n == b"Image")
+            })
+        });
+
+    Ok(PageInfo {
+        page_number,
+        width: media[2] - media[0],
+        height: media[3] - media[1],
+        rotation,
+        has_annotations,
+        has_images,
+    })
+}
+
+/// Decoded content-stream size of each page, in page order. Streams with a
+/// filter we can't decode are counted at their stored size. Form XObjects and
+/// images drawn by the page are not included.
+#[tauri::command]
+fn page_content_sizes(path: String) -> AppResult<Vec<(u32, u64)>> {
+    let doc = load_pdf(&path)?;
+    Ok(doc
+        .get_pages()
+        .into_iter()
+        .map(|(page_number, page_id)| {
+            let size: usize = doc
+                .get_page_contents(page_id)
+                .into_iter()
+                .filter_map(|id| doc.get_object(id).and_then(|o| o.as_stream()).ok())
+                .map(|stream| stream.get_plain_content().map(|c| c.len()).unwrap_or(stream.content.len()))
+                .sum();
+            (page_number, size as u64)
+        })
+        .collect())
+}
+
+/// Finds a page by number by descending the page tree using each node's
+/// /Count, rather than enumerating every page. Returns None if the page is out
+/// of range or the counts don't add up.
+fn find_page_id(doc: &Document, page_number: u32) -> Option<lopdf::ObjectId> {
+    let mut node_id = doc.catalog().ok()?.get(b"Pages").ok()?.as_reference().ok()?;
+    let mut remaining = page_number;
+    if remaining == 0 {
+        return None;
+    }
+    // Depth guard against /Kids cycles.
+    for _ in 0..64 {
+        let node = doc.get_dictionary(node_id).ok()?;
+        let kids = match node.get(b"Kids").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()) {
+            Ok(kids) => kids,
+            Err(_) => return (remaining == 1).then_some(node_id),
+        };
+        let mut next = None;
+        for kid in kids {
+            let kid_id = kid.as_reference().ok()?;
+            let kid_dict = doc.get_dictionary(kid_id).ok()?;
+            let count = if kid_dict.has(b"Kids") {
+                u32::try_from(kid_dict.get(b"Count").and_then(|o| o.as_i64()).ok()?).ok()?
+            } else {
+                1
+            };
+            if remaining <= count {
+                next = Some(kid_id);
+                break;
+            }
+            remaining -= count;
+        }
+        node_id = next?;
+    }
+    None
+}
+
+#[tauri::command]
+fn get_organiser_pdf_metadata(path: String) -> AppResult<Vec<PageMetadata>> {
+    let doc = load_pdf(&path)?;
+    let mut results = Vec::new();
+
+    for (i, (_page_num, &page_id)) in doc.get_pages().iter().enumerate() {
+        let (width, height) = inherited_attr(&doc, page_id, b"MediaBox")
+            .and_then(|o| parse_rect(&doc, o))
+            .map_or((0.0, 0.0), |r| ((r[2] - r[0]).abs(), (r[3] - r[1]).abs()));
+        let rotation = inherited_attr(&doc, page_id, b"Rotate")
+            .and_then(|o| o.as_i64().ok())
+            .unwrap_or(0)
+            .rem_euclid(360)
+            / 90
+            * 90;
+
+        // A quarter turn swaps width and height; a square page stays portrait
+        // either way.
+        let quarter_turn = rotation % 180 == 90;
+        results.push(PageMetadata {
+            page_number: (i + 1) as u32,
+            is_landscape: width > height,
+            rotation: rotation as i32,
+            display_landscape: if quarter_turn { height > width } else { width > height },
+        });
+    }
+
+    Ok(results)
+}
+
+#[tauri::command]
+
+/// Applies the user's organisation changes to the PDF.
+/// 
+/// **Strategy: Safe Tree Flattening**
+/// Instead of copying pages between documents (which risks missing indirect resources like fonts),
+/// we modify the *existing* document in memory:
+/// 1. Create a new "Pages" dictionary.
+/// 2. Reparent the selected Page objects to this new root.
+/// 3. Update the Catalog to point to the new root.
+/// 4. Prune any pages that are no longer referenced.
+/// 
+/// This ensures 100% fidelity for resources since we never "move" the page content's resources,
+/// only the reference to the Page object itself.
+fn apply_pdf_organisation(
+    input_path: String,
+    actions: Vec<PageAction>,
+    output_path: String,
+    target_version: Option<String>,
+) -> AppResult<OrganiseResult> {
+    // Load the release PDF using memory mapping
+    let mut doc = load_pdf(&input_path)?;
+
+    // 1. Get current pages mapping (page_num -> object_id)
+    let pages = doc.get_pages();
+
+    // Get MediaBox from the first page (if available) to use for blank pages
+    let default_media_box = if let Some(&first_page_id) = pages.get(&1) {
+        doc.get_dictionary(first_page_id)
+            .ok()
+            .and_then(|dict| dict.get(b"MediaBox").ok())
+            .cloned()
+            .unwrap_or_else(|| vec![0.into(), 0.into(), 595.28.into(), 841.89.into()].into()) // Fallback A4
+    } else {
+        vec![0.into(), 0.into(), 595.28.into(), 841.89.into()].into() // Fallback A4
+    };
+    
+    // 2. Resolve actions to a list of ObjectIds for the new document
+    let mut new_page_ids = Vec::new();
+    
+    for action in actions {
+        match action {
+            PageAction::Existing { page_number } => {
+                if let Some(&id) = pages.get(&(page_number as u32)) {
+                    new_page_ids.push(id);
+                }
+            }
+            PageAction::Blank => {
+                // Create a blank page matching the document size
+                let content_id = doc.add_object(lopdf::Object::Stream(lopdf::Stream::new(
+                    dictionary! {},
+                    vec![],
+                )));
+                
+                let page_id = doc.add_object(dictionary! {
+                    b"Type" => "Page",
+                    b"MediaBox" => default_media_box.clone(),
+                    b"Resources" => dictionary! {},
+                    b"Contents" => content_id,
+                });
+                new_page_ids.push(page_id);
+            }
+        }
+    }
+    
+    // Tags for deleted pages are pruned with the pages, but the structure
+    // tree's reading order cannot follow a reorder or a duplicated page.
+    let original_order: std::collections::HashMap<lopdf::ObjectId, u32> =
+        pages.iter().map(|(&n, &id)| (id, n)).collect();
+    let kept_order: Vec<u32> = new_page_ids.iter().filter_map(|id| original_order.get(id).copied()).collect();
+    let accessibility_affected = has_structure_tree(&doc) && !kept_order.windows(2).all(|w| w[0] < w[1]);
+
+    // 3-7. Flatten the tree onto the new page order and prune the rest.
+    // Links to deleted pages would keep them (and the old tree) alive.
+    rebuild_page_tree(&mut doc, new_page_ids)?;
+    let dropped_links = drop_external_page_links(&mut doc);
+    if dropped_links > 0 {
+        doc.prune_objects();
+    }
+    
+    // 8. Save
+    // We use compress to keep it efficient
+    apply_target_version(&mut doc, target_version.as_deref())?;
+    doc.save(resolve_path(&output_path))?;
+
+    let mut warnings = Vec::new();
+    if accessibility_affected {
+        warnings.push("Accessibility tags no longer follow the new page order.".to_string());
+    }
+    if dropped_links > 0 {
+        warnings.push(format!(
+            "Removed {} link{} to deleted pages.",
+            dropped_links,
+            if dropped_links == 1 { "" } else { "s" }
+        ));
+    }
+    Ok(OrganiseResult {
+        accessibility_affected,
+        warnings,
+    })
+}
+
+/// Writes the document with its pages in reverse order, for stacks scanned
+/// back to front.
+#[tauri::command]
+fn reverse_pages(path: String, output_path: String) -> AppResult<OrganiseResult> {
+    let page_count = load_pdf(&path)?.get_pages().len() as u32;
+    let actions = (1..=page_count)
+        .rev()
+        .map(|page_number| PageAction::Existing { page_number })
+        .collect();
+    apply_pdf_organisation(path, actions, output_path, None)
+}
+
+/// Saves an organiser action list to disk as JSON so the same restructuring can
+/// be re-applied later (e.g. to next month's export of the same document).
+#[tauri::command]
+fn save_organise_plan(plan: Vec<PageAction>, path: String) -> AppResult<()> {
+    let json = serde_json::to_string_pretty(&plan)
+        .map_err(|e| AppError::Validation(format!("Could not serialize organise plan: {}", e)))?;
+    fs::write(resolve_path(&path), json)?;
+    Ok(())
+}
+
+/// Loads an organiser action list previously written by `save_organise_plan`.
+#[tauri::command]
+fn load_organise_plan(path: String) -> AppResult<Vec<PageAction>> {
+    let json = fs::read_to_string(resolve_path(&path))?;
+    serde_json::from_str(&json)
+        .map_err(|e| AppError::Validation(format!("Invalid organise plan file: {}", e)))
+}
+
+/// Replaces the document's page tree with a single flat "Pages" node holding
+/// `page_ids` in order, then prunes anything no longer referenced.
+///
+/// This is the shared core of the Safe Tree Flattening strategy: page objects are
+/// reparented rather than copied, so their resources stay intact.
+fn rebuild_page_tree(doc: &mut Document, page_ids: Vec<lopdf::ObjectId>) -> AppResult<()> {
+    // Create a new "Pages" tree root.
+    // We flatten the tree to a single Pages object for simplicity and robustness.
+    let pages_root_id = doc.new_object_id();
+
+    // Update all pages to point to this new parent. Attributes inherited from
+    // the old intermediate nodes are copied onto each page first, since those
+    // nodes are about to be dropped.
+    for &page_id in &page_ids {
+        let inherited: Vec<(&[u8], Object)> = INHERITABLE_PAGE_ATTRS
+            .iter()
+            .filter(|&&key| !doc.get_dictionary(page_id).is_ok_and(|d| d.has(key)))
+            .filter_map(|&key| Some((key, inherited_attr(doc, page_id, key)?.clone())))
+            .collect();
+        if let Ok(page_dict) = doc.get_object_mut(page_id).and_then(|o| o.as_dict_mut()) {
+            for (key, value) in inherited {
+                page_dict.set(key, value);
+            }
+            page_dict.set(b"Parent", lopdf::Object::Reference(pages_root_id));
+        }
+    }
+
+    let kept: std::collections::HashSet<lopdf::ObjectId> = page_ids.iter().copied().collect();
+    let removed: std::collections::HashSet<lopdf::ObjectId> =
+        doc.get_pages().into_values().filter(|id| !kept.contains(id)).collect();
+    if !removed.is_empty() {
+        prune_structure_tree(doc, &removed);
+    }
+
+    // Create the Pages dictionary
+    let pages_dict = dictionary! {
+        b"Type" => "Pages",
+        b"Count" => page_ids.len() as i64,
+        b"Kids" => page_ids.into_iter().map(lopdf::Object::Reference).collect::<Vec<_>>(),
+    };
+
+    doc.objects.insert(pages_root_id, lopdf::Object::Dictionary(pages_dict));
+
+    // Update the Catalog to point to our new Pages root
+    let catalog_id = doc.trailer.get(b"Root")?.as_reference()?;
+    if let Ok(catalog) = doc.get_object_mut(catalog_id).and_then(|o| o.as_dict_mut()) {
+        catalog.set(b"Pages", lopdf::Object::Reference(pages_root_id));
+    }
+
+    // Prune unused objects (orphaned old Pages nodes, unused pages)
+    doc.prune_objects();
+    Ok(())
+}
+
+fn has_structure_tree(doc: &Document) -> bool {
+    doc.catalog().is_ok_and(|catalog| catalog.has(b"StructTreeRoot"))
+}
+
+/// Removes structure elements and parent-tree entries that point only at
+/// `removed` pages, so the tags of deleted pages (and the pages themselves)
+/// don't linger in the output.
+fn prune_structure_tree(doc: &mut Document, removed: &std::collections::HashSet<lopdf::ObjectId>) {
+    let Some(root_id) = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"StructTreeRoot"))
+        .and_then(|o| o.as_reference())
+        .ok()
+    else {
+        return;
+    };
+
+    // Parent-tree keys of the removed pages and their annotations.
+    let mut dead_keys = std::collections::HashSet::new();
+    for &page_id in removed {
+        let Ok(page) = doc.get_dictionary(page_id) else { continue };
+        if let Ok(key) = page.get(b"StructParents").and_then(|o| o.as_i64()) {
+            dead_keys.insert(key);
+        }
+        let annots = page
+            .get(b"Annots")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for annot in &annots {
+            if let Ok(key) = doc
+                .dereference(annot)
+                .and_then(|(_, o)| o.as_dict())
+                .and_then(|a| a.get(b"StructParent"))
+                .and_then(|o| o.as_i64())
+            {
+                dead_keys.insert(key);
+            }
+        }
+    }
+    if let Ok(parent_tree) = doc.get_dictionary(root_id).and_then(|r| r.get(b"ParentTree")).and_then(|o| o.as_reference()) {
+        prune_number_tree(doc, parent_tree, &dead_keys, &mut std::collections::HashSet::new());
+    }
+
+    let mut visited = std::collections::HashSet::new();
+    prune_struct_element(doc, root_id, None, removed, &mut visited);
+}
+
+fn prune_number_tree(
+    doc: &mut Document,
+    node_id: lopdf::ObjectId,
+    dead_keys: &std::collections::HashSet<i64>,
+    visited: &mut std::collections::HashSet<lopdf::ObjectId>,
+) {
+    if !visited.insert(node_id) {
+        return;
+    }
+    let Ok(node) = doc.get_dictionary_mut(node_id) else { return };
+    if let Ok(nums) = node.get_mut(b"Nums").and_then(|o| o.as_array_mut()) {
+        let pairs: Vec<Object> = nums
+            .chunks(2)
+            .filter(|pair| !pair[0].as_i64().is_ok_and(|key| dead_keys.contains(&key)))
+            .flatten()
+            .cloned()
+            .collect();
+        *nums = pairs;
+    }
+    let kids: Vec<lopdf::ObjectId> = node
+        .get(b"Kids")
+        .and_then(|o| o.as_array())
+        .map(|kids| kids.iter().filter_map(|kid| kid.as_reference().ok()).collect())
+        .unwrap_or_default();
+    for kid in kids {
+        prune_number_tree(doc, kid, dead_keys, visited);
+    }
+}
+
+/// Filters a structure element's /K down to content on surviving pages.
+/// Returns false when nothing is left and the element should be dropped.
+fn prune_struct_element(
+    doc: &mut Document,
+    id: lopdf::ObjectId,
+    inherited_page: Option<lopdf::ObjectId>,
+    removed: &std::collections::HashSet<lopdf::ObjectId>,
+    visited: &mut std::collections::HashSet<lopdf::ObjectId>,
+) -> bool {
+    if !visited.insert(id) {
+        return true;
+    }
+    let Ok(element) = doc.get_dictionary(id) else { return true };
+    let page = element.get(b"Pg").and_then(|o| o.as_reference()).ok().or(inherited_page);
+    let Ok(kids) = element.get(b"K").cloned() else {
+        return !page.is_some_and(|p| removed.contains(&p));
+    };
+    let (kids, was_array) = match kids {
+        Object::Array(items) => (items, true),
+        single => (vec![single], false),
+    };
+
+    let mut kept = Vec::new();
+    for kid in kids {
+        let keep = match &kid {
+            Object::Integer(_) => !page.is_some_and(|p| removed.contains(&p)),
+            Object::Dictionary(dict) => !struct_content_page(dict, page).is_some_and(|p| removed.contains(&p)),
+            Object::Reference(kid_id) => match doc.get_dictionary(*kid_id) {
+                Ok(dict) if dict.get(b"Type").and_then(|t| t.as_name()).is_ok_and(|t| t == b"MCR" || t == b"OBJR") => {
+                    !struct_content_page(dict, page).is_some_and(|p| removed.contains(&p))
+                }
+                Ok(_) => prune_struct_element(doc, *kid_id, page, removed, visited),
+                Err(_) => false,
+            },
+            _ => true,
+        };
+        if keep {
+            kept.push(kid);
+        }
+    }
+
+    if kept.is_empty() {
+        return false;
+    }
+    if let Ok(element) = doc.get_dictionary_mut(id) {
+        let k = if was_array || kept.len() > 1 { Object::Array(kept) } else { kept.remove(0) };
+        element.set("K", k);
+    }
+    true
+}
+
+/// The page a marked-content or object reference lives on.
+fn struct_content_page(dict: &lopdf::Dictionary, inherited: Option<lopdf::ObjectId>) -> Option<lopdf::ObjectId> {
+    dict.get(b"Pg").and_then(|o| o.as_reference()).ok().or(inherited)
+}
+
+/// Recomputes /Count on every Pages node from the pages actually reachable
+/// through /Kids, fixing documents whose declared count disagrees with the tree.
+#[tauri::command]
+fn fix_page_count(path: String, output_path: String) -> AppResult<PageCountReport> {
+    let mut doc = load_pdf(&path)?;
+    let root_id = doc.catalog()?.get(b"Pages")?.as_reference()?;
+    let declared_count = doc.get_dictionary(root_id)?.get(b"Count").and_then(|o| o.as_i64()).ok();
+
+    let mut visited = std::collections::HashSet::new();
+    let mut nodes_fixed = 0;
+    let actual_count = recount_pages(&mut doc, root_id, &mut visited, &mut nodes_fixed);
+
+    doc.save(resolve_path(&output_path))?;
+    Ok(PageCountReport {
+        declared_count,
+        actual_count,
+        nodes_fixed,
+    })
+}
+
+/// Returns the number of pages under `node_id`, rewriting /Count on every
+/// Pages node whose value is wrong. Nodes already visited count as empty, so
+/// cycles and shared subtrees can't inflate the total.
+fn recount_pages(
+    doc: &mut Document,
+    node_id: lopdf::ObjectId,
+    visited: &mut std::collections::HashSet<lopdf::ObjectId>,
+    nodes_fixed: &mut u32,
+) -> u32 {
+    if !visited.insert(node_id) {
+        return 0;
+    }
+    let kids: Vec<lopdf::ObjectId> = match doc
+        .get_dictionary(node_id)
+        .and_then(|d| d.get(b"Kids"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_array())
+    {
+        Ok(kids) => kids.iter().filter_map(|o| o.as_reference().ok()).collect(),
+        // No /Kids: a leaf page.
+        Err(_) => return 1,
+    };
+
+    let count: u32 = kids
+        .into_iter()
+        .map(|kid| recount_pages(doc, kid, visited, nodes_fixed))
+        .sum();
+    if let Ok(node) = doc.get_dictionary_mut(node_id) {
+        if node.get(b"Count").and_then(|o| o.as_i64()).ok() != Some(count as i64) {
+            node.set("Count", count as i64);
+            *nodes_fixed += 1;
+        }
+    }
+    count
+}
+
+/// Page attributes that a page may inherit from its ancestors in the tree.
+const INHERITABLE_PAGE_ATTRS: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Repairs a nested or inconsistent page tree by rebuilding it as a single
+/// flat Pages node with every page in its current order.
+#[tauri::command]
+fn flatten_page_tree(path: String, output_path: String) -> AppResult<()> {
+    let mut doc = load_pdf(&path)?;
+    let page_ids: Vec<lopdf::ObjectId> = doc.get_pages().into_values().collect();
+    if page_ids.is_empty() {
+        return Err(AppError::Validation("PDF has no pages.".to_string()));
+    }
+
+    rebuild_page_tree(&mut doc, page_ids)?;
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Trims a document down to the given pages, discarding every other page.
+/// Ranges are applied in the order given.
+#[tauri::command]
+fn keep_pages(path: String, ranges: PageSelection, output_path: String) -> AppResult<()> {
+    let mut doc = load_pdf(&path)?;
+    let pages = doc.get_pages();
+    let kept_ids = page_ids_for_selection(&pages, &ranges)?;
+
+    rebuild_page_tree(&mut doc, kept_ids)?;
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Removes the given pages (1-based, repeats ignored) and writes the rest, in
+/// their original order.
+#[tauri::command]
+fn delete_pages(path: String, pages: Vec<u32>, output_path: String) -> AppResult<()> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let page_map = doc.get_pages();
+    let page_count = page_map.len() as u32;
+    let mut deleted = std::collections::HashSet::new();
+    for page in pages {
+        check_page_range(page, page, page_count)?;
+        deleted.insert(page);
+    }
+    if deleted.len() as u32 == page_count {
+        return Err(AppError::Validation("Can't delete every page of the document.".to_string()));
+    }
+
+    let kept_ids = page_map
+        .iter()
+        .filter(|(page_number, _)| !deleted.contains(*page_number))
+        .map(|(_, &id)| id)
+        .collect();
+    rebuild_page_tree(&mut doc, kept_ids)?;
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Writes a sample of a document: its first `first` and last `last` pages.
+/// When the two overlap, every page is kept once.
+#[tauri::command]
+fn sample_pages(path: String, first: u32, last: u32, output_path: String) -> AppResult<()> {
+    if first == 0 && last == 0 {
+        return Err(AppError::Validation("Choose at least one page to sample.".to_string()));
+    }
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let pages = doc.get_pages();
+    let page_count = pages.len() as u32;
+
+    let head_end = first.min(page_count);
+    let tail_start = page_count.saturating_sub(last).max(head_end) + 1;
+    let kept_ids = (1..=head_end)
+        .chain(tail_start..=page_count)
+        .filter_map(|page_number| pages.get(&page_number).copied())
+        .collect();
+
+    rebuild_page_tree(&mut doc, kept_ids)?;
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Resolves a page selection to page object ids, in selection order and
+/// without duplicates.
+fn page_ids_for_selection(
+    pages: &std::collections::BTreeMap<u32, lopdf::ObjectId>,
+    selection: &PageSelection,
+) -> AppResult<Vec<lopdf::ObjectId>> {
+    let mut kept_ids = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for page_number in selection.page_numbers(pages.len() as u32)? {
+        // Overlapping ranges keep a page once; a trim never duplicates pages.
+        if let Some(&id) = pages.get(&page_number) {
+            if seen.insert(id) {
+                kept_ids.push(id);
+            }
+        }
+    }
+    Ok(kept_ids)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlankTrimReport {
+    pub leading_removed: u32,
+    pub trailing_removed: u32,
+}
+
+/// Drops blank pages from the end of the document (and from the start when
+/// `include_leading` is set), leaving interior blanks alone. Exports that pad
+/// to an even page count are the usual source of these.
+#[tauri::command]
+fn trim_trailing_blank_pages(
+    path: String,
+    output_path: String,
+    include_leading: Option<bool>,
+) -> AppResult<BlankTrimReport> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let page_ids: Vec<lopdf::ObjectId> = doc.get_pages().into_values().collect();
+
+    let trailing = page_ids.iter().rev().take_while(|&&id| page_is_blank(&doc, id)).count();
+    if trailing == page_ids.len() {
+        return Err(AppError::Validation("Every page is blank.".to_string()));
+    }
+    let leading = if include_leading.unwrap_or(false) {
+        page_ids.iter().take_while(|&&id| page_is_blank(&doc, id)).count()
+    } else {
+        0
+    };
+
+    let kept = page_ids[leading..page_ids.len() - trailing].to_vec();
+    rebuild_page_tree(&mut doc, kept)?;
+    doc.save(resolve_path(&output_path))?;
+    Ok(BlankTrimReport {
+        leading_removed: leading as u32,
+        trailing_removed: trailing as u32,
+    })
+}
+
+/// True when a page draws nothing: no painted paths, images, shadings or
+/// visible non-space text, and no annotations other than links. Anything
+/// we can't parse counts as content, so pages are never dropped on a guess.
+fn page_is_blank(doc: &Document, page_id: lopdf::ObjectId) -> bool {
+    let has_annotations = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Annots"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_array())
+        .is_ok_and(|annots| {
+            annots.iter().any(|annot| {
+                !doc.dereference(annot)
+                    .and_then(|(_, o)| o.as_dict())
+                    .and_then(|a| a.get(b"Subtype"))
+                    .and_then(|o| o.as_name())
+                    .is_ok_and(|subtype| subtype == b"Link" || subtype == b"Popup")
+            })
+        });
+    if has_annotations {
+        return false;
+    }
+    let Ok(content) = doc.get_page_content(page_id) else { return false };
+    let resources = inherited_attr(doc, page_id, b"Resources")
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok());
+    content_is_blank(doc, &content, resources, 0)
+}
+
+fn content_is_blank(doc: &Document, content: &[u8], resources: Option<&lopdf::Dictionary>, depth: u32) -> bool {
+    use lopdf::content::Content;
+
+    let Ok(content) = Content::decode(content) else { return false };
+    let mut render_mode = 0;
+    for op in &content.operations {
+        match op.operator.as_str() {
+            "S" | "s" | "f" | "F" | "f*" | "B" | "B*" | "b" | "b*" | "sh" | "BI" | "EI" => return false,
+            "Tr" => render_mode = op.operands.first().and_then(|o| o.as_i64().ok()).unwrap_or(0),
+            "Tj" | "'" | "\"" | "TJ" if render_mode != 3 => {
+                let shows_ink = |obj: &Object| match obj {
+                    Object::String(bytes, _) => bytes.iter().any(|b| !matches!(b, 0 | b' ' | b'\t' | b'\n' | b'\r')),
+                    Object::Array(items) => items
+                        .iter()
+                        .any(|i| matches!(i, Object::String(b, _) if b.iter().any(|b| !matches!(b, 0 | b' ' | b'\t' | b'\n' | b'\r')))),
+                    _ => false,
+                };
+                if op.operands.iter().any(shows_ink) {
+                    return false;
+                }
+            }
+            "Do" => {
+                let Some(xobject) = op
+                    .operands
+                    .first()
+                    .and_then(|o| o.as_name().ok())
+                    .and_then(|name| {
+                        resources?
+                            .get(b"XObject")
+                            .and_then(|o| doc.dereference(o))
+                            .and_then(|(_, o)| o.as_dict())
+                            .and_then(|d| d.get(name))
+                            .and_then(|o| doc.dereference(o))
+                            .and_then(|(_, o)| o.as_stream())
+                            .ok()
+                    })
+                else {
+                    return false;
+                };
+                let is_form = xobject.dict.get(b"Subtype").and_then(|o| o.as_name()).is_ok_and(|n| n == b"Form");
+                if !is_form || depth >= 8 {
+                    return false;
+                }
+                let form_resources = xobject
+                    .dict
+                    .get(b"Resources")
+                    .and_then(|o| doc.dereference(o))
+                    .and_then(|(_, o)| o.as_dict())
+                    .ok()
+                    .or(resources);
+                let Ok(form_content) = xobject.get_plain_content() else { return false };
+                if !content_is_blank(doc, &form_content, form_resources, depth + 1) {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    true
+}
+
+#[tauri::command]
+fn debug_pdf_structure(path: String) -> AppResult<PdfDiagnosticResult> {
+    let mut file = fs::File::open(resolve_path(&path))?;
+    let metadata = file.metadata()?;
+    let file_size = metadata.len();
+
+    let mut header_buf = vec![0u8; 1024.min(file_size as usize)];
+    file.read_exact(&mut header_buf)?;
+    let header_str = String::from_utf8_lossy(&header_buf).to_string();
+
+    let mut trailer_buf = vec![0u8; 2048.min(file_size as usize)];
+    let seek_pos = if file_size > 2048 { file_size - 2048 } else { 0 };
+    file.seek(SeekFrom::Start(seek_pos))?;
+    file.read_exact(&mut trailer_buf)?;
+    let trailer_str = String::from_utf8_lossy(&trailer_buf).to_string();
+
+    Ok(PdfDiagnosticResult {
+        header: header_str,
+        trailer: trailer_str,
+        file_size,
+    })
+}
+
+fn decode_pdf_text(obj: &Object) -> String {
+    match obj {
+        Object::String(bytes, _) => {
+            if bytes.starts_with(&[0xFE, 0xFF]) {
+                let tail = &bytes[2..];
+                // Guard against odd-length UTF-16 data from malformed PDFs
+                let even_len = tail.len() & !1;
+                let utf16: Vec<u16> = tail[..even_len]
+                    .chunks_exact(2)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                String::from_utf16_lossy(&utf16)
+            } else if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+                String::from_utf8_lossy(&bytes[3..]).to_string()
+            } else {
+                bytes.iter().map(|&b| b as char).collect()
+            }
+        }
+        Object::Name(bytes) => String::from_utf8_lossy(bytes).to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Effective resolution of each image XObject at its highest-resolution
+/// placement. Images are drawn onto the unit square, so the CTM in effect at
+/// `Do` gives the rendered width and height in points as the lengths of its
+/// column vectors; the DPI is the larger of the two axes' pixels per inch.
+/// Images never drawn from page content (soft masks, unused resources) are
+/// absent.
+fn image_placement_dpi(doc: &Document) -> std::collections::HashMap<lopdf::ObjectId, f64> {
+    let mut dpis = std::collections::HashMap::new();
+    for (_, page_id) in doc.get_pages() {
+        let Ok(content) = doc.get_page_content(page_id) else { continue };
+        let Some(resources) = inherited_attr(doc, page_id, b"Resources")
+            .and_then(|o| doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_dict().ok())
+        else {
+            continue;
+        };
+        collect_image_dpi(doc, &content, resources, IDENTITY, 0, &mut dpis);
+    }
+    dpis
+}
+
+fn collect_image_dpi(
+    doc: &Document,
+    content: &[u8],
+    resources: &lopdf::Dictionary,
+    base: Matrix,
+    depth: u32,
+    dpis: &mut std::collections::HashMap<lopdf::ObjectId, f64>,
+) {
+    use lopdf::content::Content;
+
+    let Ok(content) = Content::decode(content) else { return };
+    let mut ctm = base;
+    let mut stack = Vec::new();
+    for op in &content.operations {
+        match op.operator.as_str() {
+            "q" => stack.push(ctm),
+            "Q" => ctm = stack.pop().unwrap_or(ctm),
+            "cm" => {
+                let n: Vec<f64> = op.operands.iter().map(|o| o.as_float().unwrap_or(0.0) as f64).collect();
+                if n.len() == 6 {
+                    ctm = mat_mul(&[n[0], n[1], n[2], n[3], n[4], n[5]], &ctm);
+                }
+            }
+            "Do" if depth < 8 => {
+                let Some(id) = op.operands.first().and_then(|o| o.as_name().ok()).and_then(|name| {
+                    resources
+                        .get(b"XObject")
+                        .and_then(|o| doc.dereference(o))
+                        .and_then(|(_, o)| o.as_dict())
+                        .and_then(|d| d.get(name))
+                        .and_then(|o| o.as_reference())
+                        .ok()
+                }) else {
+                    continue;
+                };
+                let Ok(stream) = doc.get_object(id).and_then(|o| o.as_stream()) else { continue };
+                match stream.dict.get(b"Subtype").and_then(|o| o.as_name()) {
+                    Ok(b"Image") => {
+                        let pixels = |key: &[u8]| stream.dict.get(key).and_then(|o| o.as_i64()).unwrap_or(0) as f64;
+                        let axis_dpi = |px: f64, pts: f64| if pts > 0.01 { px * 72.0 / pts } else { 0.0 };
+                        let dpi = axis_dpi(pixels(b"Width"), ctm[0].hypot(ctm[1]))
+                            .max(axis_dpi(pixels(b"Height"), ctm[2].hypot(ctm[3])));
+                        if dpi > 0.0 {
+                            let entry = dpis.entry(id).or_insert(0.0);
+                            *entry = entry.max(dpi);
+                        }
+                    }
+                    Ok(b"Form") => {
+                        let matrix = stream
+                            .dict
+                            .get(b"Matrix")
+                            .and_then(|o| o.as_array())
+                            .ok()
+                            .map(|a| a.iter().map(|o| o.as_float().unwrap_or(0.0) as f64).collect::<Vec<_>>())
+                            .filter(|v| v.len() == 6)
+                            .map(|v| [v[0], v[1], v[2], v[3], v[4], v[5]])
+                            .unwrap_or(IDENTITY);
+                        let form_resources = stream
+                            .dict
+                            .get(b"Resources")
+                            .and_then(|o| doc.dereference(o))
+                            .and_then(|(_, o)| o.as_dict())
+                            .unwrap_or(resources);
+                        if let Ok(form_content) = stream.get_plain_content() {
+                            collect_image_dpi(doc, &form_content, form_resources, mat_mul(&matrix, &ctm), depth + 1, dpis);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[tauri::command]
+fn get_pdf_properties(path: String) -> AppResult<PdfProperties> {
+    let doc = load_pdf(&path)?;
+    let pages = doc.get_pages();
+    let page_count = pages.len() as u32;
+
+    // Get page size from first page
+    let mut page_width = 0.0;
+    let mut page_height = 0.0;
+    let mut colorspace = "DeviceRGB (Likely)".to_string();
+
+    if let Some(&page_id) = pages.get(&1) {
+        let page_dict = doc.get_dictionary(page_id)?;
+        
+        // Dimensions
+        if let Some(r) = page_dict.get(b"MediaBox").ok().and_then(|o| parse_rect(&doc, o)) {
+            page_width = (r[2] - r[0]).abs() as f32;
+            page_height = (r[3] - r[1]).abs() as f32;
+        }
+
+        // Colorspace detection (Advanced)
+        if let Ok(resources) = page_dict.get(b"Resources").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
+            if let Ok(cs_dict) = resources.get(b"ColorSpace").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
+                if cs_dict.has(b"DeviceCMYK") || cs_dict.iter().any(|(k, _)| k == b"CMYK") {
+                    colorspace = "DeviceCMYK".to_string();
+                } else if cs_dict.has(b"DeviceGray") || cs_dict.iter().any(|(k, _)| k == b"Gray") {
+                    colorspace = "DeviceGray".to_string();
+                }
+            }
+            // Check for CMYK/Gray in XObjects too
+            if let Ok(xobjects) = resources.get(b"XObject").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
+                for (_, xo_ref) in xobjects.iter() {
+                    if let Ok(xo) = doc.dereference(xo_ref).and_then(|(_, o)| o.as_dict()) {
+                        if let Ok(xo_cs) = xo.get(b"ColorSpace").and_then(|o| doc.dereference(o)) {
+                            match xo_cs.1 {
+                                Object::Name(n) if n == b"DeviceCMYK" => colorspace = "DeviceCMYK".to_string(),
+                                Object::Name(n) if n == b"DeviceGray" => colorspace = "DeviceGray".to_string(),
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mut metadata = std::collections::HashMap::new();
+    let mut created = String::new();
+    let mut modified = String::new();
+    let mut producer = String::new();
+    let mut creator = String::new();
+
+    if let Ok(info_id) = doc.trailer.get(b"Info").and_then(|o| o.as_reference()) {
+        if let Ok(info) = doc.get_object(info_id).and_then(|o| o.as_dict()) {
+            for (key, value) in info {
+                let key_str = String::from_utf8_lossy(key).to_string();
+                let val_str = decode_pdf_text(value);
+                if !val_str.is_empty() {
+                    match key_str.as_str() {
+                        "CreationDate" => created = val_str,
+                        "ModDate" => modified = val_str,
+                        "Producer" => producer = val_str,
+                        "Creator" => creator = val_str,
+                        _ => { metadata.insert(key_str, val_str); }
+                    }
+                }
+            }
+        }
+    }
+
+    // Font detection
+    let mut fonts = std::collections::HashSet::new();
+
+    for id in doc.objects.keys() {
+        if let Ok(obj) = doc.get_object(*id) {
+            if let Ok(dict) = obj.as_dict() {
+                // Fonts
+                if dict.get(b"Type").map_or(false, |t| t.as_name().map_or(false, |n| n == b"Font")) {
+                    if let Ok(base_font) = dict.get(b"BaseFont").and_then(|o| o.as_name()) {
+                        fonts.insert(String::from_utf8_lossy(base_font).to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut image_dpis: Vec<u32> = image_placement_dpi(&doc).into_values().map(|dpi| dpi.round() as u32).collect();
+    image_dpis.sort_unstable();
+    let image_dpi_summary = (!image_dpis.is_empty()).then(|| DpiSummary {
+        min: image_dpis[0],
+        typical: image_dpis[image_dpis.len() / 2],
+        max: image_dpis[image_dpis.len() - 1],
+    });
+
+    let catalog_version = catalog_version(&doc);
+    let version = match &catalog_version {
+        Some(v) if version_key(v) > version_key(&doc.version) => v.clone(),
+        _ => doc.version.clone(),
+    };
+
+    Ok(PdfProperties {
+        version,
+        header_version: doc.version.clone(),
+        catalog_version,
+        page_count,
+        page_size: String::new(), // Legacy field keeping to avoid breaking too much at once
+        metadata,
+        created,
+        modified,
+        encrypted: doc.trailer.has(b"Encrypt"),
+        producer,
+        creator,
+        fonts: fonts.into_iter().collect(),
+        image_dpi: image_dpis,
+        image_dpi_summary,
+        doc_dpi: 72,
+        colorspace,
+        page_width,
+        page_height,
+        language: document_language(&doc),
+        page_labels: page_labels(&doc, page_count),
+    })
+}
+
+/// Largest value given a roman or letter label. Both grow with the value (a
+/// letter label repeats its letter once per 26), so larger ones use decimal.
+const MAX_LETTER_LABEL: u64 = 10_000;
+
+/// Resolves the catalog /PageLabels number tree to one label per page.
+fn page_labels(doc: &Document, page_count: u32) -> Vec<String> {
+    let Ok(root) = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"PageLabels"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+    else {
+        return Vec::new();
+    };
+    let mut ranges: Vec<(u32, lopdf::Dictionary)> = Vec::new();
+    collect_number_tree(doc, root, &mut ranges, 0);
+    if ranges.is_empty() {
+        return Vec::new();
+    }
+    ranges.sort_by_key(|(start, _)| *start);
+
+    (0..page_count)
+        .map(|index| {
+            let Some((start, style)) = ranges.iter().rev().find(|(start, _)| *start <= index) else {
+                return (index + 1).to_string();
+            };
+            let prefix = style.get(b"P").map(decode_pdf_text).unwrap_or_default();
+            let first = style
+                .get(b"St")
+                .and_then(|o| o.as_i64())
+                .ok()
+                .and_then(|st| u32::try_from(st).ok())
+                .unwrap_or(1)
+                .max(1);
+            let value = u64::from(first) + u64::from(index - start);
+            let letters = (value <= MAX_LETTER_LABEL).then_some(value as u32);
+            let number = match (style.get(b"S").and_then(|o| o.as_name()), letters) {
+                (Ok(b"D"), _) => value.to_string(),
+                (Ok(b"R"), Some(value)) => roman_numeral(value),
+                (Ok(b"r"), Some(value)) => roman_numeral(value).to_lowercase(),
+                (Ok(b"A"), Some(value)) => alphabetic_label(value),
+                (Ok(b"a"), Some(value)) => alphabetic_label(value).to_lowercase(),
+                (Ok(b"R" | b"r" | b"A" | b"a"), None) => value.to_string(),
+                _ => String::new(),
+            };
+            format!("{}{}", prefix, number)
+        })
+        .collect()
+}
+
+fn collect_number_tree(doc: &Document, node: &lopdf::Dictionary, out: &mut Vec<(u32, lopdf::Dictionary)>, depth: u32) {
+    if depth > 32 {
+        return;
+    }
+    if let Ok(nums) = node.get(b"Nums").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()) {
+        for pair in nums.chunks(2) {
+            let (Some(key), Some(value)) = (pair.first(), pair.get(1)) else { continue };
+            let key = key.as_i64().ok().and_then(|k| u32::try_from(k).ok());
+            let value = doc.dereference(value).and_then(|(_, o)| o.as_dict()).ok();
+            if let (Some(key), Some(value)) = (key, value) {
+                out.push((key, value.clone()));
+            }
+        }
+    }
+    if let Ok(kids) = node.get(b"Kids").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()) {
+        for kid in kids {
+            if let Ok(kid) = doc.dereference(kid).and_then(|(_, o)| o.as_dict()) {
+                collect_number_tree(doc, kid, out, depth + 1);
+            }
+        }
+    }
+}
+
+fn roman_numeral(mut value: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+        (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+    ];
+    let mut out = String::new();
+    for &(n, numeral) in &NUMERALS {
+        while value >= n {
+            out.push_str(numeral);
+            value -= n;
+        }
+    }
+    out
+}
+
+/// PDF letter labels: A..Z, then AA..ZZ, AAA..ZZZ and so on.
+fn alphabetic_label(value: u32) -> String {
+    let value = value.max(1) - 1;
+    let letter = (b'A' + (value % 26) as u8) as char;
+    letter.to_string().repeat(value as usize / 26 + 1)
+}
+
+/// The catalog /Version entry (a name such as `/1.7`), if present.
+fn catalog_version(doc: &Document) -> Option<String> {
+    doc.catalog()
+        .and_then(|catalog| catalog.get(b"Version"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_name())
+        .map(|v| String::from_utf8_lossy(v).trim().to_string())
+        .ok()
+        .filter(|v| !v.is_empty())
+}
+
+/// Orders version strings numerically, so `1.10` sorts after `1.7`.
+fn version_key(version: &str) -> (u32, u32) {
+    let mut parts = version.split('.').map(|p| p.trim().parse::<u32>().unwrap_or(0));
+    (parts.next().unwrap_or(0), parts.next().unwrap_or(0))
+}
+
+fn document_language(doc: &Document) -> Option<String> {
+    doc.catalog()
+        .and_then(|catalog| catalog.get(b"Lang"))
+        .and_then(|o| doc.dereference(o))
+        .map(|(_, o)| decode_pdf_text(o).trim().to_string())
+        .ok()
+        .filter(|lang| !lang.is_empty())
+}
+
+/// Writes the catalog /Lang entry that screen readers use to pick a voice.
+#[tauri::command]
+fn set_document_language(path: String, lang: String, output_path: String) -> AppResult<()> {
+    let lang = lang.trim();
+    if !is_language_tag(lang) {
+        return Err(AppError::Validation(format!(
+            "'{}' is not a language tag (expected something like 'en' or 'en-GB').",
+            lang
+        )));
+    }
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    doc.catalog_mut()?.set("Lang", Object::string_literal(lang));
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Info keys whose values are PDF names rather than text strings.
+const INFO_NAME_KEYS: &[&str] = &["Trapped"];
+
+/// Info keys that take a date; they accept ISO 8601 or PDF date input and are
+/// written in the PDF `D:YYYYMMDDHHmmSS` form.
+const INFO_DATE_KEYS: &[&str] = &["CreationDate", "ModDate"];
+
+/// Info keys mirrored in the XMP packet, with the XMP property and the RDF
+/// container (if any) it is written in.
+const INFO_XMP_PROPERTIES: &[(&str, &str, Option<&str>)] = &[
+    ("Title", "dc:title", Some("rdf:Alt")),
+    ("Author", "dc:creator", Some("rdf:Seq")),
+    ("Subject", "dc:description", Some("rdf:Alt")),
+    ("Keywords", "pdf:Keywords", None),
+    ("Creator", "xmp:CreatorTool", None),
+    ("Producer", "pdf:Producer", None),
+    ("CreationDate", "xmp:CreateDate", None),
+    ("ModDate", "xmp:ModifyDate", None),
+    ("Trapped", "pdf:Trapped", None),
+];
+
+/// Writes Info dictionary entries, creating the dictionary if needed. An
+/// empty value removes the key. `Trapped` is written as a name and must be
+/// True, False or Unknown; `CreationDate` and `ModDate` take an ISO 8601 date
+/// (`2024-03-05`, `2024-03-05T14:30:00`, optionally with `Z` or `+01:00`) or
+/// a PDF date (`D:20240305143000+01'00'`), which is kept as given;
+/// everything else is written as a text string, UTF-16BE when it is not
+/// plain ASCII.
+///
+/// If the catalog has an XMP packet, the matching XMP properties are updated
+/// (or removed) too so the two stay consistent. A packet too malformed to
+/// edit is a validation error rather than being dropped, since it may carry
+/// PDF/A or PDF/X identification that the Info dictionary can't.
+#[tauri::command]
+fn set_metadata(
+    path: String,
+    fields: std::collections::HashMap<String, String>,
+    output_path: String,
+) -> AppResult<()> {
+    let mut entries = Vec::new();
+    let mut xmp_updates = Vec::new();
+    for (key, value) in &fields {
+        if key.is_empty() || key.chars().any(|c| c.is_whitespace() || "/()<>[]{}%".contains(c)) {
+            return Err(AppError::Validation(format!("'{}' is not a valid metadata key.", key)));
+        }
+        let value = value.trim();
+        let mut xmp_value = Some(value.to_string());
+        let object = if value.is_empty() {
+            xmp_value = None;
+            None
+        } else if INFO_NAME_KEYS.contains(&key.as_str()) {
+            if !matches!(value, "True" | "False" | "Unknown") {
+                return Err(AppError::Validation(format!(
+                    "{} must be True, False or Unknown, not '{}'.",
+                    key, value
+                )));
+            }
+            Some(Object::Name(value.as_bytes().to_vec()))
+        } else if INFO_DATE_KEYS.contains(&key.as_str()) {
+            let (pdf_date, xmp_date) = iso_to_pdf_date(value).or_else(|| parse_pdf_date(value)).ok_or_else(|| {
+                AppError::Validation(format!(
+                    "{} must be an ISO date such as 2024-03-05 or 2024-03-05T14:30:00+01:00, or a PDF date such as D:20240305143000+01'00', not '{}'.",
+                    key, value
+                ))
+            })?;
+            xmp_value = Some(xmp_date);
+            Some(Object::string_literal(pdf_date))
+        } else {
+            Some(lopdf::text_string(value))
+        };
+        if let Some((_, property, container)) = INFO_XMP_PROPERTIES.iter().find(|(k, _, _)| k == key) {
+            xmp_updates.push((*property, xmp_value.map(|v| xmp_element(property, *container, &v))));
+        }
+        entries.push((key.clone(), object));
+    }
+
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let info_id = match doc.trailer.get(b"Info") {
+        Ok(Object::Reference(id)) => *id,
+        Ok(Object::Dictionary(inline)) => {
+            let inline = inline.clone();
+            doc.add_object(inline)
+        }
+        _ => doc.add_object(lopdf::Dictionary::new()),
+    };
+    doc.trailer.set("Info", info_id);
+    let info = doc.get_dictionary_mut(info_id)?;
+    for (key, object) in entries {
+        match object {
+            Some(object) => info.set(key.into_bytes(), object),
+            None => {
+                info.remove(key.as_bytes());
+            }
+        }
+    }
+
+    let xmp_id = doc.catalog()?.get(b"Metadata").and_then(|o| o.as_reference()).ok();
+    if let (Some(xmp_id), false) = (xmp_id, xmp_updates.is_empty()) {
+        let stream = doc.get_object_mut(xmp_id).and_then(|o| o.as_stream_mut());
+        let updated = stream.ok().and_then(|stream| {
+            let xml = stream.get_plain_content().ok()?;
+            let xml = update_xmp_packet(std::str::from_utf8(&xml).ok()?, &xmp_updates)?;
+            stream.set_plain_content(xml.into_bytes());
+            Some(())
+        });
+        if updated.is_none() {
+            return Err(AppError::Validation(
+                "The document's XMP metadata couldn't be read, so it can't be kept in step with the new values.".to_string(),
+            ));
+        }
+    }
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Former name of `set_metadata`, kept so existing callers keep working.
+#[tauri::command]
+fn set_pdf_metadata(
+    path: String,
+    fields: std::collections::HashMap<String, String>,
+    output_path: String,
+) -> AppResult<()> {
+    set_metadata(path, fields, output_path)
+}
+
+/// Parses an ISO 8601 date or date-time into the PDF date string
+/// (`D:20240305143000+01'00'`) and the XMP form of the same instant. A
+/// missing time means midnight; a missing offset leaves the zone unstated.
+fn iso_to_pdf_date(value: &str) -> Option<(String, String)> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime};
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        let xmp = date.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        return Some((pdf_date(&date), xmp));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(value, format) {
+            return Some((date.format("D:%Y%m%d%H%M%S").to_string(), date.format("%Y-%m-%dT%H:%M:%S").to_string()));
+        }
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    Some((date.format("D:%Y%m%d000000").to_string(), date.format("%Y-%m-%d").to_string()))
+}
+
+/// Checks a PDF date string (`D:YYYYMMDDHHmmSSOHH'mm'`, where everything
+/// after the year is optional) and returns it unchanged, with the XMP form of
+/// the same date. A missing offset leaves the zone unstated.
+fn parse_pdf_date(value: &str) -> Option<(String, String)> {
+    let rest = value.strip_prefix("D:")?;
+    let (stamp, zone) = rest.split_at(rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len()));
+    if !matches!(stamp.len(), 4 | 6 | 8 | 10 | 12 | 14) {
+        return None;
+    }
+    let field = |at: usize, default: u32| stamp.get(at..at + 2).map_or(Some(default), |f| f.parse().ok());
+    let (month, day) = (field(4, 1)?, field(6, 1)?);
+    let (hour, minute, second) = (field(8, 0)?, field(10, 0)?, field(12, 0)?);
+    chrono::NaiveDate::from_ymd_opt(stamp[..4].parse().ok()?, month, day)?.and_hms_opt(hour, minute, second)?;
+
+    // The offset is `Z`, or a sign with hours and optional minutes, each
+    // usually followed by an apostrophe.
+    let offset = match zone.chars().next() {
+        None => String::new(),
+        Some(sign @ ('+' | '-' | 'Z')) => {
+            let digits: String = zone[1..].chars().filter(|&c| c != '\'').collect();
+            if !digits.chars().all(|c| c.is_ascii_digit()) || !matches!(digits.len(), 0 | 2 | 4) {
+                return None;
+            }
+            let hours: u32 = digits.get(..2).map_or(Some(0), |h| h.parse().ok())?;
+            let minutes: u32 = digits.get(2..).filter(|m| !m.is_empty()).map_or(Some(0), |m| m.parse().ok())?;
+            if hours > 23 || minutes > 59 || (sign == 'Z' && hours + minutes > 0) {
+                return None;
+            }
+            if sign == 'Z' || (sign != '-' && hours + minutes == 0) {
+                "Z".to_string()
+            } else {
+                format!("{}{:02}:{:02}", sign, hours, minutes)
+            }
+        }
+        Some(_) => return None,
+    };
+
+    let mut xmp = stamp[..4].to_string();
+    for part in [stamp.get(4..6), stamp.get(6..8)].into_iter().flatten() {
+        xmp.push('-');
+        xmp.push_str(part);
+    }
+    if stamp.len() >= 10 {
+        xmp.push_str(&format!("T{:02}:{:02}", hour, minute));
+        if stamp.len() == 14 {
+            xmp.push_str(&format!(":{:02}", second));
+        }
+        xmp.push_str(&offset);
+    }
+    Some((value.to_string(), xmp))
+}
+
+/// Formats a date with its UTC offset as a PDF date string.
+fn pdf_date(date: &chrono::DateTime<chrono::FixedOffset>) -> String {
+    let offset = date.offset().local_minus_utc();
+    let zone = if offset == 0 {
+        "Z".to_string()
+    } else {
+        let sign = if offset < 0 { '-' } else { '+' };
+        format!("{}{:02}'{:02}'", sign, offset.abs() / 3600, offset.abs() % 3600 / 60)
+    };
+    format!("{}{}", date.format("D:%Y%m%d%H%M%S"), zone)
+}
+
+/// Serialises one XMP property element; Seq values are split on ';' into
+/// one item each, matching how `xmp_properties` joins them.
+fn xmp_element(name: &str, container: Option<&str>, value: &str) -> String {
+    match container {
+        Some("rdf:Alt") => format!(
+            "<{0}><rdf:Alt><rdf:li xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></{0}>",
+            name,
+            xml_escape(value)
+        ),
+        Some(container) => {
+            let items: String = value
+                .split(';')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| format!("<rdf:li>{}</rdf:li>", xml_escape(item)))
+                .collect();
+            format!("<{0}><{1}>{2}</{1}></{0}>", name, container, items)
+        }
+        None => format!("<{0}>{1}</{0}>", name, xml_escape(value)),
+    }
+}
+
+/// Applies property updates to an XMP packet. Each property is replaced in
+/// place where it appears as an element and dropped where it appears as an
+/// rdf:Description attribute; properties that end up missing are added in
+/// a new rdf:Description. `None` removes the property. Returns `None` when
+/// the packet has no rdf:RDF to add to.
+fn update_xmp_packet(xml: &str, updates: &[(&str, Option<String>)]) -> Option<String> {
+    let mut xml = xml.to_string();
+    let mut missing = String::new();
+    for (name, element) in updates {
+        remove_xmp_attribute(&mut xml, name);
+        let mut replacement = element.as_deref();
+        let mut found = false;
+        let mut from = 0;
+        while let Some((start, end)) = find_xmp_element(&xml, name, from) {
+            let text = replacement.take().unwrap_or("");
+            xml.replace_range(start..end, text);
+            from = start + text.len();
+            found = true;
+        }
+        if let (false, Some(element)) = (found, element) {
+            missing.push_str(element);
+        }
+    }
+    let rdf_end = xml.rfind("</rdf:RDF>")?;
+    if !missing.is_empty() {
+        let about = xml
+            .find("rdf:about=")
+            .and_then(|i| xml_attributes(&format!("x {}", &xml[i..])).into_iter().next())
+            .map(|(_, about)| about)
+            .unwrap_or_default();
+        let description = format!(
+            "<rdf:Description rdf:about=\"{}\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+             xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\">{}</rdf:Description>",
+            xml_escape(&about),
+            missing
+        );
+        xml.insert_str(rdf_end, &description);
+    }
+    Some(xml)
+}
+
+/// Finds the byte range of the first `<name ...>...</name>` (or self-closing
+/// `<name/>`) element at or after `from`.
+fn find_xmp_element(xml: &str, name: &str, from: usize) -> Option<(usize, usize)> {
+    let open = format!("<{}", name);
+    let mut search = from;
+    loop {
+        let start = search + xml[search..].find(&open)?;
+        let after = start + open.len();
+        search = after;
+        if !xml[after..].starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace()) {
+            continue;
+        }
+        let tag_end = after + xml[after..].find('>')?;
+        if xml[..tag_end].ends_with('/') {
+            return Some((start, tag_end + 1));
+        }
+        let close = format!("</{}>", name);
+        let end = tag_end + xml[tag_end..].find(&close)? + close.len();
+        return Some((start, end));
+    }
+}
+
+/// Removes `name="..."` from every rdf:Description start tag.
+fn remove_xmp_attribute(xml: &mut String, name: &str) {
+    let mut from = 0;
+    while let Some(offset) = xml[from..].find("<rdf:Description") {
+        let start = from + offset;
+        let Some(len) = xml[start..].find('>') else { return };
+        let tag = &xml[start..start + len];
+        let attr = tag.char_indices().find_map(|(i, c)| {
+            let rest = tag[i + c.len_utf8()..].strip_prefix(name)?;
+            let value = rest.trim_start().strip_prefix('=')?.trim_start();
+            let quote = value.chars().next().filter(|q| c.is_whitespace() && (*q == '"' || *q == '\''))?;
+            let value_end = value[1..].find(quote)?;
+            Some((i, tag.len() - value.len() + value_end + 2))
+        });
+        match attr {
+            Some((attr_start, attr_end)) => xml.replace_range(start + attr_start..start + attr_end, ""),
+            None => from = start + len,
+        }
+    }
+}
+
+/// Loose BCP 47 shape check: a 2-8 letter primary subtag followed by
+/// alphanumeric subtags of up to 8 characters.
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary_ok = subtags
+        .next()
+        .is_some_and(|p| (2..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphabetic()));
+    primary_ok && subtags.all(|s| (1..=8).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataEntry {
+    /// `Info/<key>` for the Info dictionary, `XMP/<prefix:name>` for XMP.
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataChange {
+    pub key: String,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataDiff {
+    /// Present in the second document only.
+    pub added: Vec<MetadataEntry>,
+    /// Present in the first document only.
+    pub removed: Vec<MetadataEntry>,
+    pub changed: Vec<MetadataChange>,
+}
+
+/// Diffs the Info dictionary and catalog XMP fields of two documents.
+#[tauri::command]
+fn compare_metadata(path_a: String, path_b: String) -> AppResult<MetadataDiff> {
+    let a = metadata_fields(&load_pdf(&path_a)?);
+    let b = metadata_fields(&load_pdf(&path_b)?);
+
+    let mut diff = MetadataDiff { added: Vec::new(), removed: Vec::new(), changed: Vec::new() };
+    for (key, before) in &a {
+        match b.get(key) {
+            None => diff.removed.push(MetadataEntry { key: key.clone(), value: before.clone() }),
+            Some(after) if after != before => diff.changed.push(MetadataChange {
+                key: key.clone(),
+                before: before.clone(),
+                after: after.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, value) in &b {
+        if !a.contains_key(key) {
+            diff.added.push(MetadataEntry { key: key.clone(), value: value.clone() });
+        }
+    }
+    Ok(diff)
+}
+
+fn metadata_fields(doc: &Document) -> std::collections::BTreeMap<String, String> {
+    let mut fields = std::collections::BTreeMap::new();
+    if let Ok(info) = doc.trailer.get(b"Info").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
+        for (key, value) in info {
+            let value = doc.dereference(value).map(|(_, o)| decode_pdf_text(o)).unwrap_or_default();
+            fields.insert(format!("Info/{}", String::from_utf8_lossy(key)), value);
+        }
+    }
+    let xmp = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Metadata"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_stream())
+        .ok()
+        .map(|stream| stream.get_plain_content().unwrap_or_else(|_| stream.content.clone()));
+    if let Some(xmp) = xmp {
+        for (key, value) in xmp_properties(&String::from_utf8_lossy(&xmp)) {
+            fields.insert(format!("XMP/{}", key), value);
+        }
+    }
+    fields
+}
+
+/// Pulls the simple properties out of an XMP packet: attributes and child
+/// elements of each rdf:Description. Alt/Seq/Bag items are joined with "; ".
+/// This is a tag scanner rather than a full RDF parser; nested structures
+/// are flattened into their text.
+fn xmp_properties(xml: &str) -> Vec<(String, String)> {
+    let mut props = Vec::new();
+    let mut in_description = false;
+    let mut current: Option<(String, Vec<String>)> = None;
+    let mut rest = xml;
+
+    while let Some(open) = rest.find('<') {
+        let text = rest[..open].trim();
+        if let Some((_, values)) = current.as_mut() {
+            if !text.is_empty() {
+                values.push(xml_unescape(text));
+            }
+        }
+        let Some(close) = rest[open..].find('>') else { break };
+        let tag = &rest[open + 1..open + close];
+        rest = &rest[open + close + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            if name == "rdf:Description" {
+                in_description = false;
+            } else if current.as_ref().is_some_and(|(prop, _)| prop == name) {
+                let (prop, values) = current.take().unwrap_or_default();
+                props.push((prop, values.join("; ")));
+            }
+            continue;
+        }
+
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let name = tag.split_whitespace().next().unwrap_or("");
+        if name == "rdf:Description" {
+            in_description = !self_closing;
+            props.extend(xml_attributes(tag).into_iter().filter(|(key, _)| {
+                !key.starts_with("xmlns") && !key.starts_with("rdf:") && key.contains(':')
+            }));
+        } else if in_description && current.is_none() && !self_closing && name.contains(':') {
+            current = Some((name.to_string(), Vec::new()));
+        }
+    }
+    props
+}
+
+fn xml_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = tag.split_once(char::is_whitespace).map(|(_, r)| r).unwrap_or("");
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq].trim().to_string();
+        let after = rest[eq + 1..].trim_start();
+        let Some(quote) = after.chars().next().filter(|c| *c == '"' || *c == '\'') else { break };
+        let Some(end) = after[1..].find(quote) else { break };
+        attrs.push((key, xml_unescape(&after[1..1 + end])));
+        rest = &after[end + 2..];
+    }
+    attrs
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c if (c as u32) < 0x20 && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// --- Page Stamping ---
+
+/// Looks up a page attribute, following /Parent for the inheritable ones
+/// (Resources, MediaBox, CropBox, Rotate).
+fn inherited_attr<'a>(doc: &'a Document, page_id: lopdf::ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node_id = page_id;
+    // Guard against malformed trees with /Parent cycles.
+    for _ in 0..64 {
+        let node = doc.get_dictionary(node_id).ok()?;
+        if let Ok(value) = node.get(key) {
+            return Some(value);
+        }
+        node_id = node.get(b"Parent").and_then(|o| o.as_reference()).ok()?;
+    }
+    None
+}
+
+/// Gives the page its own direct /Resources dictionary (copying an inherited or
+/// shared one) so stamps can register fonts and XObjects without touching
+/// other pages, and returns it.
+fn page_resources_mut(doc: &mut Document, page_id: lopdf::ObjectId) -> AppResult<&mut lopdf::Dictionary> {
+    let resources = inherited_attr(doc, page_id, b"Resources")
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    let page = doc.get_object_mut(page_id)?.as_dict_mut()?;
+    page.set(b"Resources", Object::Dictionary(resources));
+    Ok(page.get_mut(b"Resources")?.as_dict_mut()?)
+}
+
+/// Registers `value` under a fresh name in the page's resource `category`
+/// (e.g. `Font`, `XObject`, `ExtGState`) and returns the name.
+fn add_page_resource(
+    doc: &mut Document,
+    page_id: lopdf::ObjectId,
+    category: &[u8],
+    prefix: &str,
+    value: Object,
+) -> AppResult<Vec<u8>> {
+    // The category dictionary may itself be indirect; take a direct copy.
+    let existing = {
+        let resources = page_resources_mut(doc, page_id)?.clone();
+        resources
+            .get(category)
+            .ok()
+            .and_then(|o| doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_dict().ok())
+            .cloned()
+            .unwrap_or_default()
+    };
+    let mut category_dict = existing;
+    let mut n = 1;
+    let name = loop {
+        let candidate = format!("{}{}", prefix, n).into_bytes();
+        if !category_dict.has(&candidate) {
+            break candidate;
+        }
+        n += 1;
+    };
+    category_dict.set(name.clone(), value);
+    page_resources_mut(doc, page_id)?.set(category, Object::Dictionary(category_dict));
+    Ok(name)
+}
+
+/// Adds a standard Helvetica font object to the document for stamping text.
+fn add_helvetica_font(doc: &mut Document) -> lopdf::ObjectId {
+    doc.add_object(dictionary! {
+        b"Type" => "Font",
+        b"Subtype" => "Type1",
+        b"BaseFont" => "Helvetica",
+        b"Encoding" => "WinAnsiEncoding",
+    })
+}
+
+/// Appends `content` on top of the page's existing content. The existing
+/// streams are wrapped in `q`/`Q` so any graphics state they leave behind
+/// cannot skew the stamp.
+fn append_page_content(doc: &mut Document, page_id: lopdf::ObjectId, content: Vec<u8>) -> AppResult<()> {
+    let mut contents: Vec<Object> = doc
+        .get_page_contents(page_id)
+        .into_iter()
+        .map(Object::Reference)
+        .collect();
+    let open_id = doc.add_object(lopdf::Stream::new(dictionary! {}, b"q\n".to_vec()));
+    let mut stamp = b"Q\n".to_vec();
+    stamp.extend(content);
+    let stamp_id = doc.add_object(lopdf::Stream::new(dictionary! {}, stamp));
+    contents.insert(0, Object::Reference(open_id));
+    contents.push(Object::Reference(stamp_id));
+    let page = doc.get_object_mut(page_id)?.as_dict_mut()?;
+    page.set(b"Contents", Object::Array(contents));
+    Ok(())
+}
+
+/// The characters WinAnsi puts at 0x80..=0x9F, where Latin-1 has control
+/// characters; `None` marks the unused codes.
+const WINANSI_HIGH: [Option<char>; 32] = [
+    Some('€'), None, Some('‚'), Some('ƒ'), Some('„'), Some('…'), Some('†'), Some('‡'),
+    Some('ˆ'), Some('‰'), Some('Š'), Some('‹'), Some('Œ'), None, Some('Ž'), None,
+    None, Some('‘'), Some('’'), Some('“'), Some('”'), Some('•'), Some('–'), Some('—'),
+    Some('˜'), Some('™'), Some('š'), Some('›'), Some('œ'), None, Some('ž'), Some('Ÿ'),
+];
+
+/// Encodes text for a WinAnsi literal string, escaping delimiters. Characters
+/// WinAnsi has no code for become `?`.
+fn pdf_literal_winansi(text: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for c in text.chars() {
+        let b = match c as u32 {
+            0x80..=0x9F => b'?',
+            code @ 0..=0xFF => code as u8,
+            _ => WINANSI_HIGH.iter().position(|&w| w == Some(c)).map_or(b'?', |i| 0x80 + i as u8),
+        };
+        if matches!(b, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(b);
+    }
+    out
+}
+
+/// Helvetica advance widths (1/1000 em) for ASCII 32..=126, from the standard AFM.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+fn helvetica_text_width(text: &str, font_size: f64) -> f64 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c as u32 {
+            code @ 32..=126 => HELVETICA_WIDTHS[(code - 32) as usize] as u32,
+            _ => 556,
+        })
+        .sum();
+    units as f64 * font_size / 1000.0
+}
+
+/// Returns the page's visible area as [llx, lly, urx, ury]: the CropBox if
+/// present, otherwise the MediaBox, otherwise US Letter.
+fn page_visible_rect(doc: &Document, page_id: lopdf::ObjectId) -> [f64; 4] {
+    let rect_of = |key: &[u8]| -> Option<[f64; 4]> {
+        parse_rect(doc, inherited_attr(doc, page_id, key)?).map(normalize_rect)
+    };
+    rect_of(b"CropBox")
+        .or_else(|| rect_of(b"MediaBox"))
+        .unwrap_or([0.0, 0.0, 612.0, 792.0])
+}
+
+/// Draws a single line of Helvetica text with its baseline origin at (x, y).
+fn stamp_text(
+    doc: &mut Document,
+    page_id: lopdf::ObjectId,
+    font_id: lopdf::ObjectId,
+    text: &str,
+    x: f64,
+    y: f64,
+    font_size: f64,
+) -> AppResult<()> {
+    let font_name = add_page_resource(doc, page_id, b"Font", "PPF", Object::Reference(font_id))?;
+    let mut content = format!("q BT /{} {:.2} Tf 0 g {:.2} {:.2} Td (", String::from_utf8_lossy(&font_name), font_size, x, y)
+        .into_bytes();
+    content.extend(pdf_literal_winansi(text));
+    content.extend(b") Tj ET Q\n");
+    append_page_content(doc, page_id, content)
+}
+
+/// Options for `add_text_watermark`; every field has a default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatermarkOptions {
+    /// Font size in points (default 72).
+    pub font_size: Option<f64>,
+    /// Fill colour as RGB, 0-255 per channel (default mid grey).
+    pub color: Option<[u8; 3]>,
+    /// From 0 (invisible) to 1 (opaque) (default 0.3).
+    pub opacity: Option<f64>,
+    /// Counter-clockwise angle in degrees (default 45).
+    pub rotation: Option<f64>,
+    /// Pages to stamp (default every page).
+    pub pages: Option<PageSelection>,
+}
+
+/// Resolves an optional page selection to distinct page numbers, ascending;
+/// every page when there is none.
+fn selected_pages(selection: Option<&PageSelection>, page_count: u32) -> AppResult<Vec<u32>> {
+    match selection {
+        Some(selection) => {
+            let pages: std::collections::BTreeSet<u32> = selection.page_numbers(page_count)?.into_iter().collect();
+            Ok(pages.into_iter().collect())
+        }
+        None => Ok((1..=page_count).collect()),
+    }
+}
+
+/// Stamps a line of Helvetica text across the middle of each selected page,
+/// rotated and semi-transparent, on top of the existing content.
+#[tauri::command]
+fn add_text_watermark(
+    path: String,
+    text: String,
+    output_path: String,
+    opts: Option<WatermarkOptions>,
+) -> AppResult<()> {
+    let opts = opts.unwrap_or_default();
+    let font_size = opts.font_size.unwrap_or(72.0);
+    let [r, g, b] = opts.color.unwrap_or([128, 128, 128]);
+    let opacity = opts.opacity.unwrap_or(0.3);
+    let angle = opts.rotation.unwrap_or(45.0).to_radians();
+    if text.trim().is_empty() {
+        return Err(AppError::Validation("Watermark text is empty.".to_string()));
+    }
+    if font_size <= 0.0 {
+        return Err(AppError::Validation("Font size must be positive.".to_string()));
+    }
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(AppError::Validation("Opacity must be between 0 and 1.".to_string()));
+    }
+    ensure_output_writable(&output_path)?;
+
+    let mut doc = load_pdf(&path)?;
+    let page_map = doc.get_pages();
+    let pages = selected_pages(opts.pages.as_ref(), page_map.len() as u32)?;
+    let font_id = add_helvetica_font(&mut doc);
+    let gs_id = doc.add_object(dictionary! {
+        "Type" => "ExtGState",
+        "ca" => opacity as f32,
+        "CA" => opacity as f32,
+    });
+    let width = helvetica_text_width(&text, font_size);
+    let literal = pdf_literal_winansi(&text);
+    for page in pages {
+        let page_id = page_map[&page];
+        let font_name = add_page_resource(&mut doc, page_id, b"Font", "PPF", Object::Reference(font_id))?;
+        let gs_name = add_page_resource(&mut doc, page_id, b"ExtGState", "PPGS", Object::Reference(gs_id))?;
+        let rect = page_visible_rect(&doc, page_id);
+        let (cx, cy) = ((rect[0] + rect[2]) / 2.0, (rect[1] + rect[3]) / 2.0);
+        let (sin, cos) = angle.sin_cos();
+        // Tm rotates about the page centre; Td then centres the line on it
+        // (cap height is roughly 0.7 em for Helvetica).
+        let mut content = format!(
+            "q /{} gs {:.3} {:.3} {:.3} rg BT /{} {:.2} Tf {:.4} {:.4} {:.4} {:.4} {:.2} {:.2} Tm {:.2} {:.2} Td (",
+            String::from_utf8_lossy(&gs_name),
+            r as f64 / 255.0,
+            g as f64 / 255.0,
+            b as f64 / 255.0,
+            String::from_utf8_lossy(&font_name),
+            font_size,
+            cos,
+            sin,
+            -sin,
+            cos,
+            cx,
+            cy,
+            -width / 2.0,
+            -font_size * 0.35,
+        )
+        .into_bytes();
+        content.extend(literal.iter());
+        content.extend(b") Tj ET Q\n");
+        append_page_content(&mut doc, page_id, content)?;
+    }
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Where `stamp_image` puts the image on each page.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StampPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+/// Options for `stamp_image`; every field has a default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImagePlacement {
+    #[serde(default)]
+    pub position: StampPosition,
+    /// Distance from the nearest page edges in points (default 36). Unused
+    /// when centred.
+    pub margin: Option<f64>,
+    /// Multiplier on the image's natural size, taken from the resolution a
+    /// JPEG records or 300 dpi otherwise (default 1).
+    pub scale: Option<f64>,
+    /// From 0 (invisible) to 1 (opaque) (default 1).
+    pub opacity: Option<f64>,
+    /// Pages to stamp (default every page).
+    pub pages: Option<PageSelection>,
+}
+
+/// Draws a PNG or JPEG (a logo, a "paid" stamp) on each selected page, on top
+/// of the existing content. The image is embedded once and shared by all pages.
+#[tauri::command]
+fn stamp_image(
+    path: String,
+    image_path: String,
+    output_path: String,
+    placement: Option<ImagePlacement>,
+) -> AppResult<()> {
+    let placement = placement.unwrap_or_default();
+    let margin = placement.margin.unwrap_or(36.0);
+    let scale = placement.scale.unwrap_or(1.0);
+    let opacity = placement.opacity.unwrap_or(1.0);
+    if scale <= 0.0 {
+        return Err(AppError::Validation("Scale must be positive.".to_string()));
+    }
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(AppError::Validation("Opacity must be between 0 and 1.".to_string()));
+    }
+    ensure_output_writable(&output_path)?;
+
+    let mut doc = load_pdf(&path)?;
+    let page_map = doc.get_pages();
+    let pages = selected_pages(placement.pages.as_ref(), page_map.len() as u32)?;
+    let (image_id, pixel_width, pixel_height, dpi) = add_image_file(&mut doc, &image_path)?;
+    let width = pixel_width as f64 * 72.0 / dpi * scale;
+    let height = pixel_height as f64 * 72.0 / dpi * scale;
+    let gs_id = (opacity < 1.0).then(|| {
+        doc.add_object(dictionary! {
+            "Type" => "ExtGState",
+            "ca" => opacity as f32,
+            "CA" => opacity as f32,
+        })
+    });
+    for page in pages {
+        let page_id = page_map[&page];
+        let image_name = add_page_resource(&mut doc, page_id, b"XObject", "PPIm", Object::Reference(image_id))?;
+        let gs = match gs_id {
+            Some(gs_id) => {
+                let name = add_page_resource(&mut doc, page_id, b"ExtGState", "PPGS", Object::Reference(gs_id))?;
+                format!("/{} gs ", String::from_utf8_lossy(&name))
+            }
+            None => String::new(),
+        };
+        let rect = page_visible_rect(&doc, page_id);
+        let (x, y) = match placement.position {
+            StampPosition::TopLeft => (rect[0] + margin, rect[3] - margin - height),
+            StampPosition::TopRight => (rect[2] - margin - width, rect[3] - margin - height),
+            StampPosition::BottomLeft => (rect[0] + margin, rect[1] + margin),
+            StampPosition::BottomRight => (rect[2] - margin - width, rect[1] + margin),
+            StampPosition::Center => ((rect[0] + rect[2] - width) / 2.0, (rect[1] + rect[3] - height) / 2.0),
+        };
+        let content = format!(
+            "q {}{:.2} 0 0 {:.2} {:.2} {:.2} cm /{} Do Q\n",
+            gs,
+            width,
+            height,
+            x,
+            y,
+            String::from_utf8_lossy(&image_name)
+        );
+        append_page_content(&mut doc, page_id, content.into_bytes())?;
+    }
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Maps the page as it is displayed (after /Rotate, origin at the bottom-left
+/// of the visible area) onto its user space, so anything drawn through it comes
+/// out upright. Returns the matrix with the displayed width and height.
+fn display_space(doc: &Document, page_id: lopdf::ObjectId) -> (Matrix, f64, f64) {
+    box_display_space(doc, page_id, page_visible_rect(doc, page_id))
+}
+
+/// `display_space` for a given page box rather than the visible area.
+fn box_display_space(doc: &Document, page_id: lopdf::ObjectId, rect: [f64; 4]) -> (Matrix, f64, f64) {
+    let [x0, y0, x1, y1] = rect;
+    let (width, height) = (x1 - x0, y1 - y0);
+    let rotation = inherited_attr(doc, page_id, b"Rotate")
+        .and_then(|o| o.as_i64().ok())
+        .unwrap_or(0)
+        .rem_euclid(360);
+    match rotation {
+        90 => ([0.0, 1.0, -1.0, 0.0, x1, y0], height, width),
+        180 => ([-1.0, 0.0, 0.0, -1.0, x1, y1], width, height),
+        270 => ([0.0, -1.0, 1.0, 0.0, x0, y1], height, width),
+        _ => ([1.0, 0.0, 0.0, 1.0, x0, y0], width, height),
+    }
+}
+
+const PAGE_NUMBER_MARGIN: f64 = 24.0;
+
+/// Where `add_page_numbers` draws the number, as the page is displayed.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageNumberPosition {
+    HeaderLeft,
+    HeaderCenter,
+    HeaderRight,
+    FooterLeft,
+    #[default]
+    FooterCenter,
+    FooterRight,
+}
+
+/// Options for `add_page_numbers`; every field has a default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageNumberOptions {
+    /// `{n}` is replaced by the page's number and `{total}` by the last number
+    /// used (default `Page {n} of {total}`).
+    pub format: Option<String>,
+    #[serde(default)]
+    pub position: PageNumberPosition,
+    /// Number given to the first numbered page (default 1).
+    pub start: Option<u32>,
+    /// Font size in points (default 10).
+    pub font_size: Option<f64>,
+    /// Pages left unnumbered, such as a cover. They don't use up a number.
+    pub skip: Option<PageSelection>,
+}
+
+/// Stamps page numbers in Helvetica into the header or footer of each page.
+/// Numbers are placed relative to the page as displayed, so pages with a
+/// /Rotate get them upright along their visible edge.
+#[tauri::command]
+fn add_page_numbers(path: String, output_path: String, opts: Option<PageNumberOptions>) -> AppResult<()> {
+    let opts = opts.unwrap_or_default();
+    let format = opts.format.unwrap_or_else(|| "Page {n} of {total}".to_string());
+    let start = opts.start.unwrap_or(1);
+    let font_size = opts.font_size.unwrap_or(10.0);
+    if font_size <= 0.0 {
+        return Err(AppError::Validation("Font size must be positive.".to_string()));
+    }
+    ensure_output_writable(&output_path)?;
+
+    let mut doc = load_pdf(&path)?;
+    let page_map = doc.get_pages();
+    let page_count = page_map.len() as u32;
+    let skipped: std::collections::HashSet<u32> = match &opts.skip {
+        Some(skip) => skip.page_numbers(page_count)?.into_iter().collect(),
+        None => std::collections::HashSet::new(),
+    };
+    let numbered: Vec<lopdf::ObjectId> = page_map
+        .iter()
+        .filter(|(page, _)| !skipped.contains(*page))
+        .map(|(_, &id)| id)
+        .collect();
+    if numbered.is_empty() {
+        return Err(AppError::Validation("Every page is skipped; nothing to number.".to_string()));
+    }
+    let total = start + numbered.len() as u32 - 1;
+
+    let font_id = add_helvetica_font(&mut doc);
+    for (i, page_id) in numbered.into_iter().enumerate() {
+        let label = format
+            .replace("{n}", &(start + i as u32).to_string())
+            .replace("{total}", &total.to_string());
+        let (m, width, height) = display_space(&doc, page_id);
+        let text_width = helvetica_text_width(&label, font_size);
+        let x = match opts.position {
+            PageNumberPosition::HeaderLeft | PageNumberPosition::FooterLeft => PAGE_NUMBER_MARGIN,
+            PageNumberPosition::HeaderCenter | PageNumberPosition::FooterCenter => (width - text_width) / 2.0,
+            PageNumberPosition::HeaderRight | PageNumberPosition::FooterRight => width - PAGE_NUMBER_MARGIN - text_width,
+        };
+        let y = match opts.position {
+            PageNumberPosition::HeaderLeft | PageNumberPosition::HeaderCenter | PageNumberPosition::HeaderRight => {
+                height - PAGE_NUMBER_MARGIN - font_size
+            }
+            _ => PAGE_NUMBER_MARGIN,
+        };
+        let font_name = add_page_resource(&mut doc, page_id, b"Font", "PPF", Object::Reference(font_id))?;
+        let mut content = format!(
+            "q {:.4} {:.4} {:.4} {:.4} {:.2} {:.2} cm BT /{} {:.2} Tf 0 g {:.2} {:.2} Td (",
+            m[0],
+            m[1],
+            m[2],
+            m[3],
+            m[4],
+            m[5],
+            String::from_utf8_lossy(&font_name),
+            font_size,
+            x,
+            y
+        )
+        .into_bytes();
+        content.extend(pdf_literal_winansi(&label));
+        content.extend(b") Tj ET Q\n");
+        append_page_content(&mut doc, page_id, content)?;
+    }
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+/// Draws the first page of `overlay_path` on every page of `base_path`, on
+/// top of the existing content or (`on_top` false) behind it, as for a
+/// letterhead. The overlay keeps its aspect ratio and is scaled to fit each
+/// page's MediaBox as displayed, centred, so it comes out upright on rotated
+/// pages. The base content is left as it was, so its text stays selectable.
+#[tauri::command]
+fn overlay_pdf(base_path: String, overlay_path: String, output_path: String, on_top: bool) -> AppResult<()> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&base_path)?;
+    let mut overlay = load_pdf(&overlay_path)?;
+    overlay.renumber_objects_with(doc.max_id);
+    doc.max_id = overlay.max_id;
+
+    let Some(&overlay_page) = overlay.get_pages().values().next() else {
+        return Err(AppError::Validation(format!("{} has no pages to overlay.", overlay_path)));
+    };
+    let bbox = page_visible_rect(&overlay, overlay_page);
+    let resources = inherited_attr(&overlay, overlay_page, b"Resources").cloned().unwrap_or_else(|| dictionary! {}.into());
+    let mut content = Vec::new();
+    for id in overlay.get_page_contents(overlay_page) {
+        if let Ok(stream) = overlay.get_object(id).and_then(|o| o.as_stream()) {
+            content.extend(stream.decompressed_content().unwrap_or_else(|_| stream.content.clone()));
+            content.push(b'\n');
+        }
+    }
+    let mut form = lopdf::Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => bbox.iter().map(|&v| Object::Real(v as f32)).collect::<Vec<_>>(),
+            "Resources" => resources,
+        },
+        content,
+    );
+    form.compress()?;
+    // The form's resources still point into the overlay's objects; the rest
+    // of the overlay document is pruned below.
+    doc.objects.extend(overlay.objects);
+    let form_id = doc.add_object(form);
+
+    let (overlay_width, overlay_height) = (bbox[2] - bbox[0], bbox[3] - bbox[1]);
+    for (_, page_id) in doc.get_pages() {
+        let media = inherited_attr(&doc, page_id, b"MediaBox")
+            .and_then(|o| parse_rect(&doc, o))
+            .map(normalize_rect)
+            .unwrap_or_else(|| page_visible_rect(&doc, page_id));
+        let (display, width, height) = box_display_space(&doc, page_id, media);
+        let scale = (width / overlay_width).min(height / overlay_height);
+        let placement = [
+            scale,
+            0.0,
+            0.0,
+            scale,
+            (width - overlay_width * scale) / 2.0 - bbox[0] * scale,
+            (height - overlay_height * scale) / 2.0 - bbox[1] * scale,
+        ];
+        let m = mat_mul(&placement, &display);
+        let name = add_page_resource(&mut doc, page_id, b"XObject", "PPOv", Object::Reference(form_id))?;
+        let stamp = format!(
+            "q {:.6} {:.6} {:.6} {:.6} {:.4} {:.4} cm /{} Do Q\n",
+            m[0],
+            m[1],
+            m[2],
+            m[3],
+            m[4],
+            m[5],
+            String::from_utf8_lossy(&name)
+        )
+        .into_bytes();
+        if on_top {
+            append_page_content(&mut doc, page_id, stamp)?;
+        } else {
+            let mut contents: Vec<Object> = doc.get_page_contents(page_id).into_iter().map(Object::Reference).collect();
+            contents.insert(0, doc.add_object(lopdf::Stream::new(dictionary! {}, stamp)).into());
+            doc.get_dictionary_mut(page_id)?.set(b"Contents", Object::Array(contents));
+        }
+    }
+    doc.prune_objects();
+    doc.save(resolve_path(&output_path))?;
+    Ok(())
+}
+
+// --- Bates Numbering ---
+
+/// Zero-padded width of Bates numbers, the usual litigation convention.
+const BATES_DIGITS: usize = 6;
+const BATES_FONT_SIZE: f64 = 10.0;
+const BATES_MARGIN: f64 = 24.0;
+
+/// Stamps continuous Bates numbers across a set of documents. Numbering starts
+/// at `start` on the first page of the first file and carries on from each
+/// file's last page to the next file's first. Each stamped copy is written to
+/// `output_dir` under its original file name, suffixed if that name is taken.
+///
+/// Returns the last Bates number applied.
+#[tauri::command]
+fn batch_bates_number(
+    paths: Vec<String>,
+    start: u32,
+    prefix: String,
+    output_dir: String,
+) -> AppResult<u32> {
+    if paths.is_empty() {
+        return Err(AppError::Validation("No files to number.".to_string()));
+    }
+    let out_dir = resolve_path(&output_dir);
+    if !out_dir.is_dir() {
+        return Err(AppError::Path("Output path is not a directory.".to_string()));
+    }
+    ensure_dir_writable(&out_dir)?;
+
+    let mut next = start;
+    let mut last = start;
+    let mut taken = std::collections::HashSet::new();
+    for path_str in &paths {
+        let source = resolve_path(path_str);
+        let file_name = source
+            .file_name()
+            .ok_or_else(|| AppError::Path(format!("Invalid file path: {}", path_str)))?;
+        // Never overwrite a source (or an earlier output with the same name).
+        let out_path = unique_output_path(&out_dir, file_name, &mut taken);
+
+        let mut doc = load_pdf(source)?;
+        let font_id = add_helvetica_font(&mut doc);
+        for (_page_num, page_id) in doc.get_pages() {
+            let label = format!("{}{:0width$}", prefix, next, width = BATES_DIGITS);
+            let rect = page_visible_rect(&doc, page_id);
+            let width = helvetica_text_width(&label, BATES_FONT_SIZE);
+            let x = rect[2] - BATES_MARGIN - width;
+            let y = rect[1] + BATES_MARGIN;
+            stamp_text(&mut doc, page_id, font_id, &label, x, y, BATES_FONT_SIZE)?;
+            last = next;
+            next += 1;
+        }
+        doc.save(&out_path)?;
+    }
+
+    Ok(last)
+}
+
+// --- Print Preparation ---
+
+/// Default ceiling for image resolution in print output.
+const PRINT_DPI: u32 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrintSettings {
+    /// Images above this resolution are downsampled (default 300).
+    pub max_image_dpi: Option<u32>,
+    /// JPEG quality for re-encoded photographs (default 90).
+    pub image_quality: Option<u32>,
+    /// The job prints in CMYK, so RGB images are reported (default true).
+    pub cmyk: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrintReport {
+    /// What was changed in the output.
+    pub applied: Vec<String>,
+    /// Problems left for the user or the print shop to resolve.
+    pub issues: Vec<String>,
+}
+
+/// One-step "print ready" preset: writes an unencrypted copy with images
+/// downsampled to the target resolution, then reports what still needs
+/// attention. Transparency flattening, colour conversion and font embedding
+/// are not done here; the report lists unembedded fonts, RGB images and
+/// transparency so they can be fixed upstream or left to the RIP.
+#[tauri::command]
+async fn prepare_for_print(path: String, settings: PrintSettings, output_path: String) -> AppResult<PrintReport> {
+    tauri::async_runtime::spawn_blocking(move || prepare_for_print_blocking(path, settings, output_path))
+        .await
+        .map_err(|e| AppError::Validation(format!("Print preparation task failed: {}", e)))?
+}
+
+/// The body of `prepare_for_print`.
+fn prepare_for_print_blocking(path: String, settings: PrintSettings, output_path: String) -> AppResult<PrintReport> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    if doc.is_encrypted() {
+        return Err(AppError::Validation("PDF requires a password to open; unlock it first".to_string()));
+    }
+
+    let max_dpi = settings.max_image_dpi.unwrap_or(PRINT_DPI).max(1);
+    let mut applied = Vec::new();
+    let mut issues = Vec::new();
+
+    // The reader has already decrypted the document; saving writes it in the clear.
+    if doc.was_encrypted() {
+        applied.push("Removed encryption and permission restrictions".to_string());
+    }
+
+    let compression = CompressionSettings {
+        image_quality: settings.image_quality.unwrap_or(90),
+        max_resolution_dpi: max_dpi,
+        reduce_color_complexity: false,
+        clip_invisible: false,
+        force_recompression: false,
+        remove_unused_fonts: false,
+        convert_to_cff: false,
+        merge_font_programs: false,
+        remove_annotations: false,
+        flatten_form_fields: false,
+        remove_metadata: false,
+        remove_thumbnails: false,
+        remove_application_data: false,
+        remove_structure_tree: false,
+    };
+    let stats = compress_document(&mut doc, &compression)?;
+    if stats.recompressed_images > 0 {
+        applied.push(format!(
+            "Recompressed {} image(s), downsampling any above {} dpi",
+            stats.recompressed_images, max_dpi
+        ));
+    }
+    if stats.jpeg_converted_images > 0 {
+        applied.push(format!("Re-encoded {} losslessly compressed image(s) as JPEG", stats.jpeg_converted_images));
+    }
+    // CMYK is what the press wants, so those are only left at their resolution.
+    let unsupported = stats.skipped_images - stats.skipped_cmyk_images;
+    if unsupported > 0 {
+        issues.push(format!("{} image(s) could not be resampled (unsupported format)", unsupported));
+    }
+
+    let fonts = unembedded_fonts(&doc);
+    if !fonts.is_empty() {
+        issues.push(format!("Fonts not embedded: {}", fonts.join(", ")));
+    }
+    if settings.cmyk.unwrap_or(true) {
+        let rgb = rgb_image_count(&doc);
+        if rgb > 0 {
+            issues.push(format!("{} RGB image(s) in a CMYK job", rgb));
+        }
+    }
+    if uses_transparency(&doc) {
+        issues.push("Contains transparency that has not been flattened".to_string());
+    }
+
+    doc.save(resolve_path(&output_path))?;
+    Ok(PrintReport { applied, issues })
+}
+
+/// Base names of fonts whose program is not embedded (Type 3 fonts are drawn
+/// from content streams and always count as embedded).
+fn unembedded_fonts(doc: &Document) -> Vec<String> {
+    let mut names = std::collections::BTreeSet::new();
+    for obj in doc.objects.values() {
+        let Ok(font) = obj.as_dict() else { continue };
+        if font.get(b"Type").and_then(|o| o.as_name()).ok() != Some(b"Font".as_slice()) {
+            continue;
+        }
+        let subtype = font.get(b"Subtype").and_then(|o| o.as_name()).unwrap_or_default();
+        // A composite font's program lives on its descendant, which is checked on its own.
+        if subtype == b"Type3" || subtype == b"Type0" {
+            continue;
+        }
+        let embedded = font
+            .get(b"FontDescriptor")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_dict())
+            .is_ok_and(|fd| fd.has(b"FontFile") || fd.has(b"FontFile2") || fd.has(b"FontFile3"));
+        if !embedded {
+            let name = font.get(b"BaseFont").and_then(|o| o.as_name()).unwrap_or(b"(unnamed)");
+            names.insert(String::from_utf8_lossy(name).to_string());
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Number of image XObjects in an RGB colour space.
+fn rgb_image_count(doc: &Document) -> usize {
+    doc.objects
+        .values()
+        .filter_map(|o| o.as_stream().ok())
+        .filter(|s| s.dict.get(b"Subtype").and_then(|o| o.as_name()).is_ok_and(|n| n == b"Image"))
+        .filter(|s| {
+            match s.dict.get(b"ColorSpace").and_then(|o| doc.dereference(o)).map(|(_, o)| o) {
+                Ok(Object::Name(n)) => n == b"DeviceRGB",
+                Ok(Object::Array(a)) => match a.first().and_then(|o| o.as_name().ok()) {
+                    Some(b"CalRGB") => true,
+                    Some(b"ICCBased") => a
+                        .get(1)
+                        .and_then(|o| doc.dereference(o).ok())
+                        .and_then(|(_, o)| o.as_stream().ok())
+                        .and_then(|icc| icc.dict.get(b"N").and_then(|n| n.as_i64()).ok())
+                        == Some(3),
+                    _ => false,
+                },
+                _ => false,
+            }
+        })
+        .count()
+}
+
+/// Whether anything uses transparency: soft masks, constant alpha below 1
+/// or a page transparency group.
+fn uses_transparency(doc: &Document) -> bool {
+    doc.objects.values().any(|obj| {
+        let dict = match obj {
+            Object::Dictionary(d) => d,
+            Object::Stream(s) => &s.dict,
+            _ => return false,
+        };
+        let soft_mask = dict.get(b"SMask").is_ok_and(|m| m.as_name().ok() != Some(b"None".as_slice()));
+        let alpha = [b"CA".as_slice(), b"ca"]
+            .iter()
+            .any(|key| dict.get(key).and_then(|o| o.as_float()).is_ok_and(|a| a < 1.0));
+        let group = dict
+            .get(b"Group")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_dict())
+            .and_then(|g| g.get(b"S"))
+            .and_then(|o| o.as_name())
+            .is_ok_and(|s| s == b"Transparency");
+        soft_mask || alpha || group
+    })
+}
+
+// --- OCR ---
+
+/// Resolution assumed for scans that don't record one.
+const DEFAULT_SCAN_DPI: f64 = 300.0;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OcrResult {
+    /// Words recognised on each page, in page order.
+    pub words_per_page: Vec<u32>,
+}
+
+/// Builds a PDF with one page per image, each carrying an invisible text
+/// layer from Tesseract (which must be on the PATH) so the result can be
+/// searched and copied from. `lang` is a Tesseract language such as `eng` or
+/// `deu+eng`; the text layer is WinAnsi, so only languages it can spell (see
+/// `WINANSI_LANGUAGES`) are accepted. Emits `scan-progress` with the number of
+/// images done.
+#[tauri::command]
+async fn scans_to_searchable_pdf(
+    app: tauri::AppHandle,
+    image_paths: Vec<String>,
+    lang: String,
+    output_path: String,
+) -> AppResult<OcrResult> {
+    #[cfg(feature = "ocr")]
+    {
+        tauri::async_runtime::spawn_blocking(move || scans_to_searchable_pdf_blocking(app, image_paths, lang, output_path))
+            .await
+            .map_err(|e| AppError::Validation(format!("OCR task failed: {}", e)))?
+    }
+    #[cfg(not(feature = "ocr"))]
+    {
+        let _ = (app, image_paths, lang, output_path);
+        Err(AppError::UnsupportedFeature("ocr".to_string()))
+    }
+}
+
+/// Tesseract languages whose alphabet WinAnsi covers. Text in any other would
+/// come out of `pdf_literal_winansi` as a layer of question marks.
+#[cfg(feature = "ocr")]
+const WINANSI_LANGUAGES: &[&str] = &[
+    "afr", "cat", "cos", "dan", "deu", "eng", "enm", "est", "eus", "fao", "fil", "fin", "fra", "frk",
+    "frm", "fry", "gla", "gle", "glg", "hat", "ind", "isl", "ita", "ita_old", "jav", "lat", "ltz",
+    "msa", "nld", "nor", "oci", "por", "que", "spa", "spa_old", "sqi", "sun", "swa", "swe", "tgl",
+];
+
+#[cfg(feature = "ocr")]
+fn scans_to_searchable_pdf_blocking(
+    app: tauri::AppHandle,
+    image_paths: Vec<String>,
+    lang: String,
+    output_path: String,
+) -> AppResult<OcrResult> {
+    if image_paths.is_empty() {
+        return Err(AppError::Validation("No images to convert.".to_string()));
+    }
+    if lang.is_empty() || !lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+') {
+        return Err(AppError::Validation(format!("'{}' is not a Tesseract language.", lang)));
+    }
+    if let Some(other) = lang.split('+').find(|code| !WINANSI_LANGUAGES.contains(code)) {
+        return Err(AppError::Validation(format!(
+            "'{}' isn't supported: the text layer can only hold Western European languages.",
+            other
+        )));
+    }
+    ensure_output_writable(&output_path)?;
+
+    let mut doc = Document::new();
+    doc.version = "1.7".to_string();
+    let pages_root_id = doc.new_object_id();
+    let font_id = add_helvetica_font(&mut doc);
+    let mut page_ids = Vec::new();
+    let mut words_per_page = Vec::new();
+
+    for (i, image_path) in image_paths.iter().enumerate() {
+        let (image_id, width, height, dpi) = add_image_file(&mut doc, image_path)?;
+        let words = ocr_words(image_path, &lang)?;
+        let page_width = width as f64 * 72.0 / dpi;
+        let page_height = height as f64 * 72.0 / dpi;
+
+        let mut content = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im1 Do Q\n", page_width, page_height).into_bytes();
+        content.extend(ocr_text_layer(&words, 72.0 / dpi, page_height));
+        let content_id = doc.add_object(lopdf::Stream::new(dictionary! {}, content));
+        let page_id = doc.add_object(dictionary! {
+            b"Type" => "Page",
+            b"Parent" => pages_root_id,
+            b"MediaBox" => vec![0.into(), 0.into(), Object::Real(page_width as f32), Object::Real(page_height as f32)],
+            b"Resources" => dictionary! {
+                b"XObject" => dictionary! { b"Im1" => image_id },
+                b"Font" => dictionary! { b"F1" => font_id },
+            },
+            b"Contents" => content_id,
+        });
+        page_ids.push(page_id);
+        words_per_page.push(words.len() as u32);
+        let _ = app.emit("scan-progress", (i + 1) as u32);
+    }
+
+    doc.objects.insert(
+        pages_root_id,
+        Object::Dictionary(dictionary! {
+            b"Type" => "Pages",
+            b"Count" => page_ids.len() as i64,
+            b"Kids" => page_ids.into_iter().map(Object::Reference).collect::<Vec<_>>(),
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        b"Type" => "Catalog",
+        b"Pages" => pages_root_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.compress();
+    doc.save(resolve_path(&output_path))?;
+    Ok(OcrResult { words_per_page })
+}
+
+/// Adds an image file to the document as an image XObject, returning its id
+/// with its pixel size and resolution. Gray and RGB JPEGs are embedded as-is;
+/// everything else is decoded and stored losslessly, with any transparency
+/// kept as a soft mask.
+fn add_image_file(doc: &mut Document, path: &str) -> AppResult<(lopdf::ObjectId, u32, u32, f64)> {
+    let bytes = fs::read(resolve_path(path))?;
+    if let Some(jpeg) = jpeg_info(&bytes).filter(|j| j.components == 1 || j.components == 3) {
+        let stream = lopdf::Stream::new(
+            dictionary! {
+                b"Type" => "XObject",
+                b"Subtype" => "Image",
+                b"Width" => jpeg.width as i64,
+                b"Height" => jpeg.height as i64,
+                b"ColorSpace" => if jpeg.components == 1 { "DeviceGray" } else { "DeviceRGB" },
+                b"BitsPerComponent" => 8,
+                b"Filter" => "DCTDecode",
+            },
+            bytes,
+        );
+        return Ok((doc.add_object(stream), jpeg.width, jpeg.height, jpeg.dpi.unwrap_or(DEFAULT_SCAN_DPI)));
+    }
+
+    let image = image::load_from_memory(&bytes)
+        .map_err(|e| AppError::Validation(format!("Could not read image {}: {}", path, e)))?;
+    let (width, height) = (image.width(), image.height());
+    let (pixels, colorspace) = if image.color().has_color() {
+        (image.to_rgb8().into_raw(), "DeviceRGB")
+    } else {
+        (image.to_luma8().into_raw(), "DeviceGray")
+    };
+    let mut stream = lopdf::Stream::new(
+        dictionary! {
+            b"Type" => "XObject",
+            b"Subtype" => "Image",
+            b"Width" => width as i64,
+            b"Height" => height as i64,
+            b"ColorSpace" => colorspace,
+            b"BitsPerComponent" => 8,
+        },
+        pixels,
+    );
+    if image.color().has_alpha() {
+        let alpha: Vec<u8> = image.to_rgba8().pixels().map(|p| p.0[3]).collect();
+        if alpha.iter().any(|&a| a < 255) {
+            let mut mask = lopdf::Stream::new(
+                dictionary! {
+                    b"Type" => "XObject",
+                    b"Subtype" => "Image",
+                    b"Width" => width as i64,
+                    b"Height" => height as i64,
+                    b"ColorSpace" => "DeviceGray",
+                    b"BitsPerComponent" => 8,
+                },
+                alpha,
+            );
+            mask.compress()?;
+            stream.dict.set("SMask", doc.add_object(mask));
+        }
+    }
+    stream.compress()?;
+    Ok((doc.add_object(stream), width, height, DEFAULT_SCAN_DPI))
+}
+
+struct JpegInfo {
+    width: u32,
+    height: u32,
+    components: u8,
+    /// Pixels per inch from the JFIF header, when it records one.
+    dpi: Option<f64>,
+}
+
+/// Reads the frame size, component count and JFIF density from a JPEG's
+/// markers without decoding it.
+fn jpeg_info(data: &[u8]) -> Option<JpegInfo> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut dpi = None;
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let segment = data.get(pos + 4..pos + 2 + length)?;
+        match marker {
+            0xE0 if segment.starts_with(b"JFIF\0") && segment.len() >= 12 => {
+                let x_density = u16::from_be_bytes([segment[8], segment[9]]) as f64;
+                dpi = match segment[7] {
+                    1 if x_density > 0.0 => Some(x_density),
+                    2 if x_density > 0.0 => Some(x_density * 2.54),
+                    _ => None,
+                };
+            }
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) && segment.len() >= 6 => {
+                return Some(JpegInfo {
+                    height: u16::from_be_bytes([segment[1], segment[2]]) as u32,
+                    width: u16::from_be_bytes([segment[3], segment[4]]) as u32,
+                    components: segment[5],
+                    dpi,
+                });
+            }
+            _ => {}
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+/// A recognised word and its box in image pixels (origin top-left).
+#[cfg(feature = "ocr")]
+struct OcrWord {
+    text: String,
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Runs `tesseract <image> stdout -l <lang> tsv` and collects the words.
+#[cfg(feature = "ocr")]
+fn ocr_words(image_path: &str, lang: &str) -> AppResult<Vec<OcrWord>> {
+    let output = std::process::Command::new("tesseract")
+        .arg(resolve_path(image_path))
+        .args(["stdout", "-l", lang, "tsv"])
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                AppError::Validation("Tesseract OCR is not installed or not on the PATH.".to_string())
+            }
+            _ => AppError::Io(e),
+        })?;
+    if !output.status.success() {
+        return Err(AppError::Validation(format!(
+            "Tesseract failed on {}: {}",
+            image_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(parse_tesseract_tsv(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Word rows (level 5) of Tesseract's TSV output: level, page, block, par,
+/// line, word, left, top, width, height, conf, text.
+#[cfg(feature = "ocr")]
+fn parse_tesseract_tsv(tsv: &str) -> Vec<OcrWord> {
+    tsv.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.splitn(12, '\t').collect();
+            if cols.len() < 12 || cols[0] != "5" {
+                return None;
+            }
+            let text = cols[11].trim();
+            let number = |i: usize| cols[i].trim().parse::<f64>().ok();
+            if text.is_empty() || number(10)? < 0.0 {
+                return None;
+            }
+            Some(OcrWord {
+                text: text.to_string(),
+                left: number(6)?,
+                top: number(7)?,
+                width: number(8)?,
+                height: number(9)?,
+            })
+        })
+        .collect()
+}
+
+/// Invisible (render mode 3) text for each word, sized to its box height and
+/// horizontally scaled to its box width so selections line up with the scan.
+#[cfg(feature = "ocr")]
+fn ocr_text_layer(words: &[OcrWord], scale: f64, page_height: f64) -> Vec<u8> {
+    if words.is_empty() {
+        return Vec::new();
+    }
+    let mut content = b"BT 3 Tr\n".to_vec();
+    for word in words {
+        let size = (word.height * scale).max(1.0);
+        let natural = helvetica_text_width(&word.text, size);
+        let stretch = if natural > 0.0 { (word.width * scale / natural * 100.0).clamp(10.0, 1000.0) } else { 100.0 };
+        let x = word.left * scale;
+        let y = page_height - (word.top + word.height) * scale;
+        content.extend(format!("/F1 {:.2} Tf {:.1} Tz 1 0 0 1 {:.2} {:.2} Tm (", size, stretch, x, y).into_bytes());
+        content.extend(pdf_literal_winansi(&word.text));
+        content.extend(b") Tj\n");
+    }
+    content.extend(b"ET\n");
+    content
+}
+
+// --- Text Extraction ---
+
+/// Decodes the byte strings shown with a given font into Unicode text.
+///
+/// Simple fonts use one byte per character code. Type0 (CID-keyed) fonts use
+/// multi-byte codes whose widths are declared by the codespace ranges of the
+/// font's CMap, so we split strings according to those ranges before mapping.
+struct FontDecoder {
+    /// Codespace ranges as (low, high) byte sequences of equal length.
+    codespace: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Character code -> Unicode text, from the /ToUnicode CMap.
+    to_unicode: std::collections::HashMap<u32, String>,
+    /// Type0 fonts fall back to 2-byte codes when no codespace is declared.
+    is_cid: bool,
+    /// Predefined UCS-2/UTF-16 encodings can be decoded without a ToUnicode map.
+    utf16_encoding: bool,
+    /// Byte -> text for simple fonts, from their base encoding (WinAnsi,
+    /// Standard, MacRoman). Used for codes the ToUnicode map doesn't cover.
+    simple_encoding: Option<Vec<String>>,
+}
+
+impl FontDecoder {
+    fn from_font(doc: &Document, font: &lopdf::Dictionary) -> Self {
+        let is_cid = font
+            .get(b"Subtype")
+            .and_then(|o| o.as_name())
+            .is_ok_and(|n| n == b"Type0");
+        let encoding_name = font
+            .get(b"Encoding")
+            .and_then(|o| o.as_name())
+            .map(|n| String::from_utf8_lossy(n).to_string())
+            .unwrap_or_default();
+        let utf16_encoding = is_cid && (encoding_name.contains("UCS2") || encoding_name.contains("UTF16"));
+
+        let mut decoder = FontDecoder {
+            codespace: Vec::new(),
+            to_unicode: std::collections::HashMap::new(),
+            is_cid,
+            utf16_encoding,
+            simple_encoding: if is_cid { None } else { simple_font_encoding(doc, font) },
+        };
+
+        if let Ok(stream) = font
+            .get(b"ToUnicode")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_stream())
+        {
+            let data = stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone());
+            decoder.parse_cmap(&data);
+        }
+        decoder
+    }
+
+    /// Parses the codespace ranges and bfchar/bfrange mappings of a ToUnicode CMap.
+    fn parse_cmap(&mut self, data: &[u8]) {
+        let tokens = tokenize_cmap(data);
+        let mut i = 0;
+        while i < tokens.len() {
+            match &tokens[i] {
+                CMapToken::Keyword(k) if k == "begincodespacerange" => {
+                    i += 1;
+                    while i + 1 < tokens.len() {
+                        match (&tokens[i], &tokens[i + 1]) {
+                            (CMapToken::Hex(lo), CMapToken::Hex(hi)) if lo.len() == hi.len() => {
+                                self.codespace.push((lo.clone(), hi.clone()));
+                                i += 2;
+                            }
+                            _ => break,
+                        }
+                    }
+                }
+                CMapToken::Keyword(k) if k == "beginbfchar" => {
+                    i += 1;
+                    while i + 1 < tokens.len() {
+                        match (&tokens[i], &tokens[i + 1]) {
+                            (CMapToken::Hex(src), CMapToken::Hex(dst)) => {
+                                self.to_unicode.insert(code_value(src), utf16_hex_to_string(dst));
+                                i += 2;
+                            }
+                            _ => break,
+                        }
+                    }
+                }
+                CMapToken::Keyword(k) if k == "beginbfrange" => {
+                    i += 1;
+                    while i + 2 < tokens.len() {
+                        let (lo, hi) = match (&tokens[i], &tokens[i + 1]) {
+                            (CMapToken::Hex(lo), CMapToken::Hex(hi)) => (code_value(lo), code_value(hi)),
+                            _ => break,
+                        };
+                        match &tokens[i + 2] {
+                            CMapToken::Hex(dst) => {
+                                // Consecutive codes map to consecutive values; only the last
+                                // UTF-16 unit is incremented, per the CMap spec.
+                                let mut units: Vec<u16> = dst
+                                    .chunks(2)
+                                    .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+                                    .collect();
+                                for code in lo..=hi.min(lo.saturating_add(0xFFFF)) {
+                                    self.to_unicode.insert(code, String::from_utf16_lossy(&units));
+                                    if let Some(last) = units.last_mut() {
+                                        *last = last.wrapping_add(1);
+                                    }
+                                }
+                                i += 3;
+                            }
+                            CMapToken::ArrayStart => {
+                                let mut j = i + 3;
+                                let mut code = lo;
+                                while j < tokens.len() {
+                                    match &tokens[j] {
+                                        CMapToken::Hex(dst) => {
+                                            if code <= hi {
+                                                self.to_unicode.insert(code, utf16_hex_to_string(dst));
+                                            }
+                                            code = code.saturating_add(1);
+                                            j += 1;
+                                        }
+                                        CMapToken::ArrayEnd => {
+                                            j += 1;
+                                            break;
+                                        }
+                                        _ => break,
+                                    }
+                                }
+                                i = j;
+                            }
+                            _ => break,
+                        }
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// Splits a shown string into character codes using the codespace ranges.
+    fn split_codes(&self, bytes: &[u8]) -> Vec<u32> {
+        let mut codes = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let matched = self.codespace.iter().find_map(|(lo, hi)| {
+                let n = lo.len();
+                let candidate = bytes.get(pos..pos + n)?;
+                let in_range = candidate
+                    .iter()
+                    .zip(lo.iter().zip(hi.iter()))
+                    .all(|(b, (l, h))| b >= l && b <= h);
+                if in_range { Some(n) } else { None }
+            });
+            let n = matched.unwrap_or(if self.is_cid { 2 } else { 1 });
+            let end = (pos + n).min(bytes.len());
+            codes.push(code_value(&bytes[pos..end]));
+            pos = end;
+        }
+        codes
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        if self.to_unicode.is_empty() && self.utf16_encoding {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            return String::from_utf16_lossy(&units);
+        }
+
+        let mut out = String::new();
+        for code in self.split_codes(bytes) {
+            if let Some(text) = self.to_unicode.get(&code) {
+                out.push_str(text);
+            } else if let Some(table) = &self.simple_encoding {
+                out.push_str(table.get(code as usize).map_or("", String::as_str));
+            } else if !self.is_cid {
+                // Simple font with an encoding we can't resolve: treat as Latin-1.
+                if let Some(c) = char::from_u32(code) {
+                    out.push(c);
+                }
+            }
+            // CIDs without a ToUnicode entry carry no recoverable text; drop them
+            // rather than emitting garbage.
+        }
+        out
+    }
+}
+
+/// Builds the byte -> text table for a simple font's base encoding. Type1
+/// fonts without an /Encoding use StandardEncoding; other simple fonts are
+/// assumed to be WinAnsi. A /Differences array is not applied.
+fn simple_font_encoding(doc: &Document, font: &lopdf::Dictionary) -> Option<Vec<String>> {
+    let is_type1 = font
+        .get(b"Subtype")
+        .and_then(|o| o.as_name())
+        .is_ok_and(|n| n == b"Type1");
+    let default_name: &[u8] = if is_type1 { b"StandardEncoding" } else { b"WinAnsiEncoding" };
+    let name = match font.get(b"Encoding").and_then(|o| doc.dereference(o)) {
+        Ok((_, Object::Name(name))) => name.clone(),
+        Ok((_, Object::Dictionary(dict))) => dict
+            .get(b"BaseEncoding")
+            .and_then(|o| o.as_name())
+            .unwrap_or(default_name)
+            .to_vec(),
+        _ => default_name.to_vec(),
+    };
+    let probe = dictionary! { "Type" => "Font", "Encoding" => Object::Name(name) };
+    match probe.get_font_encoding(doc).ok()? {
+        encoding @ lopdf::Encoding::OneByteEncoding(_) => Some(
+            (0..=255u8)
+                .map(|b| encoding.bytes_to_string(&[b]).unwrap_or_default())
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+enum CMapToken {
+    Hex(Vec<u8>),
+    ArrayStart,
+    ArrayEnd,
+    Keyword(String),
+}
+
+fn tokenize_cmap(data: &[u8]) -> Vec<CMapToken> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        match b {
+            b'%' => {
+                while i < data.len() && data[i] != b'\n' && data[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'<' if data.get(i + 1) == Some(&b'<') => i += 2,
+            b'>' if data.get(i + 1) == Some(&b'>') => i += 2,
+            b'<' => {
+                let start = i + 1;
+                let end = data[start..]
+                    .iter()
+                    .position(|&c| c == b'>')
+                    .map_or(data.len(), |p| start + p);
+                let digits: Vec<u8> = data[start..end]
+                    .iter()
+                    .copied()
+                    .filter(|c| c.is_ascii_hexdigit())
+                    .collect();
+                let bytes = digits
+                    .chunks(2)
+                    .map(|pair| {
+                        let s = std::str::from_utf8(pair).unwrap_or("0");
+                        // A trailing odd digit is padded with 0, as for PDF hex strings.
+                        let padded = if s.len() == 1 { format!("{}0", s) } else { s.to_string() };
+                        u8::from_str_radix(&padded, 16).unwrap_or(0)
+                    })
+                    .collect();
+                tokens.push(CMapToken::Hex(bytes));
+                i = end + 1;
+            }
+            b'[' => {
+                tokens.push(CMapToken::ArrayStart);
+                i += 1;
+            }
+            b']' => {
+                tokens.push(CMapToken::ArrayEnd);
+                i += 1;
+            }
+            b'(' => {
+                // Literal strings only appear in CMap headers; skip them.
+                let mut depth = 0;
+                while i < data.len() {
+                    match data[i] {
+                        b'\\' => i += 1,
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            _ if b.is_ascii_whitespace() => i += 1,
+            _ => {
+                let start = i;
+                while i < data.len()
+                    && !data[i].is_ascii_whitespace()
+                    && !b"<>[]()%".contains(&data[i])
+                {
+                    i += 1;
+                }
+                if i == start {
+                    i += 1;
+                    continue;
+                }
+                tokens.push(CMapToken::Keyword(String::from_utf8_lossy(&data[start..i]).to_string()));
+            }
+        }
+    }
+    tokens
+}
+
+fn code_value(bytes: &[u8]) -> u32 {
+    bytes.iter().take(4).fold(0u32, |acc, &b| (acc << 8) | b as u32)
+}
+
+fn utf16_hex_to_string(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Extracts the text shown on a single page by walking its content stream.
+fn extract_page_text(doc: &Document, page_id: lopdf::ObjectId) -> AppResult<String> {
+    use lopdf::content::Content;
+
+    let fonts = doc.get_page_fonts(page_id).unwrap_or_default();
+    let mut decoders: std::collections::HashMap<Vec<u8>, FontDecoder> = std::collections::HashMap::new();
+    let content = Content::decode(&doc.get_page_content(page_id)?)?;
+
+    let mut text = String::new();
+    let mut current_font: Option<Vec<u8>> = None;
+
+    for op in &content.operations {
+        match op.operator.as_str() {
+            "Tf" => {
+                if let Some(Ok(name)) = op.operands.first().map(|o| o.as_name()) {
+                    if !decoders.contains_key(name) {
+                        if let Some(font) = fonts.get(name) {
+                            decoders.insert(name.to_vec(), FontDecoder::from_font(doc, font));
+                        }
+                    }
+                    current_font = Some(name.to_vec());
+                }
+            }
+            "Td" | "TD" => {
+                let offset = |i: usize| op.operands.get(i).and_then(|o| o.as_float().ok()).unwrap_or(0.0);
+                if offset(1) != 0.0 {
+                    break_line(&mut text);
+                } else if offset(0) > 0.0 && !text.is_empty() && !text.ends_with([' ', '\n']) {
+                    text.push(' ');
+                }
+            }
+            "T*" => break_line(&mut text),
+            "Tj" | "'" | "\"" => {
+                if op.operator != "Tj" {
+                    break_line(&mut text);
+                }
+                if let Some(Object::String(bytes, _)) = op.operands.last() {
+                    text.push_str(&decode_shown_text(&decoders, &current_font, bytes));
+                }
+            }
+            "TJ" => {
+                if let Some(Ok(items)) = op.operands.first().map(|o| o.as_array()) {
+                    for item in items {
+                        match item {
+                            Object::String(bytes, _) => {
+                                text.push_str(&decode_shown_text(&decoders, &current_font, bytes));
+                            }
+                            // Large negative kerning adjustments are how many producers
+                            // encode word spacing.
+                            Object::Integer(_) | Object::Real(_)
+                                if item.as_float().unwrap_or(0.0) < -250.0 && !text.ends_with(' ') =>
+                            {
+                                text.push(' ');
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(text)
+}
+
+/// Starts a new line unless the text is empty or already at a line start.
+fn break_line(text: &mut String) {
+    let trimmed = text.trim_end_matches(' ').len();
+    text.truncate(trimmed);
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+fn decode_shown_text(
+    decoders: &std::collections::HashMap<Vec<u8>, FontDecoder>,
+    current_font: &Option<Vec<u8>>,
+    bytes: &[u8],
+) -> String {
+    match current_font.as_ref().and_then(|f| decoders.get(f)) {
+        Some(decoder) => decoder.decode(bytes),
+        None => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// Extracts the text of every page, one string per page.
+///
+/// Fonts with a /ToUnicode CMap (including CID-keyed Type0 fonts) are decoded
+/// through it; without one, simple fonts fall back to their base encoding
+/// (WinAnsi, Standard or MacRoman) and CIDs that cannot be mapped are skipped.
+/// Line breaks follow the text-positioning operators, so they are approximate.
+#[tauri::command]
+fn extract_pdf_text(path: String) -> AppResult<Vec<String>> {
+    extract_text(path, None)
+}
+
+/// Extracts the text of the given 1-based pages, in the order requested, or of
+/// every page when `pages` is `None`. Decoding works as for `extract_pdf_text`.
+#[tauri::command]
+fn extract_text(path: String, pages: Option<Vec<u32>>) -> AppResult<Vec<String>> {
+    let doc = load_pdf(&path)?;
+    let page_map = doc.get_pages();
+    let page_ids = match pages {
+        Some(pages) => {
+            let page_count = page_map.len() as u32;
+            let mut ids = Vec::with_capacity(pages.len());
+            for page in pages {
+                check_page_range(page, page, page_count)?;
+                ids.push(page_map[&page]);
+            }
+            ids
+        }
+        None => page_map.values().copied().collect(),
+    };
+    page_ids
+        .into_iter()
+        .map(|page_id| extract_page_text(&doc, page_id))
+        .collect()
+}
+
+/// Fingerprints what a PDF shows rather than how it is stored: a SHA-256 over
+/// each page's size, rotation, extracted text and decoded image data. Metadata,
+/// object numbering, compression filters and the trailer /ID don't affect it,
+/// so a re-exported copy of the same document hashes the same.
+#[tauri::command]
+fn pdf_content_hash(path: String) -> AppResult<String> {
+    use sha2::{Digest, Sha256};
+
+    let doc = load_pdf(&path)?;
+    let mut hasher = Sha256::new();
+    for (page_number, page_id) in doc.get_pages() {
+        let media = inherited_attr(&doc, page_id, b"MediaBox")
+            .and_then(|o| parse_rect(&doc, o))
+            .map(normalize_rect)
+            .unwrap_or_default();
+        let rotation = inherited_attr(&doc, page_id, b"Rotate")
+            .and_then(|o| o.as_i64().ok())
+            .unwrap_or(0)
+            .rem_euclid(360);
+        hasher.update(format!(
+            "page {} {:.2}x{:.2} {}\n",
+            page_number,
+            media[2] - media[0],
+            media[3] - media[1],
+            rotation
+        ));
+
+        // Text that can't be decoded still contributes the raw content stream.
+        match extract_page_text(&doc, page_id) {
+            Ok(text) => hasher.update(text.as_bytes()),
+            Err(_) => hasher.update(doc.get_page_content(page_id).unwrap_or_default()),
+        }
+        hasher.update(b"\n");
+
+        // Resource names are arbitrary, so images are hashed individually and
+        // fed in sorted order.
+        let mut image_hashes: Vec<Vec<u8>> = inherited_attr(&doc, page_id, b"Resources")
+            .and_then(|o| doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_dict().ok())
+            .and_then(|r| r.get(b"XObject").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()).ok())
+            .map(|xobjects| {
+                xobjects
+                    .iter()
+                    .filter_map(|(_, xo)| doc.dereference(xo).and_then(|(_, o)| o.as_stream()).ok())
+                    .filter(|s| s.dict.get(b"Subtype").and_then(|o| o.as_name()).is_ok_and(|n| n == b"Image"))
+                    .map(|s| {
+                        let data = s.decompressed_content().unwrap_or_else(|_| s.content.clone());
+                        Sha256::digest(&data).to_vec()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        image_hashes.sort();
+        for image_hash in image_hashes {
+            hasher.update(image_hash);
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// --- Forms ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormFieldType {
+    Text,
+    Checkbox,
+    Radio,
+    Choice,
+    Button,
+    Signature,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FormField {
+    /// Fully-qualified name: the partial names of the field and its
+    /// ancestors joined with `.`.
+    pub name: String,
+    pub field_type: FormFieldType,
+    pub value: Option<String>,
+    pub read_only: bool,
+    /// Choice entries for choice fields; on-state names for checkboxes and
+    /// radio groups.
+    pub options: Vec<String>,
+}
+
+const FIELD_FLAG_READ_ONLY: i64 = 1;
+const FIELD_FLAG_RADIO: i64 = 1 << 15;
+const FIELD_FLAG_PUSHBUTTON: i64 = 1 << 16;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FormFillResult {
+    pub filled: usize,
+    /// Names from the request that match no field in the form.
+    pub unmatched: Vec<String>,
+}
+
+/// A terminal form field with the inheritable attributes (/FT, /Ff, /V)
+/// resolved from its ancestors.
+struct TerminalField {
+    id: lopdf::ObjectId,
+    name: String,
+    field_type: Option<Vec<u8>>,
+    flags: i64,
+    value: Option<Object>,
+}
+
+/// Lists every AcroForm field with its current value, walking /AcroForm
+/// /Fields recursively. A document without a form gives an empty list.
+#[tauri::command]
+fn list_form_fields(path: String) -> AppResult<Vec<FormField>> {
+    let doc = load_pdf(&path)?;
+    let fields = collect_form_fields(&doc)
+        .into_iter()
+        .filter_map(|field| {
+            let field_type = form_field_type(&field)?;
+            let options = match field_type {
+                FormFieldType::Choice => choice_options(&doc, field.id),
+                FormFieldType::Checkbox | FormFieldType::Radio => on_states(&doc, field.id),
+                _ => Vec::new(),
+            };
+            let value = match &field.value {
+                Some(Object::Array(items)) => Some(
+                    items
+                        .iter()
+                        .filter_map(|item| doc.dereference(item).ok())
+                        .map(|(_, item)| decode_pdf_text(item))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                ),
+                Some(obj) => Some(decode_pdf_text(obj)).filter(|v| !v.is_empty()),
+                None => None,
+            };
+            Some(FormField {
+                name: field.name,
+                field_type,
+                value,
+                read_only: field.flags & FIELD_FLAG_READ_ONLY != 0,
+                options,
+            })
+        })
+        .collect();
+    Ok(fields)
+}
+
+/// Reads every AcroForm field with its value; an alias of `list_form_fields`.
+#[tauri::command]
+fn read_form_fields(path: String) -> AppResult<Vec<FormField>> {
+    list_form_fields(path)
+}
+
+/// The top-level fields listed in /AcroForm /Fields.
+fn form_field_roots(doc: &Document) -> Vec<lopdf::ObjectId> {
+    doc.catalog()
+        .and_then(|catalog| catalog.get(b"AcroForm"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .and_then(|form| form.get(b"Fields"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_array())
+        .map(|fields| fields.iter().filter_map(|f| f.as_reference().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Walks /AcroForm /Fields and returns the terminal fields in document order.
+/// Kids without a /T are widget annotations of their parent, not fields.
+fn collect_form_fields(doc: &Document) -> Vec<TerminalField> {
+    let mut out = Vec::new();
+    let mut visited = std::collections::HashSet::new();
+    let inherited = TerminalField { id: (0, 0), name: String::new(), field_type: None, flags: 0, value: None };
+    for id in form_field_roots(doc) {
+        walk_form_field(doc, id, &inherited, &mut visited, &mut out);
+    }
+    out
+}
+
+/// Lists another document's top-level fields (already copied into `doc`) in
+/// `doc`'s AcroForm. A top-level name already in `used` gets a `_2`, `_3`, ...
+/// suffix; values and appearances are untouched. `names` are the source's
+/// terminal field names, used to report (original, new) pairs for renames.
+fn adopt_form_fields(
+    doc: &mut Document,
+    roots: &[lopdf::ObjectId],
+    names: &[String],
+    used: &mut std::collections::HashSet<String>,
+) -> AppResult<Vec<(String, String)>> {
+    let mut renamed = Vec::new();
+    for &root in roots {
+        let Ok(field) = doc.get_dictionary_mut(root) else { continue };
+        let name = field.get(b"T").map(decode_pdf_text).unwrap_or_default();
+        let mut unique = name.clone();
+        let mut n = 2;
+        while !used.insert(unique.clone()) {
+            unique = format!("{}_{}", name, n);
+            n += 1;
+        }
+        if unique == name {
+            continue;
+        }
+        field.set("T", lopdf::text_string(&unique));
+        let prefix = format!("{}.", name);
+        for original in names {
+            if *original == name || original.starts_with(&prefix) {
+                renamed.push((original.clone(), format!("{}{}", unique, &original[name.len()..])));
+            }
+        }
+    }
+
+    let form_id = match doc.catalog()?.get(b"AcroForm") {
+        Ok(Object::Reference(id)) => Some(*id),
+        Ok(_) => None,
+        Err(_) => {
+            doc.catalog_mut()?.set("AcroForm", dictionary! { "Fields" => Vec::<Object>::new() });
+            None
+        }
+    };
+    let form = match form_id {
+        Some(id) => doc.get_dictionary_mut(id)?,
+        None => doc.catalog_mut()?.get_mut(b"AcroForm")?.as_dict_mut()?,
+    };
+    let fields_id = match form.get(b"Fields") {
+        Ok(Object::Reference(id)) => Some(*id),
+        Ok(Object::Array(_)) => None,
+        _ => {
+            form.set("Fields", Vec::<Object>::new());
+            None
+        }
+    };
+    let fields = match fields_id {
+        Some(id) => doc.get_object_mut(id)?.as_array_mut()?,
+        None => form.get_mut(b"Fields")?.as_array_mut()?,
+    };
+    fields.extend(roots.iter().map(|&id| Object::Reference(id)));
+    Ok(renamed)
+}
+
+fn walk_form_field(
+    doc: &Document,
+    id: lopdf::ObjectId,
+    parent: &TerminalField,
+    visited: &mut std::collections::HashSet<lopdf::ObjectId>,
+    out: &mut Vec<TerminalField>,
+) {
+    if !visited.insert(id) {
+        return;
+    }
+    let Ok(dict) = doc.get_dictionary(id) else { return };
+
+    let partial = dict.get(b"T").map(decode_pdf_text).unwrap_or_default();
+    let name = match (parent.name.is_empty(), partial.is_empty()) {
+        (true, _) => partial,
+        (false, true) => parent.name.clone(),
+        (false, false) => format!("{}.{}", parent.name, partial),
+    };
+    let field = TerminalField {
+        id,
+        name,
+        field_type: dict
+            .get(b"FT")
+            .and_then(Object::as_name)
+            .map(|n| n.to_vec())
+            .ok()
+            .or_else(|| parent.field_type.clone()),
+        flags: dict
+            .get(b"Ff")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_i64())
+            .unwrap_or(parent.flags),
+        value: dict
+            .get(b"V")
+            .and_then(|o| doc.dereference(o))
+            .map(|(_, o)| o.clone())
+            .ok()
+            .or_else(|| parent.value.clone()),
+    };
+
+    let field_kids: Vec<lopdf::ObjectId> = dict
+        .get(b"Kids")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_array())
+        .map(|kids| {
+            kids.iter()
+                .filter_map(|kid| kid.as_reference().ok())
+                .filter(|&kid| doc.get_dictionary(kid).is_ok_and(|d| d.has(b"T")))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if field_kids.is_empty() {
+        out.push(field);
+    } else {
+        for kid in field_kids {
+            walk_form_field(doc, kid, &field, visited, out);
+        }
+    }
+}
+
+/// Sets field values by fully-qualified name. Text and choice widgets get a
+/// plain appearance showing the new value, and the form is flagged
+/// NeedAppearances so viewers that can will redraw it in the field's own
+/// style; checkboxes and radio groups switch /AS between their existing on and
+/// Off appearances. Checkboxes accept an on-state name, `Off`, or true/false
+/// style values.
+#[tauri::command]
+fn fill_form_fields(
+    path: String,
+    values: std::collections::HashMap<String, String>,
+    output_path: String,
+) -> AppResult<FormFillResult> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let fields = collect_form_fields(&doc);
+
+    let mut filled = 0;
+    let mut font_id = None;
+    for field in &fields {
+        let Some(value) = values.get(&field.name) else { continue };
+        let Some(field_type) = form_field_type(field) else { continue };
+        if field.flags & FIELD_FLAG_READ_ONLY != 0 {
+            return Err(AppError::Validation(format!("Field '{}' is read-only.", field.name)));
+        }
+        match field_type {
+            FormFieldType::Text | FormFieldType::Choice => {
+                doc.get_dictionary_mut(field.id)?.set("V", lopdf::text_string(value));
+                let updated = TerminalField {
+                    id: field.id,
+                    name: field.name.clone(),
+                    field_type: field.field_type.clone(),
+                    flags: field.flags,
+                    value: Some(lopdf::text_string(value)),
+                };
+                for widget in field_widgets(&doc, field.id) {
+                    let appearance = generate_field_appearance(&mut doc, widget, &updated, &mut font_id);
+                    let dict = doc.get_dictionary_mut(widget)?;
+                    match appearance {
+                        Some(id) => dict.set("AP", dictionary! { "N" => id }),
+                        None => {
+                            dict.remove(b"AP");
+                        }
+                    }
+                }
+            }
+            FormFieldType::Checkbox | FormFieldType::Radio => {
+                let states = on_states(&doc, field.id);
+                let state = button_state(field_type, value, &states).ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "'{}' is not a state of field '{}' (expected one of: {}, Off).",
+                        value,
+                        field.name,
+                        states.join(", ")
+                    ))
+                })?;
+                doc.get_dictionary_mut(field.id)?.set("V", Object::Name(state.clone().into_bytes()));
+                for widget in field_widgets(&doc, field.id) {
+                    let has_state = widget_has_state(&doc, widget, &state);
+                    let dict = doc.get_dictionary_mut(widget)?;
+                    dict.set("AS", Object::Name(if has_state { state.as_bytes().to_vec() } else { b"Off".to_vec() }));
+                }
+            }
+            FormFieldType::Button | FormFieldType::Signature => {
+                return Err(AppError::Validation(format!("Field '{}' cannot be filled.", field.name)));
+            }
+        }
+        filled += 1;
+    }
+
+    if filled > 0 {
+        let form = match doc.catalog()?.get(b"AcroForm").and_then(|o| o.as_reference()) {
+            Ok(id) => doc.get_dictionary_mut(id).ok(),
+            Err(_) => doc.catalog_mut()?.get_mut(b"AcroForm").and_then(|o| o.as_dict_mut()).ok(),
+        };
+        if let Some(form) = form {
+            form.set("NeedAppearances", true);
+        }
+    }
+
+    let known: std::collections::HashSet<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+    let mut unmatched: Vec<String> = values.keys().filter(|name| !known.contains(name.as_str())).cloned().collect();
+    unmatched.sort();
+
+    doc.save(resolve_path(&output_path))?;
+    Ok(FormFillResult { filled, unmatched })
+}
+
+/// Maps a requested value onto one of a button field's appearance states.
+fn button_state(field_type: FormFieldType, value: &str, states: &[String]) -> Option<String> {
+    if let Some(state) = states.iter().find(|s| s.as_str() == value) {
+        return Some(state.clone());
+    }
+    let lower = value.trim().to_ascii_lowercase();
+    if matches!(lower.as_str(), "off" | "" | "false" | "no" | "0") {
+        return Some("Off".to_string());
+    }
+    if field_type == FormFieldType::Checkbox && matches!(lower.as_str(), "on" | "true" | "yes" | "1") {
+        return states.first().cloned();
+    }
+    None
+}
+
+fn widget_has_state(doc: &Document, widget: lopdf::ObjectId, state: &str) -> bool {
+    doc.get_dictionary(widget)
+        .and_then(|w| w.get(b"AP"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .and_then(|ap| ap.get(b"N"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .is_ok_and(|normal| normal.has(state.as_bytes()))
+}
+
+fn form_field_type(field: &TerminalField) -> Option<FormFieldType> {
+    Some(match field.field_type.as_deref()? {
+        b"Tx" => FormFieldType::Text,
+        b"Ch" => FormFieldType::Choice,
+        b"Sig" => FormFieldType::Signature,
+        b"Btn" if field.flags & FIELD_FLAG_PUSHBUTTON != 0 => FormFieldType::Button,
+        b"Btn" if field.flags & FIELD_FLAG_RADIO != 0 => FormFieldType::Radio,
+        b"Btn" => FormFieldType::Checkbox,
+        _ => return None,
+    })
+}
+
+/// The display strings of a choice field's /Opt entries, which are either
+/// plain strings or `[export display]` pairs.
+fn choice_options(doc: &Document, id: lopdf::ObjectId) -> Vec<String> {
+    let Ok(dict) = doc.get_dictionary(id) else { return Vec::new() };
+    let Ok(opts) = dict.get(b"Opt").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_array()) else {
+        return Vec::new();
+    };
+    opts.iter()
+        .filter_map(|opt| doc.dereference(opt).ok())
+        .map(|(_, opt)| match opt {
+            Object::Array(pair) => pair
+                .last()
+                .and_then(|o| doc.dereference(o).ok())
+                .map(|(_, o)| decode_pdf_text(o))
+                .unwrap_or_default(),
+            other => decode_pdf_text(other),
+        })
+        .collect()
+}
+
+/// The widget ids of a terminal field: the field itself when it doubles as
+/// its widget, otherwise its /Kids.
+fn field_widgets(doc: &Document, id: lopdf::ObjectId) -> Vec<lopdf::ObjectId> {
+    let kids = doc
+        .get_dictionary(id)
+        .and_then(|dict| dict.get(b"Kids"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_array())
+        .map(|kids| kids.iter().filter_map(|kid| kid.as_reference().ok()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    if kids.is_empty() { vec![id] } else { kids }
+}
+
+/// The non-Off appearance state names across a button field's widgets.
+fn on_states(doc: &Document, id: lopdf::ObjectId) -> Vec<String> {
+    let mut states = Vec::new();
+    for widget in field_widgets(doc, id) {
+        let normal = doc
+            .get_dictionary(widget)
+            .and_then(|w| w.get(b"AP"))
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_dict())
+            .and_then(|ap| ap.get(b"N"))
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_dict())
+            .ok();
+        for (key, _) in normal.into_iter().flat_map(|n| n.iter()) {
+            let state = String::from_utf8_lossy(key).to_string();
+            if state != "Off" && !states.contains(&state) {
+                states.push(state);
+            }
+        }
+    }
+    states
+}
+
+const FIELD_FLAG_MULTILINE: i64 = 1 << 12;
+const FIELD_FLAG_PASSWORD: i64 = 1 << 13;
+const ANNOT_FLAG_HIDDEN: i64 = 1 << 1;
+
+/// Flattens the form: each field's current appearance becomes part of the page
+/// and the fields themselves are removed, so the values can no longer be
+/// edited. Returns how many widgets were drawn.
+#[tauri::command]
+fn flatten_forms(path: String, output_path: String) -> AppResult<u32> {
+    ensure_output_writable(&output_path)?;
+    let mut doc = load_pdf(&path)?;
+    let drawn = flatten_form_fields(&mut doc)?;
+    doc.save(resolve_path(&output_path))?;
+    Ok(drawn)
+}
+
+/// Draws the normal appearance (/AP /N, in its /AS state) of every field
+/// widget into its page's content, then removes the widgets and the catalog
+/// /AcroForm. Text and choice widgets without an appearance get a plain
+/// Helvetica one from their value; other widgets without one, and hidden
+/// widgets, are removed undrawn.
+fn flatten_form_fields(doc: &mut Document) -> AppResult<u32> {
+    let fields = collect_form_fields(doc);
+    let mut widget_fields = std::collections::HashMap::new();
+    for (i, field) in fields.iter().enumerate() {
+        for widget in field_widgets(doc, field.id) {
+            widget_fields.insert(widget, i);
+        }
+    }
+
+    let mut font_id = None;
+    let mut drawn = 0;
+    for (_, page_id) in doc.get_pages() {
+        let annots: Vec<Object> = doc
+            .get_dictionary(page_id)?
+            .get(b"Annots")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_array())
+            .cloned()
+            .unwrap_or_default();
+        if annots.is_empty() {
+            continue;
+        }
+        let mut kept = Vec::new();
+        let mut content = String::new();
+        for annot in annots {
+            let Some((widget_id, &field)) = annot
+                .as_reference()
+                .ok()
+                .and_then(|id| Some(id).zip(widget_fields.get(&id)))
+            else {
+                kept.push(annot);
+                continue;
+            };
+            let hidden = doc
+                .get_dictionary(widget_id)
+                .and_then(|w| w.get(b"F"))
+                .and_then(|o| o.as_i64())
+                .is_ok_and(|flags| flags & ANNOT_FLAG_HIDDEN != 0);
+            if hidden {
+                continue;
+            }
+            let Some(appearance) = widget_appearance(doc, widget_id)
+                .or_else(|| generate_field_appearance(doc, widget_id, &fields[field], &mut font_id))
+            else {
+                continue;
+            };
+            let Some(m) = appearance_placement(doc, widget_id, appearance) else { continue };
+            if let Ok(Object::Stream(stream)) = doc.get_object_mut(appearance) {
+                // Appearance streams often omit these, but a Do needs them.
+                stream.dict.set("Type", "XObject");
+                stream.dict.set("Subtype", "Form");
+            }
+            let name = add_page_resource(doc, page_id, b"XObject", "PPFm", Object::Reference(appearance))?;
+            content.push_str(&format!(
+                "q {:.6} {:.6} {:.6} {:.6} {:.4} {:.4} cm /{} Do Q\n",
+                m[0],
+                m[1],
+                m[2],
+                m[3],
+                m[4],
+                m[5],
+                String::from_utf8_lossy(&name)
+            ));
+            drawn += 1;
+        }
+        if !content.is_empty() {
+            append_page_content(doc, page_id, content.into_bytes())?;
+        }
+        let page = doc.get_dictionary_mut(page_id)?;
+        if kept.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set(b"Annots", Object::Array(kept));
+        }
+    }
+    doc.catalog_mut()?.remove(b"AcroForm");
+    Ok(drawn)
+}
+
+/// The widget's normal appearance stream: /AP /N itself, or for widgets with
+/// several states (checkboxes, radio buttons) the one named by /AS.
+fn widget_appearance(doc: &Document, widget_id: lopdf::ObjectId) -> Option<lopdf::ObjectId> {
+    let widget = doc.get_dictionary(widget_id).ok()?;
+    let normal = widget
+        .get(b"AP")
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .and_then(|ap| ap.get(b"N"))
+        .ok()?;
+    let id = match doc.dereference(normal).ok()? {
+        (Some(id), Object::Stream(_)) => id,
+        (_, Object::Dictionary(states)) => {
+            let state = widget.get(b"AS").and_then(|o| o.as_name()).ok()?;
+            states.get(state).and_then(|o| o.as_reference()).ok()?
+        }
+        _ => return None,
+    };
+    doc.get_object(id).ok()?.as_stream().ok().map(|_| id)
+}
+
+/// The matrix that maps an appearance's bounding box (after its own /Matrix)
+/// onto the widget's /Rect, as a viewer places it.
+fn appearance_placement(doc: &Document, widget_id: lopdf::ObjectId, appearance: lopdf::ObjectId) -> Option<Matrix> {
+    let rect = normalize_rect(parse_rect(doc, doc.get_dictionary(widget_id).ok()?.get(b"Rect").ok()?)?);
+    let stream = doc.get_object(appearance).ok()?.as_stream().ok()?;
+    let bbox = normalize_rect(parse_rect(doc, stream.dict.get(b"BBox").ok()?)?);
+    let m: Vec<f64> = stream
+        .dict
+        .get(b"Matrix")
+        .and_then(|o| o.as_array())
+        .map(|a| a.iter().map(|o| o.as_float().unwrap_or(0.0) as f64).collect())
+        .unwrap_or_default();
+    let m = if m.len() == 6 { [m[0], m[1], m[2], m[3], m[4], m[5]] } else { IDENTITY };
+    let corners = [(bbox[0], bbox[1]), (bbox[2], bbox[1]), (bbox[0], bbox[3]), (bbox[2], bbox[3])]
+        .map(|(x, y)| (x * m[0] + y * m[2] + m[4], x * m[1] + y * m[3] + m[5]));
+    let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+    let max_x = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max);
+    let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min);
+    let max_y = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max);
+    if max_x - min_x <= 0.0 || max_y - min_y <= 0.0 {
+        return None;
+    }
+    let sx = (rect[2] - rect[0]) / (max_x - min_x);
+    let sy = (rect[3] - rect[1]) / (max_y - min_y);
+    Some([sx, 0.0, 0.0, sy, rect[0] - min_x * sx, rect[1] - min_y * sy])
+}
+
+/// Builds a plain appearance for a text or choice widget showing the field's
+/// value in Helvetica, at the /DA font size (auto-sized when that is 0) and
+/// aligned per /Q. Returns None for other fields and empty values.
+fn generate_field_appearance(
+    doc: &mut Document,
+    widget_id: lopdf::ObjectId,
+    field: &TerminalField,
+    font_id: &mut Option<lopdf::ObjectId>,
+) -> Option<lopdf::ObjectId> {
+    if !matches!(form_field_type(field)?, FormFieldType::Text | FormFieldType::Choice) {
+        return None;
+    }
+    let value = match field.value.as_ref()? {
+        Object::Array(items) => items.iter().map(decode_pdf_text).collect::<Vec<_>>().join(", "),
+        other => decode_pdf_text(other),
+    };
+    if value.is_empty() {
+        return None;
+    }
+    let value = if field.flags & FIELD_FLAG_PASSWORD != 0 { "*".repeat(value.chars().count()) } else { value };
+
+    // /DA and /Q are inheritable: widget, then field, then the AcroForm.
+    let form = doc
+        .catalog()
+        .and_then(|c| c.get(b"AcroForm"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+        .ok();
+    let lookup = |key: &[u8]| -> Option<&Object> {
+        [widget_id, field.id]
+            .iter()
+            .filter_map(|&id| doc.get_dictionary(id).ok())
+            .chain(form)
+            .find_map(|dict| dict.get(key).ok())
+    };
+    let rect = normalize_rect(parse_rect(doc, doc.get_dictionary(widget_id).ok()?.get(b"Rect").ok()?)?);
+    let (width, height) = (rect[2] - rect[0], rect[3] - rect[1]);
+    let da_size = lookup(b"DA")
+        .and_then(|o| o.as_str().ok())
+        .and_then(|da| {
+            let da = String::from_utf8_lossy(da).to_string();
+            let tokens: Vec<&str> = da.split_whitespace().collect();
+            let tf = tokens.iter().position(|t| *t == "Tf")?;
+            tokens.get(tf.checked_sub(1)?)?.parse::<f64>().ok()
+        })
+        .unwrap_or(0.0);
+    let quadding = lookup(b"Q").and_then(|o| o.as_i64().ok()).unwrap_or(0);
+    let multiline = field.flags & FIELD_FLAG_MULTILINE != 0;
+    let font_size = if da_size > 0.0 {
+        da_size
+    } else if multiline {
+        12.0
+    } else {
+        ((height - 4.0) / 1.15).clamp(4.0, 12.0)
+    };
+
+    let lines: Vec<String> = if multiline {
+        value.lines().map(str::to_string).collect()
+    } else {
+        vec![value.replace(['\r', '\n'], " ")]
+    };
+    let mut content = format!(
+        "/Tx BMC q 1 1 {:.2} {:.2} re W n BT /Helv {:.2} Tf 0 g\n",
+        width - 2.0,
+        height - 2.0,
+        font_size
+    )
+    .into_bytes();
+    for (i, line) in lines.iter().enumerate() {
+        let text_width = helvetica_text_width(line, font_size);
+        let x = match quadding {
+            1 => (width - text_width) / 2.0,
+            2 => width - 2.0 - text_width,
+            _ => 2.0,
+        };
+        let y = if multiline {
+            height - 2.0 - font_size * (0.9 + 1.15 * i as f64)
+        } else {
+            (height - font_size * 0.7) / 2.0
+        };
+        content.extend(format!("1 0 0 1 {:.2} {:.2} Tm (", x, y).into_bytes());
+        content.extend(pdf_literal_winansi(line));
+        content.extend(b") Tj\n");
+    }
+    content.extend(b"ET Q EMC\n");
+
+    let font = *font_id.get_or_insert_with(|| add_helvetica_font(doc));
+    let stream = lopdf::Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => vec![0.into(), 0.into(), Object::Real(width as f32), Object::Real(height as f32)],
+            "Resources" => dictionary! { "Font" => dictionary! { "Helv" => font } },
+        },
+        content,
+    );
+    Some(doc.add_object(stream))
+}
+
+// --- Rendering ---
+
+/// Renders one page's vector content (paths, text and JPEG images) to SVG.
+///
+/// This is a first-pass converter: shadings, patterns, clipping and non-JPEG
+/// images are not reproduced (images become grey placeholders).
+#[tauri::command]
+fn render_page_svg(path: String, page_number: u32) -> AppResult<String> {
+    #[cfg(feature = "render")]
+    {
+        let doc = load_pdf(&path)?;
+        let pages = doc.get_pages();
+        let &page_id = pages.get(&page_number).ok_or_else(|| {
+            AppError::Validation(format!("Page {} is out of range (document has {} pages).", page_number, pages.len()))
+        })?;
+        render_svg(&doc, page_id)
+    }
+    #[cfg(not(feature = "render"))]
+    {
+        let _ = (path, page_number);
+        Err(AppError::UnsupportedFeature("render".to_string()))
+    }
+}
+
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Concatenates two transformation matrices: applies `a`, then `b`.
+fn mat_mul(a: &Matrix, b: &Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+#[cfg(feature = "render")]
+fn mat_apply(m: &Matrix, x: f64, y: f64) -> (f64, f64) {
+    (x * m[0] + y * m[2] + m[4], x * m[1] + y * m[3] + m[5])
+}
+
+/// Converts PDF color operands (gray, RGB or CMYK) to an SVG color.
+#[cfg(feature = "render")]
+fn svg_color(operands: &[Object]) -> Option<String> {
+    let vals: Vec<f64> = operands.iter().filter_map(|o| o.as_float().ok().map(|f| f as f64)).collect();
+    if vals.len() != operands.len() {
+        // Pattern or separation names: leave the current color alone.
+        return None;
+    }
+    let (r, g, b) = match vals.as_slice() {
+        [gray] => (*gray, *gray, *gray),
+        [r, g, b] => (*r, *g, *b),
+        [c, m, y, k] => ((1.0 - c) * (1.0 - k), (1.0 - m) * (1.0 - k), (1.0 - y) * (1.0 - k)),
+        _ => return None,
+    };
+    let to_byte = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Some(format!("#{:02x}{:02x}{:02x}", to_byte(r), to_byte(g), to_byte(b)))
+}
+
+/// A font as needed for SVG output: text decoding plus glyph advances.
+#[cfg(feature = "render")]
+struct SvgFont {
+    decoder: FontDecoder,
+    first_char: u32,
+    widths: Vec<f64>,
+    cid_widths: std::collections::HashMap<u32, f64>,
+    default_width: f64,
+    family: &'static str,
+    bold: bool,
+    italic: bool,
+}
+
+#[cfg(feature = "render")]
+impl SvgFont {
+    fn from_font(doc: &Document, font: &lopdf::Dictionary) -> Self {
+        let deref_array = |key: &[u8], dict: &lopdf::Dictionary| -> Vec<Object> {
+            dict.get(key)
+                .and_then(|o| doc.dereference(o))
+                .and_then(|(_, o)| o.as_array())
+                .cloned()
+                .unwrap_or_default()
+        };
+        let base_font = font
+            .get(b"BaseFont")
+            .and_then(|o| o.as_name())
+            .map(|n| String::from_utf8_lossy(n).to_string())
+            .unwrap_or_default();
+        let lower = base_font.to_lowercase();
+        let family = if lower.contains("times") || (lower.contains("serif") && !lower.contains("sans")) {
+            "serif"
+        } else if lower.contains("courier") || lower.contains("mono") {
+            "monospace"
+        } else {
+            "sans-serif"
+        };
+
+        let mut svg_font = SvgFont {
+            decoder: FontDecoder::from_font(doc, font),
+            first_char: font.get(b"FirstChar").and_then(|o| o.as_i64()).unwrap_or(0).max(0) as u32,
+            widths: deref_array(b"Widths", font)
+                .iter()
+                .map(|o| o.as_float().unwrap_or(0.0) as f64)
+                .collect(),
+            cid_widths: std::collections::HashMap::new(),
+            default_width: 500.0,
+            family,
+            bold: lower.contains("bold") || lower.contains("black"),
+            italic: lower.contains("italic") || lower.contains("oblique"),
+        };
+
+        // CID fonts keep their widths on the descendant font: /DW and /W.
+        if let Some(descendant) = deref_array(b"DescendantFonts", font)
+            .first()
+            .and_then(|o| doc.dereference(o).ok())
+            .and_then(|(_, o)| o.as_dict().ok())
+        {
+            svg_font.default_width = descendant.get(b"DW").and_then(|o| o.as_float()).unwrap_or(1000.0) as f64;
+            let w = deref_array(b"W", descendant);
+            let mut i = 0;
+            while i < w.len() {
+                let first = w[i].as_i64().unwrap_or(0).max(0) as u32;
+                match w.get(i + 1) {
+                    Some(Object::Array(list)) => {
+                        for (offset, width) in list.iter().enumerate() {
+                            svg_font.cid_widths.insert(first + offset as u32, width.as_float().unwrap_or(0.0) as f64);
+                        }
+                        i += 2;
+                    }
+                    Some(last) => {
+                        let last = last.as_i64().unwrap_or(0).max(0) as u32;
+                        let width = w.get(i + 2).and_then(|o| o.as_float().ok()).unwrap_or(0.0) as f64;
+                        for cid in first..=last.min(first.saturating_add(0xFFFF)) {
+                            svg_font.cid_widths.insert(cid, width);
+                        }
+                        i += 3;
+                    }
+                    None => break,
+                }
+            }
+        }
+        svg_font
+    }
+
+    /// Advance width of a character code in text space units (1/1000 em).
+    fn advance(&self, code: u32) -> f64 {
+        if self.decoder.is_cid {
+            return *self.cid_widths.get(&code).unwrap_or(&self.default_width);
+        }
+        code.checked_sub(self.first_char)
+            .and_then(|i| self.widths.get(i as usize))
+            .copied()
+            .filter(|w| *w > 0.0)
+            .unwrap_or(self.default_width)
+    }
+}
+
+#[cfg(feature = "render")]
+#[derive(Clone)]
+struct SvgState {
+    ctm: Matrix,
+    fill: String,
+    stroke: String,
+    line_width: f64,
+    font_size: f64,
+    leading: f64,
+    char_spacing: f64,
+    word_spacing: f64,
+    horizontal_scale: f64,
+    render_mode: i64,
+}
+
+#[cfg(feature = "render")]
+struct SvgRenderer<'a> {
+    doc: &'a Document,
+    out: String,
+    fonts: std::collections::HashMap<lopdf::ObjectId, SvgFont>,
+}
+
+#[cfg(feature = "render")]
+impl<'a> SvgRenderer<'a> {
+    fn font_for(&mut self, resources: &lopdf::Dictionary, name: &[u8]) -> Option<lopdf::ObjectId> {
+        let font_ref = resources
+            .get(b"Font")
+            .and_then(|o| self.doc.dereference(o))
+            .and_then(|(_, o)| o.as_dict())
+            .and_then(|d| d.get(name))
+            .and_then(|o| o.as_reference())
+            .ok()?;
+        if !self.fonts.contains_key(&font_ref) {
+            let font = self.doc.get_dictionary(font_ref).ok()?;
+            self.fonts.insert(font_ref, SvgFont::from_font(self.doc, font));
+        }
+        Some(font_ref)
+    }
+
+    fn render_content(&mut self, content: &[u8], resources: &lopdf::Dictionary, base: SvgState, depth: u32) {
+        use lopdf::content::Content;
+
+        let operations = match Content::decode(content) {
+            Ok(c) => c.operations,
+            Err(_) => return,
+        };
+        let mut state = base;
+        let mut stack: Vec<SvgState> = Vec::new();
+        let mut path = String::new();
+        let mut current = (0.0, 0.0);
+        let mut tm = IDENTITY;
+        let mut tlm = IDENTITY;
+        let mut current_font: Option<lopdf::ObjectId> = None;
+
+        let nums = |ops: &[Object]| -> Vec<f64> { ops.iter().map(|o| o.as_float().unwrap_or(0.0) as f64).collect() };
+
+        for op in &operations {
+            let n = nums(&op.operands);
+            match op.operator.as_str() {
+                "q" => stack.push(state.clone()),
+                "Q" => {
+                    if let Some(s) = stack.pop() {
+                        state = s;
+                    }
+                }
+                "cm" if n.len() == 6 => {
+                    state.ctm = mat_mul(&[n[0], n[1], n[2], n[3], n[4], n[5]], &state.ctm);
+                }
+                "w" if !n.is_empty() => state.line_width = n[0],
+                "g" | "rg" | "k" | "sc" | "scn" => {
+                    if let Some(c) = svg_color(&op.operands) {
+                        state.fill = c;
+                    }
+                }
+                "G" | "RG" | "K" | "SC" | "SCN" => {
+                    if let Some(c) = svg_color(&op.operands) {
+                        state.stroke = c;
+                    }
+                }
+                "m" if n.len() == 2 => {
+                    let (x, y) = mat_apply(&state.ctm, n[0], n[1]);
+                    path.push_str(&format!("M{:.2} {:.2} ", x, y));
+                    current = (x, y);
+                }
+                "l" if n.len() == 2 => {
+                    let (x, y) = mat_apply(&state.ctm, n[0], n[1]);
+                    path.push_str(&format!("L{:.2} {:.2} ", x, y));
+                    current = (x, y);
+                }
+                "c" | "v" | "y" => {
+                    let pts: Vec<(f64, f64)> = n.chunks(2).filter(|c| c.len() == 2).map(|c| mat_apply(&state.ctm, c[0], c[1])).collect();
+                    let (c1, c2, end) = match (op.operator.as_str(), pts.as_slice()) {
+                        ("c", [a, b, e]) => (*a, *b, *e),
+                        ("v", [b, e]) => (current, *b, *e),
+                        ("y", [a, e]) => (*a, *e, *e),
+                        _ => continue,
+                    };
+                    path.push_str(&format!(
+                        "C{:.2} {:.2} {:.2} {:.2} {:.2} {:.2} ",
+                        c1.0, c1.1, c2.0, c2.1, end.0, end.1
+                    ));
+                    current = end;
+                }
+                "re" if n.len() == 4 => {
+                    let corners = [(n[0], n[1]), (n[0] + n[2], n[1]), (n[0] + n[2], n[1] + n[3]), (n[0], n[1] + n[3])];
+                    for (i, &(x, y)) in corners.iter().enumerate() {
+                        let (px, py) = mat_apply(&state.ctm, x, y);
+                        path.push_str(&format!("{}{:.2} {:.2} ", if i == 0 { "M" } else { "L" }, px, py));
+                    }
+                    path.push_str("Z ");
+                    current = mat_apply(&state.ctm, n[0], n[1]);
+                }
+                "h" => path.push_str("Z "),
+                "f" | "F" | "f*" | "S" | "s" | "B" | "B*" | "b" | "b*" | "n" => {
+                    let operator = op.operator.as_str();
+                    if operator != "n" && !path.is_empty() {
+                        if matches!(operator, "s" | "b" | "b*") {
+                            path.push('Z');
+                        }
+                        let fills = matches!(operator, "f" | "F" | "f*" | "B" | "B*" | "b" | "b*");
+                        let strokes = matches!(operator, "S" | "s" | "B" | "B*" | "b" | "b*");
+                        let scale = (state.ctm[0] * state.ctm[3] - state.ctm[1] * state.ctm[2]).abs().sqrt();
+                        self.out.push_str(&format!(
+                            "<path d=\"{}\" fill=\"{}\"{} stroke=\"{}\" stroke-width=\"{:.2}\"/>\n",
+                            path.trim_end(),
+                            if fills { state.fill.as_str() } else { "none" },
+                            if operator.ends_with('*') { " fill-rule=\"evenodd\"" } else { "" },
+                            if strokes { state.stroke.as_str() } else { "none" },
+                            (state.line_width.max(0.0) * scale).max(0.25),
+                        ));
+                    }
+                    path.clear();
+                }
+                "BT" => {
+                    tm = IDENTITY;
+                    tlm = IDENTITY;
+                }
+                "Tf" => {
+                    if let Some(Ok(name)) = op.operands.first().map(|o| o.as_name()) {
+                        current_font = self.font_for(resources, name);
+                    }
+                    if let Some(size) = op.operands.get(1).and_then(|o| o.as_float().ok()) {
+                        state.font_size = size as f64;
+                    }
+                }
+                "TL" if !n.is_empty() => state.leading = n[0],
+                "Tc" if !n.is_empty() => state.char_spacing = n[0],
+                "Tw" if !n.is_empty() => state.word_spacing = n[0],
+                "Tz" if !n.is_empty() => state.horizontal_scale = n[0] / 100.0,
+                "Tr" if !n.is_empty() => state.render_mode = n[0] as i64,
+                "Td" | "TD" if n.len() == 2 => {
+                    if op.operator == "TD" {
+                        state.leading = -n[1];
+                    }
+                    tlm = mat_mul(&[1.0, 0.0, 0.0, 1.0, n[0], n[1]], &tlm);
+                    tm = tlm;
+                }
+                "Tm" if n.len() == 6 => {
+                    tlm = [n[0], n[1], n[2], n[3], n[4], n[5]];
+                    tm = tlm;
+                }
+                "T*" => {
+                    tlm = mat_mul(&[1.0, 0.0, 0.0, 1.0, 0.0, -state.leading], &tlm);
+                    tm = tlm;
+                }
+                "Tj" | "TJ" | "'" | "\"" => {
+                    if op.operator == "'" || op.operator == "\"" {
+                        if op.operator == "\"" && n.len() == 3 {
+                            state.word_spacing = n[0];
+                            state.char_spacing = n[1];
+                        }
+                        tlm = mat_mul(&[1.0, 0.0, 0.0, 1.0, 0.0, -state.leading], &tlm);
+                        tm = tlm;
+                    }
+                    let items: Vec<Object> = match op.operator.as_str() {
+                        "TJ" => op.operands.first().and_then(|o| o.as_array().ok()).cloned().unwrap_or_default(),
+                        _ => op.operands.last().cloned().into_iter().collect(),
+                    };
+                    for item in items {
+                        match item {
+                            Object::String(bytes, _) => {
+                                self.show_text(&bytes, current_font, &state, &mut tm);
+                            }
+                            Object::Integer(_) | Object::Real(_) => {
+                                let adjust = item.as_float().unwrap_or(0.0) as f64;
+                                let tx = -adjust / 1000.0 * state.font_size * state.horizontal_scale;
+                                tm = mat_mul(&[1.0, 0.0, 0.0, 1.0, tx, 0.0], &tm);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                "Do" if depth < 8 => {
+                    if let Some(Ok(name)) = op.operands.first().map(|o| o.as_name()) {
+                        self.draw_xobject(resources, name, &state, depth);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn show_text(&mut self, bytes: &[u8], font_id: Option<lopdf::ObjectId>, state: &SvgState, tm: &mut Matrix) {
+        let font = match font_id.and_then(|id| self.fonts.get(&id)) {
+            Some(f) => f,
+            None => return,
+        };
+        let text = font.decoder.decode(bytes);
+        // Render mode 3 is invisible text (e.g. OCR layers); 7 is clip-only.
+        if !text.trim().is_empty() && state.render_mode != 3 && state.render_mode != 7 {
+            let m = mat_mul(&[state.horizontal_scale, 0.0, 0.0, 1.0, 0.0, 0.0], &mat_mul(tm, &state.ctm));
+            self.out.push_str(&format!(
+                "<text transform=\"matrix({:.4} {:.4} {:.4} {:.4} {:.2} {:.2}) scale(1 -1)\" font-size=\"{:.2}\" font-family=\"{}\"{}{} fill=\"{}\" xml:space=\"preserve\">{}</text>\n",
+                m[0], m[1], m[2], m[3], m[4], m[5],
+                state.font_size,
+                font.family,
+                if font.bold { " font-weight=\"bold\"" } else { "" },
+                if font.italic { " font-style=\"italic\"" } else { "" },
+                state.fill,
+                xml_escape(&text),
+            ));
+        }
+        let mut advance = 0.0;
+        for code in font.decoder.split_codes(bytes) {
+            advance += font.advance(code) / 1000.0 * state.font_size + state.char_spacing;
+            if code == 32 && !font.decoder.is_cid {
+                advance += state.word_spacing;
+            }
+        }
+        *tm = mat_mul(&[1.0, 0.0, 0.0, 1.0, advance * state.horizontal_scale, 0.0], tm);
+    }
+
+    fn draw_xobject(&mut self, resources: &lopdf::Dictionary, name: &[u8], state: &SvgState, depth: u32) {
+        let doc = self.doc;
+        let stream = match resources
+            .get(b"XObject")
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_dict())
+            .and_then(|d| d.get(name))
+            .and_then(|o| doc.dereference(o))
+            .and_then(|(_, o)| o.as_stream())
+        {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+        let subtype = stream.dict.get(b"Subtype").and_then(|o| o.as_name()).unwrap_or(b"");
+        let m = state.ctm;
+        if subtype == b"Image" {
+            // Image space is the unit square with its origin at the top-left.
+            let transform = format!(
+                "matrix({:.4} {:.4} {:.4} {:.4} {:.2} {:.2}) matrix(1 0 0 -1 0 1)",
+                m[0], m[1], m[2], m[3], m[4], m[5]
+            );
+            let is_jpeg = match stream.dict.get(b"Filter") {
+                Ok(Object::Name(n)) => n == b"DCTDecode",
+                Ok(Object::Array(a)) => a.len() == 1 && a[0].as_name().is_ok_and(|n| n == b"DCTDecode"),
+                _ => false,
+            };
+            if is_jpeg {
+                self.out.push_str(&format!(
+                    "<image transform=\"{}\" width=\"1\" height=\"1\" preserveAspectRatio=\"none\" href=\"data:image/jpeg;base64,{}\"/>\n",
+                    transform,
+                    base64_encode(&stream.content)
+                ));
+            } else {
+                self.out.push_str(&format!(
+                    "<rect transform=\"{}\" width=\"1\" height=\"1\" fill=\"#cccccc\"/>\n",
+                    transform
+                ));
+            }
+        } else if subtype == b"Form" {
+            let matrix = stream
+                .dict
+                .get(b"Matrix")
+                .and_then(|o| o.as_array())
+                .ok()
+                .map(|a| a.iter().map(|o| o.as_float().unwrap_or(0.0) as f64).collect::<Vec<_>>())
+                .filter(|v| v.len() == 6)
+                .map(|v| [v[0], v[1], v[2], v[3], v[4], v[5]])
+                .unwrap_or(IDENTITY);
+            let form_resources = stream
+                .dict
+                .get(b"Resources")
+                .and_then(|o| doc.dereference(o))
+                .and_then(|(_, o)| o.as_dict())
+                .unwrap_or(resources);
+            let content = stream.decompressed_content().unwrap_or_else(|_| stream.content.clone());
+            let mut form_state = state.clone();
+            form_state.ctm = mat_mul(&matrix, &state.ctm);
+            self.render_content(&content, form_resources, form_state, depth + 1);
+        }
+    }
+}
+
+#[cfg(feature = "render")]
+fn render_svg(doc: &Document, page_id: lopdf::ObjectId) -> AppResult<String> {
+    let rect = page_visible_rect(doc, page_id);
+    let (width, height) = (rect[2] - rect[0], rect[3] - rect[1]);
+    let rotation = inherited_attr(doc, page_id, b"Rotate")
+        .and_then(|o| o.as_i64().ok())
+        .unwrap_or(0)
+        .rem_euclid(360);
+
+    // Flip PDF's bottom-up y axis, then apply the page's display rotation.
+    let flip = [1.0, 0.0, 0.0, -1.0, -rect[0], rect[3]];
+    let (rotate, out_w, out_h): (Matrix, f64, f64) = match rotation {
+        90 => ([0.0, 1.0, -1.0, 0.0, height, 0.0], height, width),
+        180 => ([-1.0, 0.0, 0.0, -1.0, width, height], width, height),
+        270 => ([0.0, -1.0, 1.0, 0.0, 0.0, width], height, width),
+        _ => (IDENTITY, width, height),
+    };
+    let m = mat_mul(&flip, &rotate);
+
+    let resources = inherited_attr(doc, page_id, b"Resources")
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .cloned()
+        .unwrap_or_default();
+    let content = doc.get_page_content(page_id)?;
+
+    let mut renderer = SvgRenderer {
+        doc,
+        out: String::new(),
+        fonts: std::collections::HashMap::new(),
+    };
+    let state = SvgState {
+        ctm: IDENTITY,
+        fill: "#000000".to_string(),
+        stroke: "#000000".to_string(),
+        line_width: 1.0,
+        font_size: 12.0,
+        leading: 0.0,
+        char_spacing: 0.0,
+        word_spacing: 0.0,
+        horizontal_scale: 1.0,
+        render_mode: 0,
+    };
+    renderer.render_content(&content, &resources, state, 0);
+
+    Ok(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.2}\" height=\"{h:.2}\" viewBox=\"0 0 {w:.2} {h:.2}\">\n<rect width=\"100%\" height=\"100%\" fill=\"#ffffff\"/>\n<g transform=\"matrix({:.4} {:.4} {:.4} {:.4} {:.2} {:.2})\">\n{}</g>\n</svg>\n",
+        m[0], m[1], m[2], m[3], m[4], m[5],
+        renderer.out,
+        w = out_w,
+        h = out_h,
+    ))
+}
+
+/// Ink coverage of one page, as percentages of the page area.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PageCoverage {
+    pub page_number: u32,
+    /// Share of pixels that are not paper white.
+    pub coverage: f32,
+    pub cyan: f32,
+    pub magenta: f32,
+    pub yellow: f32,
+    pub black: f32,
+}
+
+/// Resolution used to rasterize pages for coverage; one pixel per point is
+/// plenty for an estimate.
+#[cfg(feature = "render")]
+const COVERAGE_DPI: f32 = 72.0;
+
+/// Estimates ink coverage per page for print costing by rasterizing the SVG
+/// rendering. Channel figures use a naive RGB to CMYK split, so treat them as
+/// estimates; images the SVG renderer can't draw count as grey.
+#[tauri::command]
+async fn ink_coverage(path: String) -> AppResult<Vec<PageCoverage>> {
+    #[cfg(feature = "render")]
+    {
+        tauri::async_runtime::spawn_blocking(move || ink_coverage_blocking(path))
+            .await
+            .map_err(|e| AppError::Validation(format!("Coverage task failed: {}", e)))?
+    }
+    #[cfg(not(feature = "render"))]
+    {
+        let _ = path;
+        Err(AppError::UnsupportedFeature("render".to_string()))
+    }
 }
 
-#[tauri::command]
-fn debug_pdf_structure(path: String) -> AppResult<PdfDiagnosticResult> {
-    let mut file = fs::File::open(&path)?;
-    let metadata = file.metadata()?;
-    let file_size = metadata.len();
-
-    let mut header_buf = vec![0u8; 1024.min(file_size as usize)];
-    file.read_exact(&mut header_buf)?;
-    let header_str = String::from_utf8_lossy(&header_buf).to_string();
+#[cfg(feature = "render")]
+fn ink_coverage_blocking(path: String) -> AppResult<Vec<PageCoverage>> {
+    let doc = load_pdf(&path)?;
+    let mut options = resvg::usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
 
-    let mut trailer_buf = vec![0u8; 2048.min(file_size as usize)];
-    let seek_pos = if file_size > 2048 { file_size - 2048 } else { 0 };
-    file.seek(SeekFrom::Start(seek_pos))?;
-    file.read_exact(&mut trailer_buf)?;
-    let trailer_str = String::from_utf8_lossy(&trailer_buf).to_string();
+    let mut results = Vec::new();
+    for (page_number, page_id) in doc.get_pages() {
+        let svg = render_svg(&doc, page_id)?;
+        let pixmap = rasterize_svg(&svg, &options, COVERAGE_DPI)?;
+        results.push(pixmap_coverage(page_number, &pixmap));
+    }
+    Ok(results)
+}
 
-    Ok(PdfDiagnosticResult {
-        header: header_str,
-        trailer: trailer_str,
-        file_size,
-    })
+#[cfg(feature = "render")]
+fn rasterize_svg(svg: &str, options: &resvg::usvg::Options, dpi: f32) -> AppResult<resvg::tiny_skia::Pixmap> {
+    let tree = resvg::usvg::Tree::from_str(svg, options)
+        .map_err(|e| AppError::Validation(format!("Failed to rasterize page: {}", e)))?;
+    let scale = dpi / 72.0;
+    let size = tree.size();
+    let width = (size.width() * scale).ceil().max(1.0) as u32;
+    let height = (size.height() * scale).ceil().max(1.0) as u32;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| AppError::Validation("Page is too large to rasterize".to_string()))?;
+    pixmap.fill(resvg::tiny_skia::Color::WHITE);
+    resvg::render(&tree, resvg::tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    Ok(pixmap)
 }
 
-fn decode_pdf_text(obj: &Object) -> String {
-    match obj {
-        Object::String(bytes, _) => {
-            if bytes.starts_with(&[0xFE, 0xFF]) {
-                let tail = &bytes[2..];
-                // Guard against odd-length UTF-16 data from malformed PDFs
-                let even_len = tail.len() & !1;
-                let utf16: Vec<u16> = tail[..even_len]
-                    .chunks_exact(2)
-                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
-                    .collect();
-                String::from_utf16_lossy(&utf16)
-            } else if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
-                String::from_utf8_lossy(&bytes[3..]).to_string()
-            } else {
-                bytes.iter().map(|&b| b as char).collect()
-            }
+#[cfg(feature = "render")]
+fn pixmap_coverage(page_number: u32, pixmap: &resvg::tiny_skia::Pixmap) -> PageCoverage {
+    let mut inked = 0u64;
+    let mut cmyk = [0f64; 4];
+    for pixel in pixmap.pixels() {
+        // Opaque after the white fill, so premultiplication doesn't matter.
+        let (r, g, b) = (pixel.red(), pixel.green(), pixel.blue());
+        if r.min(g).min(b) < 250 {
+            inked += 1;
         }
-        Object::Name(bytes) => String::from_utf8_lossy(bytes).to_string(),
-        _ => String::new(),
+        let (r, g, b) = (r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0);
+        let k = 1.0 - r.max(g).max(b);
+        if k < 1.0 {
+            cmyk[0] += (1.0 - r - k) / (1.0 - k);
+            cmyk[1] += (1.0 - g - k) / (1.0 - k);
+            cmyk[2] += (1.0 - b - k) / (1.0 - k);
+        }
+        cmyk[3] += k;
+    }
+    let total = pixmap.pixels().len().max(1) as f64;
+    let percent = |v: f64| (v / total * 100.0) as f32;
+    PageCoverage {
+        page_number,
+        coverage: percent(inked as f64),
+        cyan: percent(cmyk[0]),
+        magenta: percent(cmyk[1]),
+        yellow: percent(cmyk[2]),
+        black: percent(cmyk[3]),
     }
 }
 
+/// Default resolution for page image export.
+#[cfg(feature = "render")]
+const EXPORT_DPI: u32 = 150;
+
+/// Rasterizes pages to PNG, named `<stem>_page<N>.png`. Images go into
+/// `output_zip` when given (one page in memory at a time), otherwise into
+/// `output_dir` (the source's folder by default). Returns the written paths.
 #[tauri::command]
-fn get_pdf_properties(path: String) -> AppResult<PdfProperties> {
-    let doc = load_pdf(&path)?;
-    let pages = doc.get_pages();
-    let page_count = pages.len() as u32;
+async fn export_pages_as_images(
+    path: String,
+    pages: Option<PageSelection>,
+    dpi: Option<u32>,
+    output_dir: Option<String>,
+    output_zip: Option<String>,
+) -> AppResult<Vec<String>> {
+    #[cfg(feature = "render")]
+    {
+        tauri::async_runtime::spawn_blocking(move || export_pages_as_images_blocking(path, pages, dpi, output_dir, output_zip))
+            .await
+            .map_err(|e| AppError::Validation(format!("Export task failed: {}", e)))?
+    }
+    #[cfg(not(feature = "render"))]
+    {
+        let _ = (path, pages, dpi, output_dir, output_zip);
+        Err(AppError::UnsupportedFeature("render".to_string()))
+    }
+}
 
-    // Get page size from first page
-    let mut page_width = 0.0;
-    let mut page_height = 0.0;
-    let mut colorspace = "DeviceRGB (Likely)".to_string();
+#[cfg(feature = "render")]
+fn export_pages_as_images_blocking(
+    path: String,
+    pages: Option<PageSelection>,
+    dpi: Option<u32>,
+    output_dir: Option<String>,
+    output_zip: Option<String>,
+) -> AppResult<Vec<String>> {
+    use std::io::Write;
 
-    if let Some(&page_id) = pages.get(&1) {
-        let page_dict = doc.get_dictionary(page_id)?;
-        
-        // Dimensions
-        if let Ok(Object::Array(rect)) = page_dict.get(b"MediaBox") {
-            if rect.len() >= 4 {
-                let x1 = rect[0].as_float().unwrap_or(0.0);
-                let y1 = rect[1].as_float().unwrap_or(0.0);
-                let x2 = rect[2].as_float().unwrap_or(0.0);
-                let y2 = rect[3].as_float().unwrap_or(0.0);
-                page_width = (x2 - x1).abs();
-                page_height = (y2 - y1).abs();
-            }
+    let source = resolve_path(&path);
+    let stem = source
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "document".to_string());
+    let dpi = dpi.unwrap_or(EXPORT_DPI).clamp(1, 600) as f32;
+
+    let out_dir = match &output_dir {
+        Some(d) => resolve_path(d),
+        None => source.parent().unwrap_or_else(|| Path::new(".")).to_path_buf(),
+    };
+    match &output_zip {
+        Some(zip_path) => ensure_output_writable(zip_path)?,
+        None if !out_dir.is_dir() => {
+            return Err(AppError::Path("Output path is not a directory.".to_string()));
         }
+        None => ensure_dir_writable(&out_dir)?,
+    }
 
-        // Colorspace detection (Advanced)
-        if let Ok(resources) = page_dict.get(b"Resources").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
-            if let Ok(cs_dict) = resources.get(b"ColorSpace").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
-                if cs_dict.has(b"DeviceCMYK") || cs_dict.iter().any(|(k, _)| k == b"CMYK") {
-                    colorspace = "DeviceCMYK".to_string();
-                } else if cs_dict.has(b"DeviceGray") || cs_dict.iter().any(|(k, _)| k == b"Gray") {
-                    colorspace = "DeviceGray".to_string();
-                }
+    let doc = load_pdf(&source)?;
+    let page_map = doc.get_pages();
+    let page_numbers = match &pages {
+        Some(selection) => selection.page_numbers(page_map.len() as u32)?,
+        None => page_map.keys().copied().collect(),
+    };
+    let mut archive = match &output_zip {
+        Some(zip_path) => Some(zip::ZipWriter::new(fs::File::create(resolve_path(zip_path))?)),
+        None => None,
+    };
+    let mut options = resvg::usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let zip_err = |e: zip::result::ZipError| AppError::Validation(format!("Failed to write archive: {}", e));
+
+    let mut written = Vec::new();
+    let mut taken = std::collections::HashSet::new();
+    for page_number in page_numbers {
+        let Some(&page_id) = page_map.get(&page_number) else { continue };
+        let pixmap = rasterize_svg(&render_svg(&doc, page_id)?, &options, dpi)?;
+        let rgba = image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixmap.take())
+            .ok_or_else(|| AppError::Validation("Rendered page has an unexpected size".to_string()))?;
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(rgba)
+            .to_rgb8()
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .map_err(|e| AppError::Validation(format!("Failed to encode page {}: {}", page_number, e)))?;
+
+        let name = format!("{}_page{}.png", stem, page_number);
+        match archive.as_mut() {
+            Some(zip) => {
+                // PNG data is already compressed; deflating it again gains nothing.
+                let entry = zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Stored);
+                zip.start_file(name, entry).map_err(zip_err)?;
+                zip.write_all(&png)?;
             }
-            // Check for CMYK/Gray in XObjects too
-            if let Ok(xobjects) = resources.get(b"XObject").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()) {
-                for (_, xo_ref) in xobjects.iter() {
-                    if let Ok(xo) = doc.dereference(xo_ref).and_then(|(_, o)| o.as_dict()) {
-                        if let Ok(xo_cs) = xo.get(b"ColorSpace").and_then(|o| doc.dereference(o)) {
-                            match xo_cs.1 {
-                                Object::Name(n) if n == b"DeviceCMYK" => colorspace = "DeviceCMYK".to_string(),
-                                Object::Name(n) if n == b"DeviceGray" => colorspace = "DeviceGray".to_string(),
-                                _ => {}
-                            }
-                        }
-                    }
-                }
+            None => {
+                let out = unique_output_path(&out_dir, std::ffi::OsStr::new(&name), &mut taken);
+                fs::write(&out, &png)?;
+                written.push(path_to_string(&out));
             }
         }
     }
 
-    let mut metadata = std::collections::HashMap::new();
-    let mut created = String::new();
-    let mut modified = String::new();
-    let mut producer = String::new();
-    let mut creator = String::new();
-
-    if let Ok(info_id) = doc.trailer.get(b"Info").and_then(|o| o.as_reference()) {
-        if let Ok(info) = doc.get_object(info_id).and_then(|o| o.as_dict()) {
-            for (key, value) in info {
-                let key_str = String::from_utf8_lossy(key).to_string();
-                let val_str = decode_pdf_text(value);
-                if !val_str.is_empty() {
-                    match key_str.as_str() {
-                        "CreationDate" => created = val_str,
-                        "ModDate" => modified = val_str,
-                        "Producer" => producer = val_str,
-                        "Creator" => creator = val_str,
-                        _ => { metadata.insert(key_str, val_str); }
-                    }
-                }
-            }
-        }
+    if let (Some(zip), Some(zip_path)) = (archive, output_zip) {
+        zip.finish().map_err(zip_err)?;
+        written.push(zip_path);
     }
+    Ok(written)
+}
 
-    // Font detection
-    let mut fonts = std::collections::HashSet::new();
-    let mut image_dpis = Vec::new();
+// --- Barcode Separators ---
 
-    for id in doc.objects.keys() {
-        if let Ok(obj) = doc.get_object(*id) {
-            if let Ok(dict) = obj.as_dict() {
-                // Fonts
-                if dict.get(b"Type").map_or(false, |t| t.as_name().map_or(false, |n| n == b"Font")) {
-                    if let Ok(base_font) = dict.get(b"BaseFont").and_then(|o| o.as_name()) {
-                        fonts.insert(String::from_utf8_lossy(base_font).to_string());
-                    }
+/// Plans a split at QR separator sheets. Pages before the first separator
+/// form an unnamed part; each separator starts a part named after its code,
+/// keeping the separator page unless `remove_separators` is set.
+///
+/// Separators are read from the page's scanned image, so this suits the
+/// image-only pages a scanner produces; vector-drawn codes are not seen.
+fn barcode_separator_parts(doc: &Document, remove_separators: bool) -> AppResult<Vec<SplitPart>> {
+    #[cfg(feature = "barcode")]
+    {
+        let mut parts: Vec<SplitPart> = Vec::new();
+        let mut current: Option<SplitPart> = None;
+        for (page_number, page_id) in doc.get_pages() {
+            match page_barcode(doc, page_id) {
+                Some(value) => {
+                    parts.extend(current.take());
+                    let label = sanitize_part_label(&value);
+                    current = if remove_separators {
+                        Some(SplitPart { start: page_number + 1, end: page_number, label, prepared: None })
+                    } else {
+                        Some(SplitPart { start: page_number, end: page_number, label, prepared: None })
+                    };
                 }
-                // Images (XObjects)
-                if dict.get(b"Subtype").map_or(false, |t| t.as_name().map_or(false, |n| n == b"Image")) {
-                    if let (Ok(w), Ok(_h)) = (dict.get(b"Width").and_then(|o| o.as_i64()), dict.get(b"Height").and_then(|o| o.as_i64())) {
-                        // Calculate an estimated DPI if it was to fill the page width
-                        let dpi = if page_width > 0.0 {
-                            (w as f32 * 72.0 / page_width) as u32
-                        } else { 72 };
-                        image_dpis.push(dpi);
+                None => match current.as_mut() {
+                    Some(part) => part.end = page_number,
+                    None => {
+                        current = Some(SplitPart { start: page_number, end: page_number, label: None, prepared: None })
                     }
-                }
+                },
             }
         }
+        parts.extend(current);
+        // A separator followed directly by another (or by the end) leaves an
+        // empty part when separators are removed.
+        parts.retain(|part| part.start <= part.end);
+        if parts.is_empty() {
+            return Err(AppError::Validation("The document contains only separator pages.".to_string()));
+        }
+        Ok(parts)
+    }
+    #[cfg(not(feature = "barcode"))]
+    {
+        let _ = (doc, remove_separators);
+        Err(AppError::UnsupportedFeature("barcode".to_string()))
     }
+}
 
-    Ok(PdfProperties {
-        version: doc.version.clone(),
-        page_count,
-        page_size: String::new(), // Legacy field keeping to avoid breaking too much at once
-        metadata,
-        created,
-        modified,
-        encrypted: doc.trailer.has(b"Encrypt"),
-        producer,
-        creator,
-        fonts: fonts.into_iter().collect(),
-        image_dpi: image_dpis,
-        doc_dpi: 72,
-        colorspace,
-        page_width,
-        page_height,
-    })
+/// Decodes the first QR code found in the largest image drawn on a page.
+#[cfg(feature = "barcode")]
+fn page_barcode(doc: &Document, page_id: lopdf::ObjectId) -> Option<String> {
+    let image = page_scan_image(doc, page_id)?.to_luma8();
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        image.width() as usize,
+        image.height() as usize,
+        |x, y| image.get_pixel(x as u32, y as u32).0[0],
+    );
+    prepared
+        .detect_grids()
+        .into_iter()
+        .find_map(|grid| grid.decode().ok())
+        .map(|(_, content)| content)
+}
+
+/// The largest decodable image XObject in a page's resources, which for a
+/// scanned page is the scan itself.
+#[cfg(feature = "barcode")]
+fn page_scan_image(doc: &Document, page_id: lopdf::ObjectId) -> Option<image::DynamicImage> {
+    let xobjects = inherited_attr(doc, page_id, b"Resources")
+        .and_then(|o| doc.dereference(o).ok())
+        .and_then(|(_, o)| o.as_dict().ok())
+        .and_then(|r| r.get(b"XObject").and_then(|o| doc.dereference(o)).and_then(|(_, o)| o.as_dict()).ok())?;
+    xobjects
+        .iter()
+        .filter_map(|(_, xo)| doc.dereference(xo).and_then(|(_, o)| o.as_stream()).ok())
+        .filter(|s| s.dict.get(b"Subtype").and_then(|o| o.as_name()).is_ok_and(|n| n == b"Image"))
+        .filter_map(|s| decode_image_xobject(doc, s).map(|decoded| decoded.pixels))
+        .max_by_key(|img| img.width() as u64 * img.height() as u64)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1314,6 +9542,7 @@ pub fn run() {
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_localhost::Builder::new(LOCALHOST_PORT).build())
+        .manage(OperationRegistry::default())
         .menu(|handle| {
             use tauri::menu::{Menu, MenuItem, Submenu, PredefinedMenuItem};
             
@@ -1387,17 +9616,72 @@ pub fn run() {
             pdf_page_count,
             split_pdf_preview,
             split_pdf,
+            cancel_operation,
+            extract_pages,
             get_page_boxes,
+            check_page_boxes,
+            fix_missing_mediabox,
+            set_crop_box,
+            normalize_page_size,
             merge_pdfs,
+            merge_pdfs_with_bookmarks,
+            insert_pages,
+            merge_capped,
             rotate_pdf_pages,
             read_pdf_buffer,
             get_organiser_pdf_metadata,
             apply_pdf_organisation,
+            reverse_pages,
+            save_organise_plan,
+            load_organise_plan,
+            keep_pages,
+            delete_pages,
             mix_pdfs,
             protect_pdf,
             compress_pdf_v2,
+            compress_pdf_bytes,
+            rotate_pdf_bytes,
+            process_pipeline,
+            scrub_metadata,
             debug_pdf_structure,
             get_pdf_properties,
+            extract_pdf_text,
+            extract_text,
+            extract_images,
+            extract_attachments,
+            add_attachment,
+            pdf_content_hash,
+            batch_bates_number,
+            add_text_watermark,
+            stamp_image,
+            add_page_numbers,
+            overlay_pdf,
+            render_page_svg,
+            flatten_page_tree,
+            get_page_info,
+            fix_page_count,
+            batch_apply,
+            split_part_count,
+            list_form_fields,
+            read_form_fields,
+            fill_form_fields,
+            flatten_forms,
+            set_document_language,
+            set_metadata,
+            set_pdf_metadata,
+            compare_metadata,
+            trim_trailing_blank_pages,
+            page_content_sizes,
+            scans_to_searchable_pdf,
+            remove_restrictions,
+            decrypt_pdf,
+            get_capabilities,
+            ink_coverage,
+            sample_pages,
+            export_pages_as_images,
+            prepare_for_print,
+            report_incremental_updates,
+            remove_update_history,
         ])
         .setup(move |app| {
             let url: tauri::Url = format!("http://localhost:{}", LOCALHOST_PORT)
@@ -1422,3 +9706,596 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A document with `n` pages, each showing "Page N" in Helvetica. The
+    /// pages inherit their MediaBox and Resources from the root Pages node.
+    fn sample_doc(n: u32) -> Document {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {"Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica"});
+        let mut kids = Vec::new();
+        for i in 1..=n {
+            let content = format!("BT /F1 24 Tf 100 600 Td (Page {}) Tj ET", i);
+            let content_id = doc.add_object(lopdf::Stream::new(dictionary! {}, content.into_bytes()));
+            let page_id = doc.add_object(dictionary! {"Type" => "Page", "Parent" => pages_id, "Contents" => content_id});
+            kids.push(Object::Reference(page_id));
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => n as i64,
+                "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {"Type" => "Catalog", "Pages" => pages_id});
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    /// A fresh, empty directory for one test's files.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("paradise-pdf-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn save_doc(doc: &mut Document, path: &Path) -> String {
+        doc.save(path).unwrap();
+        path_to_string(path)
+    }
+
+    fn page_texts(path: &str) -> Vec<String> {
+        let doc = load_pdf(path).unwrap();
+        doc.get_pages().values().map(|&id| extract_page_text(&doc, id).unwrap()).collect()
+    }
+
+    #[test]
+    fn encryption_round_trips_with_either_password() {
+        let dir = test_dir("encryption");
+        let source = save_doc(&mut sample_doc(2), &dir.join("plain.pdf"));
+        for algorithm in [EncryptionAlgorithm::Rc4_128, EncryptionAlgorithm::Aes128, EncryptionAlgorithm::Aes256] {
+            let encrypted = path_to_string(&dir.join(format!("{:?}.pdf", algorithm)));
+            protect_pdf(
+                source.clone(),
+                "user".to_string(),
+                Some("owner".to_string()),
+                encrypted.clone(),
+                None,
+                Some(algorithm),
+                None,
+            )
+            .unwrap();
+            assert!(load_pdf(&encrypted).unwrap().is_encrypted(), "{:?}", algorithm);
+
+            for password in ["user", "owner"] {
+                let decrypted = path_to_string(&dir.join(format!("{:?}-{}.pdf", algorithm, password)));
+                decrypt_pdf(encrypted.clone(), password.to_string(), decrypted.clone())
+                    .unwrap_or_else(|e| panic!("{:?} with the {} password: {}", algorithm, password, e));
+                let texts = page_texts(&decrypted);
+                assert_eq!(texts.len(), 2, "{:?} with the {} password", algorithm, password);
+                assert!(texts[0].contains("Page 1") && texts[1].contains("Page 2"), "{:?}: {:?}", algorithm, texts);
+            }
+            assert!(matches!(
+                decrypt_pdf(encrypted, "wrong".to_string(), path_to_string(&dir.join("wrong.pdf"))),
+                Err(AppError::Validation(_))
+            ));
+        }
+    }
+
+    fn split_one_per_page(source: &str, output_dir: &Path) -> AppResult<SplitResult> {
+        split_pdf_blocking(
+            source.to_string(),
+            Some(path_to_string(output_dir)),
+            SplitMode::OnePerPage,
+            None,
+            None,
+            None,
+            None,
+            |_| {},
+        )
+    }
+
+    #[test]
+    fn failed_split_removes_finished_parts() {
+        let dir = test_dir("split_failure");
+        let source = save_doc(&mut sample_doc(6), &dir.join("doc.pdf"));
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        // A directory where part 4 should go makes that one save fail.
+        fs::create_dir_all(out.join("doc_part4.pdf")).unwrap();
+        assert!(matches!(split_one_per_page(&source, &out), Err(AppError::Io(_))));
+        let left: Vec<_> = fs::read_dir(&out).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(left, ["doc_part4.pdf"]);
+    }
+
+    fn file_entry(path: &Path) -> FileEntry {
+        FileEntry {
+            path: path_to_string(path),
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            encrypted: None,
+        }
+    }
+
+    fn dir_listing(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn rename_swaps_two_names() {
+        let dir = test_dir("rename_swap");
+        fs::write(dir.join("f1.pdf"), "one").unwrap();
+        fs::write(dir.join("f2.pdf"), "two").unwrap();
+        // f2 becomes f1 and f1 becomes f2.
+        let entries = vec![file_entry(&dir.join("f2.pdf")), file_entry(&dir.join("f1.pdf"))];
+        let result = batch_rename(entries, "f#".to_string()).unwrap();
+        assert_eq!((result.renamed, result.rolled_back), (2, false));
+        assert!(result.failed.is_empty());
+        assert_eq!(fs::read_to_string(dir.join("f1.pdf")).unwrap(), "two");
+        assert_eq!(fs::read_to_string(dir.join("f2.pdf")).unwrap(), "one");
+        assert_eq!(dir_listing(&dir), ["f1.pdf", "f2.pdf"]);
+    }
+
+    #[test]
+    fn rename_rolls_back_a_mid_batch_failure() {
+        let dir = test_dir("rename_rollback");
+        fs::write(dir.join("a.pdf"), "a").unwrap();
+        fs::write(dir.join("b.pdf"), "b").unwrap();
+        // The third source is missing, so moving it fails after the first two
+        // have already been moved.
+        let entries = vec![
+            file_entry(&dir.join("a.pdf")),
+            file_entry(&dir.join("b.pdf")),
+            file_entry(&dir.join("missing.pdf")),
+        ];
+        let result = batch_rename(entries, "x#".to_string()).unwrap();
+        assert_eq!((result.renamed, result.rolled_back), (0, true));
+        assert_eq!(result.failed.len(), 1, "{:?}", result.failed.iter().map(|f| &f.error).collect::<Vec<_>>());
+        assert_eq!(result.failed[0].path, path_to_string(&dir.join("missing.pdf")));
+        assert_eq!(fs::read_to_string(dir.join("a.pdf")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("b.pdf")).unwrap(), "b");
+        assert_eq!(dir_listing(&dir), ["a.pdf", "b.pdf"]);
+    }
+
+    /// Gives `doc` a flat outline with one bookmark per (title, page number).
+    /// A title starting with `@` points at its page through a named
+    /// destination instead of an explicit one.
+    fn add_outline(doc: &mut Document, items: &[(&str, u32)]) {
+        let pages = doc.get_pages();
+        let root_id = doc.new_object_id();
+        let mut ids = Vec::new();
+        let mut named = Vec::new();
+        for &(title, page) in items {
+            let dest: Object = vec![Object::Reference(pages[&page]), "Fit".into()].into();
+            let dest = match title.strip_prefix('@') {
+                Some(name) => {
+                    named.push(Object::string_literal(name));
+                    named.push(dest);
+                    Object::string_literal(name)
+                }
+                None => dest,
+            };
+            ids.push(doc.add_object(dictionary! {"Title" => Object::string_literal(title), "Parent" => root_id, "Dest" => dest}));
+        }
+        for (i, &id) in ids.iter().enumerate() {
+            let item = doc.get_dictionary_mut(id).unwrap();
+            if i > 0 {
+                item.set("Prev", ids[i - 1]);
+            }
+            if let Some(&next) = ids.get(i + 1) {
+                item.set("Next", next);
+            }
+        }
+        doc.objects.insert(
+            root_id,
+            Object::Dictionary(dictionary! {"Type" => "Outlines", "First" => ids[0], "Last" => ids[ids.len() - 1], "Count" => ids.len() as i64}),
+        );
+        let catalog = doc.catalog_mut().unwrap();
+        catalog.set("Outlines", root_id);
+        if !named.is_empty() {
+            catalog.set("Names", dictionary! {"Dests" => dictionary! {"Names" => named}});
+        }
+    }
+
+    #[test]
+    fn merge_keeps_every_bookmark() {
+        let dir = test_dir("merge_outlines");
+        let mut first = sample_doc(2);
+        add_outline(&mut first, &[("A1", 1), ("A2", 2)]);
+        let mut second = sample_doc(3);
+        add_outline(&mut second, &[("B1", 1), ("@B3", 3)]);
+        let paths = vec![save_doc(&mut first, &dir.join("a.pdf")), save_doc(&mut second, &dir.join("b.pdf"))];
+        let output = path_to_string(&dir.join("merged.pdf"));
+        merge_pdfs(paths, output.clone(), None, None, None, None, None, None).unwrap();
+
+        let doc = load_pdf(&output).unwrap();
+        let page_numbers: std::collections::HashMap<lopdf::ObjectId, u32> =
+            doc.get_pages().into_iter().map(|(n, id)| (id, n)).collect();
+        let root = doc.catalog().unwrap().get(b"Outlines").unwrap().as_reference().unwrap();
+        let mut bookmarks = Vec::new();
+        let mut next = doc.get_dictionary(root).unwrap().get(b"First").and_then(|o| o.as_reference()).ok();
+        while let Some(id) = next {
+            let item = doc.get_dictionary(id).unwrap();
+            let title = decode_pdf_text(item.get(b"Title").unwrap());
+            let dest = item.get(b"Dest").and_then(|o| o.as_array()).unwrap();
+            bookmarks.push((title, page_numbers[&dest[0].as_reference().unwrap()]));
+            next = item.get(b"Next").and_then(|o| o.as_reference()).ok();
+        }
+        let expected = [("A1", 1), ("A2", 2), ("B1", 3), ("@B3", 5)].map(|(t, p)| (t.to_string(), p));
+        assert_eq!(bookmarks, expected);
+    }
+
+    #[test]
+    fn merge_capped_warns_about_oversized_pages() {
+        let dir = test_dir("merge_capped");
+        let paths = vec![save_doc(&mut sample_doc(2), &dir.join("a.pdf")), save_doc(&mut sample_doc(1), &dir.join("b.pdf"))];
+        let out_dir = dir.join("out");
+        fs::create_dir(&out_dir).unwrap();
+        let result = merge_capped_blocking(paths, 1, "bundle".to_string(), path_to_string(&out_dir)).unwrap();
+
+        let names: Vec<_> = result.parts.iter().map(|part| Path::new(&part.path).file_name().unwrap().to_owned()).collect();
+        assert_eq!(names, ["bundle_part1.pdf", "bundle_part2.pdf", "bundle_part3.pdf"]);
+        assert_eq!(result.warnings.len(), 3, "{:?}", result.warnings);
+        assert!(result.warnings[2].starts_with("Page 3 alone is"), "{:?}", result.warnings);
+    }
+
+    #[test]
+    fn reverse_pages_puts_the_last_page_first() {
+        let dir = test_dir("reverse_pages");
+        let input = save_doc(&mut sample_doc(4), &dir.join("in.pdf"));
+        let output = path_to_string(&dir.join("out.pdf"));
+        reverse_pages(input.clone(), output.clone()).unwrap();
+
+        let content = |path: &str, page: u32| {
+            let doc = load_pdf(path).unwrap();
+            doc.get_page_content(doc.get_pages()[&page]).unwrap()
+        };
+        for page in 1..=4 {
+            assert_eq!(content(&output, page), content(&input, 5 - page), "page {}", page);
+        }
+        assert_ne!(content(&output, 1), content(&input, 1));
+    }
+
+    #[test]
+    fn overlay_keeps_base_text_and_draws_the_form() {
+        let dir = test_dir("overlay");
+        let mut base = sample_doc(2);
+        // Page 2 is rotated and has its content split over an array.
+        let page_2 = base.get_pages()[&2];
+        let first = base.get_dictionary(page_2).unwrap().get(b"Contents").unwrap().clone();
+        let second = base.add_object(lopdf::Stream::new(dictionary! {}, b"0 0 m 10 10 l S".to_vec()));
+        let page = base.get_dictionary_mut(page_2).unwrap();
+        page.set("Contents", vec![first, Object::Reference(second)]);
+        page.set("Rotate", 90);
+        let base = save_doc(&mut base, &dir.join("base.pdf"));
+        let mut letterhead = sample_doc(1);
+        let letterhead_page = letterhead.get_pages()[&1];
+        let content_id = letterhead.get_dictionary(letterhead_page).unwrap().get(b"Contents").unwrap().as_reference().unwrap();
+        letterhead
+            .get_object_mut(content_id)
+            .and_then(|o| o.as_stream_mut())
+            .unwrap()
+            .set_content(b"BT /F1 12 Tf 20 20 Td (Letterhead) Tj ET".to_vec());
+        let letterhead = save_doc(&mut letterhead, &dir.join("letterhead.pdf"));
+
+        for on_top in [false, true] {
+            let output = path_to_string(&dir.join(format!("out-{}.pdf", on_top)));
+            overlay_pdf(base.clone(), letterhead.clone(), output.clone(), on_top).unwrap();
+            let doc = load_pdf(&output).unwrap();
+            for (number, page_id) in doc.get_pages() {
+                let text = extract_page_text(&doc, page_id).unwrap();
+                assert!(text.contains(&format!("Page {}", number)), "page {}: {:?}", number, text);
+
+                let content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).to_string();
+                let draw = content.find("/PPOv1 Do").unwrap_or_else(|| panic!("no overlay on page {}: {}", number, content));
+                assert_eq!(draw > content.find("(Page").unwrap(), on_top, "{}", content);
+                let resources = inherited_attr(&doc, page_id, b"Resources").unwrap();
+                let resources = doc.dereference(resources).unwrap().1.as_dict().unwrap();
+                let form = resources.get(b"XObject").and_then(|o| o.as_dict()).unwrap().get(b"PPOv1").unwrap();
+                let form = doc.dereference(form).unwrap().1.as_stream().unwrap();
+                assert_eq!(form.dict.get(b"Subtype").unwrap().as_name().unwrap(), b"Form");
+                assert!(String::from_utf8_lossy(&form.get_plain_content().unwrap()).contains("(Letterhead)"));
+            }
+        }
+    }
+
+    #[test]
+    fn unreadable_xmp_is_kept_and_rejected() {
+        let dir = test_dir("bad-xmp");
+        let mut doc = sample_doc(1);
+        let packet = b"<x:xmpmeta><pdfaid:part>2</pdfaid:part>".to_vec();
+        let xmp_id = doc.add_object(lopdf::Stream::new(dictionary! {"Type" => "Metadata", "Subtype" => "XML"}, packet));
+        doc.catalog_mut().unwrap().set("Metadata", xmp_id);
+        let source = save_doc(&mut doc, &dir.join("doc.pdf"));
+        let output = dir.join("out.pdf");
+
+        let fields = [("Title".to_string(), "New".to_string())].into_iter().collect();
+        let result = set_metadata(source.clone(), fields, path_to_string(&output));
+        assert!(matches!(result, Err(AppError::Validation(_))), "{:?}", result.err());
+        assert!(!output.exists());
+
+        let doc = load_pdf(&source).unwrap();
+        assert!(doc.catalog().unwrap().get(b"Metadata").is_ok());
+    }
+
+    #[test]
+    fn same_file_sees_through_path_spelling() {
+        let dir = test_dir("same_file");
+        let file = dir.join("a.pdf");
+        fs::write(&file, b"%PDF-1.4").unwrap();
+        assert!(is_same_file(&file, &dir.join(".").join("a.pdf")));
+        assert!(!is_same_file(&file, &dir.join("b.pdf")));
+        #[cfg(unix)]
+        {
+            let link = dir.join("link.pdf");
+            std::os::unix::fs::symlink(&file, &link).unwrap();
+            assert!(is_same_file(&file, &link));
+        }
+    }
+
+    #[test]
+    fn page_labels_survive_huge_starts() {
+        let mut doc = sample_doc(3);
+        let labels = dictionary! {"Nums" => vec![
+            0.into(), dictionary! {"S" => "A", "St" => 4_000_000_000i64}.into(),
+            1.into(), dictionary! {"S" => "D", "St" => i64::from(u32::MAX)}.into(),
+            2.into(), dictionary! {"S" => "r", "St" => -7}.into(),
+        ]};
+        doc.catalog_mut().unwrap().set("PageLabels", labels);
+        assert_eq!(page_labels(&doc, 3), ["4000000000", "4294967295", "i"]);
+    }
+
+    #[test]
+    fn print_report_follows_what_compression_did() {
+        let dir = test_dir("print_report");
+        let mut doc = sample_doc(1);
+        let image = |space: &str, pixels: Vec<u8>, size: i64| {
+            let dict = dictionary! {
+                "Type" => "XObject", "Subtype" => "Image", "Width" => size, "Height" => size,
+                "ColorSpace" => space, "BitsPerComponent" => 8,
+            };
+            lopdf::Stream::new(dict, pixels)
+        };
+        let settings = PrintSettings { max_image_dpi: None, image_quality: None, cmyk: None };
+        let page = doc.get_pages()[&1];
+        let cmyk = doc.add_object(image("DeviceCMYK", vec![40; 8 * 8 * 4], 8));
+        doc.get_dictionary_mut(page).unwrap().set("Resources", dictionary! {"XObject" => dictionary! {"Im1" => cmyk}});
+        let source = save_doc(&mut doc, &dir.join("cmyk.pdf"));
+        let output = path_to_string(&dir.join("cmyk-out.pdf"));
+        let report = prepare_for_print_blocking(source, settings.clone(), output).unwrap();
+        assert!(report.applied.is_empty(), "{:?}", report.applied);
+        assert!(report.issues.iter().all(|issue| !issue.contains("resampled")), "{:?}", report.issues);
+
+        // A raw gradient is photographic, so it comes back as a JPEG.
+        let gradient = (0..64u32 * 64).flat_map(|i| [(i % 64 * 4) as u8, (i / 64 * 4) as u8, (i % 64 + i / 64) as u8]);
+        let photo = doc.add_object(image("DeviceRGB", gradient.collect(), 64));
+        doc.get_dictionary_mut(page).unwrap().set("Resources", dictionary! {
+            "XObject" => dictionary! {"Im1" => cmyk, "Im2" => photo},
+        });
+        let source = save_doc(&mut doc, &dir.join("photo.pdf"));
+        let output = path_to_string(&dir.join("photo-out.pdf"));
+        let report = prepare_for_print_blocking(source, settings, output).unwrap();
+        assert_eq!(
+            report.applied,
+            ["Recompressed 1 image(s), downsampling any above 300 dpi", "Re-encoded 1 losslessly compressed image(s) as JPEG"]
+        );
+    }
+
+    #[test]
+    fn winansi_literals_use_the_high_range() {
+        assert_eq!(pdf_literal_winansi("“5€” – (Ü)"), b"\x935\x80\x94 \x96 \\(\xDC\\)");
+        assert_eq!(pdf_literal_winansi("Ж\u{85}"), b"??");
+    }
+
+    #[test]
+    fn dropped_links_are_reported() {
+        let dir = test_dir("dropped_links");
+        let mut doc = sample_doc(3);
+        let pages = doc.get_pages();
+        let link = dictionary! {
+            "Type" => "Annot", "Subtype" => "Link", "Rect" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+            "Dest" => vec![pages[&2].into(), "Fit".into()],
+        };
+        let link = doc.add_object(link);
+        doc.get_dictionary_mut(pages[&1]).unwrap().set("Annots", vec![link.into()]);
+        let source = save_doc(&mut doc, &dir.join("doc.pdf"));
+
+        let result = split_one_per_page(&source, &dir).unwrap();
+        assert_eq!(result.warnings, ["Part 1 had 1 link to pages outside it removed."]);
+
+        let output = path_to_string(&dir.join("organised.pdf"));
+        let actions = vec![PageAction::Existing { page_number: 1 }, PageAction::Existing { page_number: 3 }];
+        let result = apply_pdf_organisation(source, actions, output.clone(), None).unwrap();
+        assert_eq!(result.warnings, ["Removed 1 link to deleted pages."]);
+        assert_eq!(page_texts(&output), ["Page 1", "Page 3"]);
+    }
+
+    #[test]
+    fn aes_256_declares_the_adobe_extension() {
+        let extension_level = |algorithm| {
+            let mut doc = sample_doc(1);
+            encrypt_document(&mut doc, "user", None, algorithm, &PdfPermissions::default()).unwrap();
+            let extensions = doc.catalog().unwrap().get(b"Extensions").and_then(|o| o.as_dict()).ok().cloned();
+            extensions.map(|e| e.get(b"ADBE").unwrap().as_dict().unwrap().get(b"ExtensionLevel").unwrap().as_i64().unwrap())
+        };
+        assert_eq!(extension_level(EncryptionAlgorithm::Aes256), Some(8));
+        assert_eq!(extension_level(EncryptionAlgorithm::Aes128), None);
+    }
+
+    #[test]
+    fn stamp_image_lands_on_selected_pages_only() {
+        let dir = test_dir("stamp_image");
+        let source = save_doc(&mut sample_doc(3), &dir.join("doc.pdf"));
+        let logo = dir.join("logo.png");
+        image::RgbImage::from_pixel(4, 4, image::Rgb([200, 0, 0])).save(&logo).unwrap();
+        let output = path_to_string(&dir.join("stamped.pdf"));
+        let placement = ImagePlacement { pages: Some(PageSelection::Spec("1,3".to_string())), ..Default::default() };
+        stamp_image(source.clone(), path_to_string(&logo), output.clone(), Some(placement)).unwrap();
+
+        assert_eq!(page_texts(&output), page_texts(&source));
+        let doc = load_pdf(&output).unwrap();
+        for (number, page_id) in doc.get_pages() {
+            let content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).to_string();
+            let resources = inherited_attr(&doc, page_id, b"Resources").unwrap();
+            let resources = doc.dereference(resources).unwrap().1.as_dict().unwrap();
+            let image = resources.get(b"XObject").and_then(|o| o.as_dict()).and_then(|x| x.get(b"PPIm1"));
+            let stamped = number != 2;
+            assert_eq!(content.contains("/PPIm1 Do"), stamped, "page {}: {}", number, content);
+            assert_eq!(image.is_ok(), stamped, "page {}", number);
+            if let Ok(image) = image {
+                let image = doc.dereference(image).unwrap().1.as_stream().unwrap();
+                assert_eq!(image.dict.get(b"Subtype").unwrap().as_name().unwrap(), b"Image");
+            }
+        }
+    }
+
+    #[test]
+    fn one_decoder_serves_extraction_and_compression() {
+        let doc = Document::with_version("1.5");
+        let image = |space: Object, bits: i64, extra: &[(&str, Object)], data: Vec<u8>| {
+            let mut dict = dictionary! {"Subtype" => "Image", "Width" => 8, "Height" => 1, "ColorSpace" => space, "BitsPerComponent" => bits};
+            for (key, value) in extra {
+                dict.set(*key, value.clone());
+            }
+            decode_image_xobject(&doc, &lopdf::Stream::new(dict, data)).unwrap()
+        };
+
+        let bilevel = image("DeviceGray".into(), 1, &[], vec![0b1010_0000]);
+        assert!(bilevel.faithful && !bilevel.jpeg);
+        assert_eq!(bilevel.pixels.to_luma8().into_raw(), [255, 0, 255, 0, 0, 0, 0, 0]);
+
+        let inverted = image("DeviceGray".into(), 1, &[("Decode", vec![1.into(), 0.into()].into())], vec![0b1010_0000]);
+        assert!(!inverted.faithful);
+        assert_eq!(inverted.pixels.to_luma8().into_raw(), [0, 255, 0, 255, 255, 255, 255, 255]);
+
+        let palette = Object::String(vec![255, 0, 0, 0, 0, 255], lopdf::StringFormat::Hexadecimal);
+        let indexed = image(vec!["Indexed".into(), "DeviceRGB".into(), 1.into(), palette].into(), 8, &[], vec![0, 1, 0, 1, 0, 1, 0, 1]);
+        assert!(!indexed.faithful);
+        assert_eq!(indexed.pixels.to_rgb8().get_pixel(1, 0).0, [0, 0, 255]);
+    }
+
+    #[test]
+    fn size_capped_parts_are_measured_as_written() {
+        let dir = test_dir("split_size_finished");
+        let mut doc = sample_doc(8);
+        doc.trailer.set("Info", dictionary! { "Title" => Object::string_literal("A long enough title") });
+        let source = save_doc(&mut doc, &dir.join("doc.pdf"));
+        let doc = load_pdf(&source).unwrap();
+        let pages = doc.get_pages();
+        let two_pages = SplitPart { start: 1, end: 2, label: None, prepared: None };
+        let max_bytes = PartOptions::default().prepare(&doc, &pages, &two_pages, 1).unwrap().bytes.len() as u64;
+
+        let mode = SplitMode::BySize { max_bytes };
+        let preview = split_pdf_preview(source.clone(), mode.clone()).unwrap();
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        let result = split_pdf_blocking(
+            source,
+            Some(path_to_string(&out)),
+            mode,
+            None,
+            Some(true),
+            Some("2.0".to_string()),
+            None,
+            |_| {},
+        )
+        .unwrap();
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        for part in &result.parts {
+            assert!(part.size <= max_bytes, "{} is {} bytes", part.path, part.size);
+            assert_eq!(fs::metadata(&part.path).unwrap().len(), part.size);
+        }
+        assert!(preview.parts.iter().all(|p| p.estimated_size.is_some_and(|size| size <= max_bytes)));
+    }
+
+    #[test]
+    fn finishing_leaves_a_newer_registration_alone() {
+        let operations = OperationRegistry::default();
+        let first = operations.register("split");
+        let second = operations.register("split");
+        operations.finish("split", &first);
+        assert!(operations.cancel("split"));
+        assert!(second.load(std::sync::atomic::Ordering::Relaxed));
+        assert!(!first.load(std::sync::atomic::Ordering::Relaxed));
+        operations.finish("split", &second);
+        assert!(!operations.cancel("split"));
+    }
+
+    #[test]
+    fn rotated_square_pages_stay_portrait() {
+        let dir = test_dir("organiser_square");
+        let mut doc = sample_doc(3);
+        let page_ids: Vec<_> = doc.get_pages().into_values().collect();
+        for (&id, (size, rotate)) in page_ids.iter().zip([((500, 500), 90), ((595, 842), 90), ((842, 595), 270)]) {
+            let page = doc.get_object_mut(id).and_then(Object::as_dict_mut).unwrap();
+            page.set("MediaBox", vec![0.into(), 0.into(), size.0.into(), size.1.into()]);
+            page.set("Rotate", rotate);
+        }
+        let source = save_doc(&mut doc, &dir.join("doc.pdf"));
+        let landscape: Vec<_> = get_organiser_pdf_metadata(source)
+            .unwrap()
+            .iter()
+            .map(|page| (page.is_landscape, page.display_landscape))
+            .collect();
+        assert_eq!(landscape, [(false, false), (false, true), (true, false)]);
+    }
+
+    #[test]
+    fn page_box_check_ignores_boxes_on_the_page_tree() {
+        let dir = test_dir("boxes_not_inherited");
+        let mut doc = sample_doc(2);
+        let root_pages = doc.catalog().unwrap().get(b"Pages").unwrap().as_reference().unwrap();
+        // Outside the MediaBox, but on the Pages node, where it doesn't apply.
+        doc.get_object_mut(root_pages)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("TrimBox", vec![0.into(), 0.into(), 900.into(), 900.into()]);
+        let source = save_doc(&mut doc, &dir.join("doc.pdf"));
+        assert!(check_page_boxes(source).unwrap().is_empty());
+    }
+
+    #[test]
+    fn metadata_dates_accept_pdf_dates_as_given() {
+        assert_eq!(
+            parse_pdf_date("D:20240305143000+01'00'"),
+            Some(("D:20240305143000+01'00'".to_string(), "2024-03-05T14:30:00+01:00".to_string()))
+        );
+        assert_eq!(parse_pdf_date("D:20240305143000Z").unwrap().1, "2024-03-05T14:30:00Z");
+        assert_eq!(parse_pdf_date("D:202403").unwrap().1, "2024-03");
+        assert_eq!(parse_pdf_date("D:202403051430-05'30").unwrap().1, "2024-03-05T14:30-05:30");
+        for bad in ["D:20241305", "D:2024030", "D:20240305143000+1'00'", "D:20240305143000X", "20240305"] {
+            assert_eq!(parse_pdf_date(bad), None, "{}", bad);
+        }
+
+        let dir = test_dir("metadata_pdf_date");
+        let source = save_doc(&mut sample_doc(1), &dir.join("doc.pdf"));
+        let output = path_to_string(&dir.join("out.pdf"));
+        let fields = std::collections::HashMap::from([("ModDate".to_string(), "D:20240305143000+01'00'".to_string())]);
+        set_pdf_metadata(source, fields, output.clone()).unwrap();
+        let doc = load_pdf(&output).unwrap();
+        let info = doc.trailer.get(b"Info").and_then(|o| o.as_reference()).and_then(|id| doc.get_dictionary(id)).unwrap();
+        assert_eq!(info.get(b"ModDate").unwrap().as_str().unwrap(), b"D:20240305143000+01'00'");
+    }
+
+    #[test]
+    fn start_xref_ignores_trailing_garbage() {
+        // Digits after the offset used to be glued onto it.
+        assert_eq!(find_start_xref(b"trailer\nstartxref\n1234\n% saved 2024-03-05\n%%EOF\n"), Some(1234));
+        assert_eq!(find_start_xref(b"startxref\r\n% offset follows\r\n  987 x5\r\n%%EOF"), Some(987));
+        // A tail padded past the old 128-byte window.
+        let mut padded = b"startxref\n42\n".to_vec();
+        padded.extend(std::iter::repeat_n(b' ', 1000));
+        padded.extend(b"\n%%EOF\n");
+        assert_eq!(find_start_xref(&padded), Some(42));
+        assert_eq!(find_start_xref(b"startxref\n% no offset\n%%EOF"), None);
+    }
+}
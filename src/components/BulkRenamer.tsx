@@ -55,7 +55,8 @@ export function BulkRenamer({
         return () => clearTimeout(t);
     }, [updatePreview]);
 
-    const canRename = template.trim().length > 0 && preview?.placeholder_found && files.length > 0;
+    const hasCollisions = !!preview && preview.internal_collisions.length > 0;
+    const canRename = template.trim().length > 0 && preview?.placeholder_found && files.length > 0 && !hasCollisions;
     const hasOverwriteWarning = preview && preview.overwrite_warnings.length > 0;
 
     const handleRename = useCallback(async () => {
@@ -74,9 +75,12 @@ export function BulkRenamer({
             });
             if (result.failed.length > 0) {
                 const msg = result.failed.map((f) => `${f.path}: ${f.error}`).join("\n");
+                const summary = result.rolled_back
+                    ? "A rename failed, so the files already renamed were changed back."
+                    : "No files were renamed.";
                 setStatus({
                     type: "error",
-                    text: `Renamed ${result.renamed} files. Failures:\n${msg}`,
+                    text: `${summary} Failures:\n${msg}`,
                 });
             } else {
                 setStatus({
@@ -115,7 +119,7 @@ export function BulkRenamer({
                     aria-describedby="pattern-hint"
                 />
                 <p id="pattern-hint" className="hint">
-                    Use # for one digit, ## for two (01, 02), ### for three (001, 002), or {"{version}"} for two digits. Extension is kept from each file.
+                    Use # for one digit, ## for two (01, 02), ### for three (001, 002), or {"{version}"} for two digits. {"{original}"} inserts the current name and {"{date}"} the modification date (YYYY-MM-DD). Extension is kept from each file.
                 </p>
             </section>
 
@@ -166,6 +170,9 @@ export function BulkRenamer({
                         <section className="section">
                             <div className="preview-box">
                                 <span className="label">Preview (first 3 names)</span>
+                                {preview.tokens.length > 0 && (
+                                    <p className="hint">Tokens: {preview.tokens.join(", ")}</p>
+                                )}
                                 <ul>
                                     {preview.preview_names.map((name, i) => (
                                         <li key={i}>{name}</li>
@@ -190,6 +197,22 @@ export function BulkRenamer({
                             </div>
                         </section>
                     )}
+
+                    {preview && hasCollisions && (
+                        <section className="section">
+                            <div className="warning-box">
+                                <strong>Warning:</strong> More than one file would be renamed to:
+                                <ul style={{ marginTop: 6, paddingLeft: 18 }}>
+                                    {preview.internal_collisions.slice(0, 5).map((p, i) => (
+                                        <li key={i}>{p}</li>
+                                    ))}
+                                    {preview.internal_collisions.length > 5 && (
+                                        <li>… and {preview.internal_collisions.length - 5} more</li>
+                                    )}
+                                </ul>
+                            </div>
+                        </section>
+                    )}
                 </>
             )}
 
@@ -166,7 +166,7 @@ export function PageBoxInspector({
                                 {boxData.map((page, idx) => (
                                     <tr key={page.page_number} style={{ borderBottom: idx < boxData.length - 1 ? '1px solid var(--border)' : 'none' }}>
                                         <td style={{ padding: '12px 16px' }}>{page.page_number}</td>
-                                        <td style={{ padding: '12px 16px', fontFamily: 'monospace' }}>{formatBoxString(page.media_box, selectedUnit)}</td>
+                                        <td style={{ padding: '12px 16px', fontFamily: 'monospace' }}>{page.missing_mediabox ? 'Missing' : formatBoxString(page.media_box, selectedUnit)}</td>
                                         <td style={{ padding: '12px 16px', fontFamily: 'monospace' }}>{formatBoxString(page.crop_box, selectedUnit)}</td>
                                         <td style={{ padding: '12px 16px', fontFamily: 'monospace' }}>{formatBoxString(page.trim_box, selectedUnit)}</td>
                                         <td style={{ padding: '12px 16px', fontFamily: 'monospace' }}>{formatBoxString(page.bleed_box, selectedUnit)}</td>
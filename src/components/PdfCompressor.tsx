@@ -25,6 +25,17 @@ interface FileStats {
 
 type TabId = "images" | "fonts" | "metadata" | "structure";
 
+// Display names for the steps in CompressionResult.breakdown
+const STEP_LABELS: Record<string, string> = {
+    images: "Images",
+    metadata: "Metadata",
+    thumbnails: "Thumbnails",
+    application_data: "Application data",
+    structure_tree: "Structure tree",
+    form_fields: "Form fields",
+    annotations: "Annotations",
+};
+
 export function PdfCompressor({
     files,
     onPickFiles,
@@ -107,12 +118,24 @@ export function PdfCompressor({
                         ...prev[file.path],
                         compressedSize: result.compressed_size,
                         originalSize: result.original_size,
-                        savingPercent: Math.round((1 - (result.compressed_size / result.original_size)) * 100),
+                        savingPercent: Math.round((1 - result.ratio) * 100),
                         isCompressing: false,
                         done: true
                     }
                 }));
-                setStatus({ type: "success", text: "Compression complete!" });
+                const images = result.images_recompressed > 0
+                    ? ` ${result.images_recompressed} image${result.images_recompressed === 1 ? "" : "s"} recompressed.`
+                    : "";
+                const savings = result.breakdown
+                    .filter((s) => s.bytes_saved > 0)
+                    .map((s) => `${STEP_LABELS[s.step] ?? s.step}: ${formatSize(s.bytes_saved)} saved`)
+                    .join(", ");
+                const lines = savings ? [savings, ...result.warnings] : result.warnings;
+                setStatus(result.kept_original
+                    ? { type: "info", text: "File is already optimized — kept the original." }
+                    : result.warnings.length > 0
+                        ? { type: "info", text: [`Compression complete.${images}`, ...lines].join("\n") }
+                        : { type: "success", text: [`Compression complete!${images}`, ...lines].join("\n") });
             }
         } catch (e) {
             setStatus({ type: "error", text: String(e) });
@@ -1,7 +1,7 @@
 import React, { useState, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { save } from "@tauri-apps/plugin-dialog";
-import { FileEntry } from "../types";
+import { FileEntry, MergeResult } from "../types";
 
 interface PdfMergerProps {
     files: FileEntry[];
@@ -34,6 +34,7 @@ export function PdfMerger({
     setFiles,
 }: PdfMergerProps) {
     const [draggedIndex, setDraggedIndex] = useState<number | null>(null);
+    const [fileBookmarks, setFileBookmarks] = useState(false);
 
     const handleMerge = useCallback(async () => {
         if (files.length < 2) {
@@ -51,17 +52,22 @@ export function PdfMerger({
 
             setStatus({ type: "info", text: "Merging PDFs..." });
 
-            await invoke("merge_pdfs", {
+            const result = await invoke<MergeResult>(fileBookmarks ? "merge_pdfs_with_bookmarks" : "merge_pdfs", {
                 paths: files.map((f) => f.path),
                 outputPath,
+                // The save dialog has already confirmed replacing an existing file.
+                overwrite: true,
             });
 
-            setStatus({ type: "success", text: `Successfully merged ${files.length} files into:\n${outputPath}` });
+            const text = `Successfully merged ${files.length} files (${result.page_count} pages) into:\n${result.output_path}`;
+            setStatus(result.warnings.length > 0
+                ? { type: "info", text: [text, ...result.warnings].join("\n") }
+                : { type: "success", text });
             onMergeComplete();
         } catch (e) {
             setStatus({ type: "error", text: String(e) });
         }
-    }, [files, setStatus, onMergeComplete]);
+    }, [files, fileBookmarks, setStatus, onMergeComplete]);
 
     const handleDrop = (e: React.DragEvent) => {
         e.preventDefault();
@@ -146,6 +152,14 @@ export function PdfMerger({
                             </li>
                         ))}
                     </ul>
+                    <label className="checkbox-label mt-12">
+                        <input
+                            type="checkbox"
+                            checked={fileBookmarks}
+                            onChange={(e) => setFileBookmarks(e.target.checked)}
+                        />
+                        Add a bookmark for each file
+                    </label>
                 </section>
             )}
 
@@ -53,6 +53,8 @@ export function PdfMixer({
             await invoke("mix_pdfs", {
                 paths: files.map((f) => f.path),
                 outputPath,
+                // The save dialog has already confirmed replacing an existing file.
+                overwrite: true,
             });
 
             setStatus({ type: "success", text: `Successfully mixed ${files.length} files into:\n${outputPath}` });
@@ -1,7 +1,7 @@
 import { useState, useEffect, useRef } from 'react';
 import { invoke } from "@tauri-apps/api/core";
 import { save } from "@tauri-apps/plugin-dialog";
-import { FileEntry, PageAction, PdfPage } from '../types';
+import { FileEntry, OrganiseResult, PageAction, PdfPage } from '../types';
 import { PdfThumbnail } from './PdfThumbnail';
 import {
     DndContext,
@@ -404,13 +404,15 @@ export default function PdfOrganiser({
                 p.type === "existing" ? { type: "existing", page_number: p.page_number! } : { type: "blank" }
             );
 
-            await invoke("apply_pdf_organisation", {
+            const result = await invoke<OrganiseResult>("apply_pdf_organisation", {
                 inputPath: file.path,
                 actions,
                 outputPath,
             });
 
-            setStatus({ type: "success", text: "Document saved successfully!" });
+            setStatus(result.warnings.length > 0
+                ? { type: "info", text: ["Document saved.", ...result.warnings].join("\n") }
+                : { type: "success", text: "Document saved successfully!" });
         } catch (e) {
             setStatus({ type: "error", text: `Failed to save: ${e}` });
         } finally {
@@ -54,6 +54,19 @@ export function PdfPropertyViewer({
         }
     };
 
+    // Collapse labels into runs of the same numbering style, e.g. "i–iv, 1–120".
+    const summarizeLabels = (labels: string[]) => {
+        const kind = (label: string) =>
+            /^\d+$/.test(label) ? "decimal" : /^[ivxlcdm]+$/.test(label) ? "roman" : /^[IVXLCDM]+$/.test(label) ? "ROMAN" : label.replace(/\d+$/, "");
+        const runs: string[][] = [];
+        for (const label of labels) {
+            const run = runs[runs.length - 1];
+            if (run && kind(run[0]) === kind(label)) run.push(label);
+            else runs.push([label]);
+        }
+        return runs.map(run => run.length > 1 ? `${run[0]}–${run[run.length - 1]}` : run[0]).join(", ");
+    };
+
     return (
         <div className="tool-container property-viewer">
             <div className="tool-header">
@@ -95,7 +108,9 @@ export function PdfPropertyViewer({
                                                 <div className="prop-row"><span>Page Size:</span> <span>{formatDimension(properties[file.path].page_width)} × {formatDimension(properties[file.path].page_height)}</span></div>
                                                 <div className="prop-row"><span>Colorspace:</span> <span className="colorspace-tag">{properties[file.path].colorspace}</span></div>
                                                 <div className="prop-row"><span>Doc PPI:</span> <span>{properties[file.path].doc_dpi}</span></div>
-                                                <div className="prop-row"><span>Version:</span> <span>PDF {properties[file.path].version}</span></div>
+                                                <div className="prop-row"><span>Version:</span> <span>PDF {properties[file.path].version}{properties[file.path].version !== properties[file.path].header_version && ` (header says ${properties[file.path].header_version})`}</span></div>
+                                                {properties[file.path].page_labels.length > 0 && <div className="prop-row"><span>Page Labels:</span> <span>{summarizeLabels(properties[file.path].page_labels)}</span></div>}
+                                                <div className="prop-row"><span>Language:</span> <span>{properties[file.path].language ?? "Not set"}</span></div>
                                                 <div className="prop-row"><span>Security:</span> <span>{properties[file.path].encrypted ? "🔒 Encrypted" : "🔓 None"}</span></div>
                                             </div>
 
@@ -119,6 +134,12 @@ export function PdfPropertyViewer({
                                                             {new Set(properties[file.path].image_dpi).size > 5 && " ..."}
                                                         </span>
                                                     </div>
+                                                    {properties[file.path].image_dpi_summary && (
+                                                        <div className="prop-row">
+                                                            <span>Min / Typical / Max:</span>
+                                                            <span>{properties[file.path].image_dpi_summary?.min} / {properties[file.path].image_dpi_summary?.typical} / {properties[file.path].image_dpi_summary?.max}</span>
+                                                        </div>
+                                                    )}
                                                 </div>
                                             )}
 
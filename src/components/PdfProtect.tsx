@@ -1,7 +1,7 @@
 import { useState, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { save } from "@tauri-apps/plugin-dialog";
-import { FileEntry, StatusMessage } from "../types";
+import { EncryptionAlgorithm, FileEntry, StatusMessage } from "../types";
 
 interface PdfProtectProps {
     files: FileEntry[];
@@ -28,6 +28,7 @@ export function PdfProtect({
 }: PdfProtectProps) {
     const [password, setPassword] = useState("");
     const [confirmPassword, setConfirmPassword] = useState("");
+    const [algorithm, setAlgorithm] = useState<EncryptionAlgorithm>("aes_256");
 
     const activeFile = files.length > 0 ? files[0] : null;
 
@@ -62,6 +63,7 @@ export function PdfProtect({
                 userPassword: password,
                 ownerPassword: null,
                 outputPath,
+                algorithm,
             });
 
             setStatus({
@@ -73,7 +75,7 @@ export function PdfProtect({
         } catch (e) {
             setStatus({ type: "error", text: String(e) });
         }
-    }, [activeFile, password, confirmPassword, setStatus]);
+    }, [activeFile, password, confirmPassword, algorithm, setStatus]);
 
     const passwordsMatch = password === confirmPassword;
     const canProtect = password && confirmPassword && passwordsMatch;
@@ -146,6 +148,24 @@ export function PdfProtect({
                             Passwords do not match
                         </p>
                     )}
+
+                    <label
+                        htmlFor="protect-algorithm"
+                        className="label"
+                        style={{ marginTop: 12 }}
+                    >
+                        Encryption
+                    </label>
+                    <select
+                        id="protect-algorithm"
+                        className="input"
+                        value={algorithm}
+                        onChange={(e) => setAlgorithm(e.target.value as EncryptionAlgorithm)}
+                    >
+                        <option value="aes_256">AES-256 (recommended)</option>
+                        <option value="aes_128">AES-128</option>
+                        <option value="rc4_128">RC4 128-bit (old readers only)</option>
+                    </select>
                 </section>
             )}
 
@@ -1,8 +1,8 @@
-import React, { useCallback, useState, useEffect } from "react";
+import React, { useCallback, useState, useEffect, useMemo, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { open } from "@tauri-apps/plugin-dialog";
 import { listen } from "@tauri-apps/api/event";
-import { FileEntry, SplitPreviewResult } from "../types";
+import { FileEntry, SplitPreviewResult, SplitResult } from "../types";
 
 interface PdfSplitterProps {
     files: FileEntry[];
@@ -31,21 +31,30 @@ export function PdfSplitter({
     status,
     onSplitComplete,
 }: PdfSplitterProps) {
-    const [splitMode, setSplitMode] = useState<"every_n" | "one_per_page">("every_n");
+    const [splitMode, setSplitMode] = useState<"every_n" | "one_per_page" | "ranges" | "bookmarks">("every_n");
     const [splitEveryN, setSplitEveryN] = useState(1);
+    const [splitOverlap, setSplitOverlap] = useState(0);
+    const [splitRanges, setSplitRanges] = useState("");
     const [outputDir, setOutputDir] = useState<string | null>(null);
     const [splitPreviews, setSplitPreviews] = useState<SplitPreviewResult[]>([]);
     const [progress, setProgress] = useState<{ current: number; total: number } | null>(null);
+    // Id of the file being split, so it can be cancelled. Each file gets its
+    // own; a cancel that lands between files stops the loop instead.
+    const operationId = useRef<string | null>(null);
+    const cancelRequested = useRef(false);
+
+    const mode = useMemo(() => {
+        if (splitMode === "one_per_page") return { mode: "one_per_page" as const };
+        if (splitMode === "ranges") return { mode: "ranges" as const, ranges: [splitRanges] };
+        if (splitMode === "bookmarks") return { mode: "bookmarks" as const };
+        return { mode: "every_n" as const, n: Math.max(1, splitEveryN), overlap: Math.min(splitOverlap, splitEveryN - 1) };
+    }, [splitMode, splitEveryN, splitOverlap, splitRanges]);
 
     useEffect(() => {
         if (files.length === 0) {
             setSplitPreviews([]);
             return;
         }
-        const mode =
-            splitMode === "one_per_page"
-                ? { mode: "one_per_page" as const }
-                : { mode: "every_n" as const, n: Math.max(1, splitEveryN) };
         let cancelled = false;
         (async () => {
             const results: SplitPreviewResult[] = [];
@@ -80,15 +89,11 @@ export function PdfSplitter({
         return () => {
             cancelled = true;
         };
-    }, [files, splitMode, splitEveryN]);
+    }, [files, mode]);
 
     const handleSplit = useCallback(async () => {
         if (files.length === 0) return;
         setStatus(null);
-        const mode =
-            splitMode === "one_per_page"
-                ? { mode: "one_per_page" as const }
-                : { mode: "every_n" as const, n: Math.max(1, splitEveryN) };
         const outDir = outputDir || undefined;
 
         const totalExpected = splitPreviews.reduce((acc, p) => acc + p.parts.length, 0);
@@ -99,28 +104,52 @@ export function PdfSplitter({
         });
 
         let total = 0;
+        const warnings: string[] = [];
+        const reportCancelled = () => {
+            const text = total > 0 ? `Split cancelled after ${total} file${total !== 1 ? "s" : ""}.` : "Split cancelled.";
+            setStatus({ type: "info", text });
+            if (total > 0) onSplitComplete();
+        };
+        cancelRequested.current = false;
         try {
             for (const f of files) {
-                const paths = await invoke<string[]>("split_pdf", {
+                if (cancelRequested.current) {
+                    reportCancelled();
+                    return;
+                }
+                operationId.current = crypto.randomUUID();
+                const result = await invoke<SplitResult>("split_pdf", {
                     sourcePath: f.path,
                     outputDir: outDir ?? null,
                     mode,
+                    operationId: operationId.current,
                 });
-                total += paths.length;
+                total += result.parts.length;
+                warnings.push(...result.warnings.map((w) => `${f.name}: ${w}`));
             }
-            setStatus({
-                type: "success",
-                text: `Created ${total} file${total !== 1 ? "s" : ""}.`,
-            });
+            const text = `Created ${total} file${total !== 1 ? "s" : ""}.`;
+            setStatus(warnings.length > 0
+                ? { type: "info", text: [text, ...warnings].join("\n") }
+                : { type: "success", text });
             onSplitComplete();
             setSplitPreviews([]);
         } catch (e) {
-            setStatus({ type: "error", text: String(e) });
+            if (String(e) === "Operation cancelled") {
+                reportCancelled();
+            } else {
+                setStatus({ type: "error", text: String(e) });
+            }
         } finally {
+            operationId.current = null;
             unlisten();
             setProgress(null);
         }
-    }, [files, splitMode, splitEveryN, outputDir, setStatus, onSplitComplete, splitPreviews]);
+    }, [files, mode, outputDir, setStatus, onSplitComplete, splitPreviews]);
+
+    const cancelSplit = useCallback(() => {
+        cancelRequested.current = true;
+        if (operationId.current) invoke("cancel_operation", { operationId: operationId.current });
+    }, []);
 
     const pickOutputFolder = useCallback(async () => {
         const selected = await open({
@@ -220,7 +249,20 @@ export function PdfSplitter({
                                 style={{ width: 64 }}
                                 disabled={splitMode === "one_per_page"}
                             />
-                            <span>pages</span>
+                            <span>pages, overlapping</span>
+                            <input
+                                type="number"
+                                min={0}
+                                max={Math.max(0, splitEveryN - 1)}
+                                value={splitOverlap}
+                                onChange={(e) =>
+                                    setSplitOverlap(Math.max(0, Math.min(splitEveryN - 1, parseInt(e.target.value, 10) || 0)))
+                                }
+                                className="input"
+                                style={{ width: 64 }}
+                                disabled={splitMode === "one_per_page" || splitEveryN < 2}
+                                aria-label="Pages shared with the previous part"
+                            />
                             <label className="radio-label">
                                 <input
                                     type="radio"
@@ -230,6 +272,34 @@ export function PdfSplitter({
                                 />
                                 One file per page
                             </label>
+                            <label className="radio-label">
+                                <input
+                                    type="radio"
+                                    name="splitMode"
+                                    checked={splitMode === "ranges"}
+                                    onChange={() => setSplitMode("ranges")}
+                                />
+                                Page ranges
+                            </label>
+                            <input
+                                type="text"
+                                value={splitRanges}
+                                onChange={(e) => setSplitRanges(e.target.value)}
+                                placeholder="1-3, 7, 10-12"
+                                className="input"
+                                style={{ width: 160 }}
+                                disabled={splitMode !== "ranges"}
+                                aria-label="Page ranges, one file each"
+                            />
+                            <label className="radio-label">
+                                <input
+                                    type="radio"
+                                    name="splitMode"
+                                    checked={splitMode === "bookmarks"}
+                                    onChange={() => setSplitMode("bookmarks")}
+                                />
+                                At each top-level bookmark
+                            </label>
                         </div>
                     </section>
 
@@ -271,6 +341,9 @@ export function PdfSplitter({
                                     style={{ width: `${(progress.current / progress.total) * 100}%` }}
                                 />
                             </div>
+                            <button type="button" className="btn btn-secondary" style={{ marginTop: 8 }} onClick={cancelSplit}>
+                                Cancel
+                            </button>
                         </section>
                     )}
 
@@ -297,7 +370,10 @@ export function PdfSplitter({
                                         {!preview.error && (
                                             <ul style={{ marginTop: 4, paddingLeft: 18 }}>
                                                 {preview.parts.slice(0, 10).map((p, i) => (
-                                                    <li key={i}>{p.output_name} (pages {p.page_range})</li>
+                                                    <li key={i}>
+                                                        {p.output_name} (pages {p.page_range}
+                                                        {p.estimated_size != null && `, ~${(p.estimated_size / 1024).toFixed(0)} KB`})
+                                                    </li>
                                                 ))}
                                                 {preview.parts.length > 10 && (
                                                     <li>… and {preview.parts.length - 10} more</li>
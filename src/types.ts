@@ -1,22 +1,32 @@
 export interface FileEntry {
   path: string;
   name: string;
+  encrypted?: boolean | null;
 }
 
 export interface PreviewResult {
   preview_names: string[];
   placeholder_found: boolean;
   overwrite_warnings: string[];
+  internal_collisions: string[];
+  tokens: string[];
 }
 
 export interface RenameResult {
   renamed: number;
   failed: { path: string; error: string }[];
+  rolled_back: boolean;
 }
 
 export interface SplitPreviewItem {
   output_name: string;
   page_range: string;
+  estimated_size: number | null;
+}
+
+export interface SplitResult {
+  parts: { path: string; size: number }[];
+  warnings: string[];
 }
 
 export interface SplitPreviewResult {
@@ -34,11 +44,14 @@ export interface PageBoxes {
   bleed_box: string | null;
   trim_box: string | null;
   art_box: string | null;
+  missing_mediabox: boolean;
 }
 
 export interface PageMetadata {
   page_number: number;
   is_landscape: boolean;
+  rotation: number;
+  display_landscape: boolean;
 }
 
 export type PageAction =
@@ -78,7 +91,28 @@ export interface CompressionResult {
   original_size: number;
   compressed_size: number;
   success: boolean;
+  ratio: number;
+  grew: boolean;
+  kept_original: boolean;
+  accessibility_affected: boolean;
+  images_recompressed: number;
+  breakdown: { step: string; bytes_saved: number }[];
+  warnings: string[];
 }
+export interface MergeResult {
+  output_path: string;
+  page_counts: [string, number][];
+  page_count: number;
+  output_intents_conflict: boolean;
+  warnings: string[];
+  renamed_fields: [string, string][];
+}
+
+export interface OrganiseResult {
+  accessibility_affected: boolean;
+  warnings: string[];
+}
+
 export interface PdfDiagnosticResult {
   header: string;
   trailer: string;
@@ -87,6 +121,8 @@ export interface PdfDiagnosticResult {
 
 export interface PdfProperties {
   version: string;
+  header_version: string;
+  catalog_version: string | null;
   page_count: number;
   page_size: string;
   metadata: { [key: string]: string };
@@ -97,8 +133,98 @@ export interface PdfProperties {
   creator: string;
   fonts: string[];
   image_dpi: number[];
+  image_dpi_summary: { min: number; typical: number; max: number } | null;
   doc_dpi: number;
   colorspace: string;
   page_width: number;
   page_height: number;
+  language: string | null;
+  page_labels: string[];
+}
+
+export interface Capabilities {
+  version: string;
+  render: boolean;
+  barcode: boolean;
+  ocr: boolean;
+}
+
+export interface PageCoverage {
+  page_number: number;
+  coverage: number;
+  cyan: number;
+  magenta: number;
+  yellow: number;
+  black: number;
+}
+
+export interface PrintSettings {
+  max_image_dpi?: number | null;
+  image_quality?: number | null;
+  cmyk?: boolean | null;
+}
+
+export interface PrintReport {
+  applied: string[];
+  issues: string[];
+}
+
+export interface WatermarkOptions {
+  font_size?: number | null;
+  color?: [number, number, number] | null;
+  opacity?: number | null;
+  rotation?: number | null;
+  pages?: string | [number, number][] | null;
+}
+
+export type StampPosition = "top_left" | "top_right" | "bottom_left" | "bottom_right" | "center";
+
+export interface ImagePlacement {
+  position?: StampPosition;
+  margin?: number | null;
+  scale?: number | null;
+  opacity?: number | null;
+  pages?: string | [number, number][] | null;
+}
+
+export type PageNumberPosition =
+  | "header_left"
+  | "header_center"
+  | "header_right"
+  | "footer_left"
+  | "footer_center"
+  | "footer_right";
+
+export interface PageNumberOptions {
+  format?: string | null;
+  position?: PageNumberPosition;
+  start?: number | null;
+  font_size?: number | null;
+  skip?: string | [number, number][] | null;
+}
+
+export type RotationMode = "relative" | "absolute";
+
+export type EncryptionAlgorithm = "rc4_128" | "aes_128" | "aes_256";
+
+export type FormFieldType = "text" | "checkbox" | "radio" | "choice" | "button" | "signature";
+
+export interface FormField {
+  name: string;
+  field_type: FormFieldType;
+  value: string | null;
+  read_only: boolean;
+  options: string[];
+}
+
+export interface FormFillResult {
+  filled: number;
+  unmatched: string[];
+}
+
+export interface UpdateInfo {
+  revision: number;
+  offset: number;
+  size: number;
+  objects: number;
 }